@@ -0,0 +1,347 @@
+//! Ability definitions.
+//!
+//! Definitions are stored as a RON map from abilities to definitions:
+//!
+//! - `title`, `description` (a list of lines) and `cooldown`;
+//! - `range`: `(min: 1, max: 3)`, `max` may be omitted for an unlimited range;
+//! - `line_of_sight`: whether the target tile must be visible, `false` by default;
+//! - `target`: what must be in the target tile, see `Target`;
+//! - `area`: `Tile` (the default) or `Circle(radius)` around the target tile;
+//! - `effects`: what happens to every object in the area, see `Effect`.
+//!
+//! Objects in an area are affected if they could be targeted themselves,
+//! with a few exceptions: `Actor` abilities affect the actor's allies
+//! and `EmptyTile` abilities affect all agents.
+//!
+//! Abilities from `Custom("name")` entries are pure data.
+//! The built-in ones may also have engine code, like moving the actor
+//! for `Jump` or creating a bomb for `Bomb`, and must all be defined.
+
+use std::{collections::HashMap, fmt};
+
+use risc0_zkvm::sha::Digest;
+use serde::{Deserialize, Serialize};
+
+use crate::battle::{ability::Ability, data, effect::Lasting, Rounds, Strength, Weight};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Definition {
+    pub title: String,
+
+    #[serde(default)]
+    pub description: Vec<String>,
+
+    pub cooldown: Rounds,
+
+    #[serde(default)]
+    pub range: Range,
+
+    #[serde(default)]
+    pub line_of_sight: bool,
+
+    pub target: Target,
+
+    #[serde(default)]
+    pub area: Area,
+
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+}
+
+/// Distances from the actor to the target tile.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Range {
+    pub min: i32,
+    pub max: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Target {
+    /// The actor's own tile, the range is ignored.
+    Actor,
+
+    /// Any agent.
+    Agent,
+
+    /// An agent of the actor's team, including the actor itself.
+    Ally,
+
+    /// An agent of an opposing team.
+    Enemy,
+
+    /// Any blocking object: an agent, a boulder or a bomb.
+    Object,
+
+    EmptyTile,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Area {
+    #[default]
+    Tile,
+
+    Circle(i32),
+}
+
+impl Area {
+    pub fn radius(self) -> i32 {
+        match self {
+            Area::Tile => 0,
+            Area::Circle(radius) => radius,
+        }
+    }
+}
+
+/// What happens to an affected object.
+///
+/// `Knockback` moves any object, the rest only affect agents.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Effect {
+    Wound(Strength),
+    Heal(Strength),
+    Stun,
+
+    /// Pushes objects next to the actor (or the area's center) one tile away.
+    /// Single target abilities can't be used on objects that are too heavy.
+    Knockback(Weight),
+
+    /// Lasts for the given number of the target owner's turns.
+    Lasting(Lasting, Rounds),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Error {
+    UnknownAbility(Ability),
+    MissingDefinition,
+
+    /// The maximum distance is less than the minimum one.
+    BadRange,
+
+    OutOfRange(i32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownAbility(ability) => write!(f, "there's no ability '{}'", ability),
+            Error::MissingDefinition => write!(f, "built-in abilities must be defined"),
+            Error::BadRange => write!(f, "the maximum distance is less than the minimum"),
+            Error::OutOfRange(n) => write!(f, "{} is out of range", n),
+        }
+    }
+}
+
+/// Paths are like `Heal.effects[0]`.
+pub type ValidationError = data::ValidationError<Error>;
+
+pub type LoadError = data::LoadError<Error>;
+
+/// Abilities that have engine code.
+const BUILT_IN: [Ability; 21] = [
+    Ability::Knockback,
+    Ability::Club,
+    Ability::Jump,
+    Ability::LongJump,
+    Ability::Poison,
+    Ability::ExplodePush,
+    Ability::ExplodeDamage,
+    Ability::ExplodeFire,
+    Ability::ExplodePoison,
+    Ability::Bomb,
+    Ability::BombPush,
+    Ability::BombFire,
+    Ability::BombPoison,
+    Ability::BombDemonic,
+    Ability::Summon,
+    Ability::Vanish,
+    Ability::Dash,
+    Ability::Rage,
+    Ability::Heal,
+    Ability::GreatHeal,
+    Ability::Bloodlust,
+];
+
+/// Reads and validates the definitions.
+pub fn from_ron(s: &str) -> Result<Definitions, LoadError> {
+    let definitions = Definitions(ron::de::from_str(s)?);
+    definitions.validate().map_err(LoadError::Invalid)?;
+    Ok(definitions)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Definitions(pub HashMap<Ability, Definition>);
+
+impl Definitions {
+    pub fn get(&self, ability: &Ability) -> &Definition {
+        self.0
+            .get(ability)
+            .unwrap_or_else(|| panic!("No definition for {}", ability))
+    }
+
+    pub fn check(&self) -> Result<(), Error> {
+        data::first_error(self.errors())
+    }
+
+    /// Like `check`, but reports all the errors with their paths.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        data::to_result(self.errors())
+    }
+
+    pub fn check_known(&self, ability: &Ability) -> Result<(), Error> {
+        if self.0.contains_key(ability) {
+            Ok(())
+        } else {
+            Err(Error::UnknownAbility(ability.clone()))
+        }
+    }
+
+    fn sorted(&self) -> Vec<(&Ability, &Definition)> {
+        let mut definitions: Vec<_> = self.0.iter().collect();
+        definitions.sort_by_key(|(ability, _)| ability.to_string());
+        definitions
+    }
+
+    fn errors(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let mut report = |path: String, error| errors.push(ValidationError { path, error });
+        for ability in &BUILT_IN {
+            if !self.0.contains_key(ability) {
+                report(ability.to_string(), Error::MissingDefinition);
+            }
+        }
+        for (ability, definition) in self.sorted() {
+            let path = |s: &str| format!("{}.{}", ability, s);
+            // Fields with their values and minimal allowed values.
+            let mut ranges = vec![
+                ("cooldown".to_string(), definition.cooldown.0, 0),
+                ("range.min".to_string(), definition.range.min, 0),
+                ("area".to_string(), definition.area.radius(), 0),
+            ];
+            for (i, effect) in definition.effects.iter().enumerate() {
+                let field = format!("effects[{}]", i);
+                match *effect {
+                    Effect::Wound(n) | Effect::Heal(n) => ranges.push((field, n.0, 1)),
+                    Effect::Lasting(_, rounds) => ranges.push((field, rounds.0, 1)),
+                    Effect::Stun | Effect::Knockback(_) => {}
+                }
+            }
+            for (field, value, min) in ranges {
+                if value < min {
+                    report(path(&field), Error::OutOfRange(value));
+                }
+            }
+            if let Some(max) = definition.range.max {
+                if max < definition.range.min {
+                    report(path("range"), Error::BadRange);
+                }
+            }
+        }
+        errors
+    }
+
+    /// A hash of the definitions that doesn't depend on the iteration order of the map.
+    pub fn digest(&self) -> Digest {
+        data::digest(&self.sorted())
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn shipped() -> Definitions {
+    let s = include_str!("../../../../assets/abilities.ron");
+    from_ron(s).expect("Can't load the abilities")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::battle::{
+        ability::{Ability, Name},
+        effect::Lasting,
+        Rounds,
+    };
+
+    use super::{from_ron, shipped, Area, Effect, Error, LoadError, Target, ValidationError};
+
+    #[test]
+    fn test_shipped() {
+        let definitions = shipped();
+        assert_eq!(definitions.validate(), Ok(()));
+        let club = definitions.get(&Ability::Club);
+        assert_eq!(club.cooldown, Rounds(2));
+        assert_eq!(club.target, Target::Object);
+    }
+
+    #[test]
+    fn test_custom() {
+        let mut definitions = shipped();
+        let s = "#![enable(unwrap_newtypes, implicit_some)]
+        {
+            Custom(\"stun_shot\"): (
+                title: \"Stun Shot\",
+                cooldown: 3,
+                range: (min: 2, max: 4),
+                line_of_sight: true,
+                target: Enemy,
+                effects: [Stun, Lasting(Stun, 1)],
+            ),
+            Custom(\"healing_circle\"): (
+                title: \"Healing Circle\",
+                cooldown: 2,
+                target: Actor,
+                area: Circle(1),
+                effects: [Heal(1)],
+            ),
+        }";
+        let custom: super::Definitions = ron::de::from_str(s).unwrap();
+        definitions.0.extend(custom.0);
+        assert_eq!(definitions.validate(), Ok(()));
+        let stun_shot = definitions.get(&Ability::Custom(Name::from("stun_shot")));
+        assert_eq!(stun_shot.range.max, Some(4));
+        assert_eq!(
+            stun_shot.effects[1],
+            Effect::Lasting(Lasting::Stun, Rounds(1))
+        );
+        let healing_circle = definitions.get(&Ability::Custom("healing_circle".into()));
+        assert_eq!(healing_circle.area, Area::Circle(1));
+        assert_eq!(healing_circle.range.max, None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let s = "#![enable(unwrap_newtypes, implicit_some)]
+        {
+            Custom(\"bad\"): (
+                title: \"Bad\",
+                cooldown: -1,
+                range: (min: 3, max: 2),
+                target: Enemy,
+                effects: [Wound(0)],
+            ),
+        }";
+        let errors = match from_ron(s) {
+            Err(LoadError::Invalid(errors)) => errors,
+            result => panic!("Unexpected result: {:?}", result),
+        };
+        assert_eq!(errors.len(), 24);
+        let error = |path: &str, error| ValidationError {
+            path: path.into(),
+            error,
+        };
+        assert_eq!(errors[0], error("Knockback", Error::MissingDefinition));
+        assert_eq!(
+            errors[21..],
+            [
+                error("bad.cooldown", Error::OutOfRange(-1)),
+                error("bad.effects[0]", Error::OutOfRange(0)),
+                error("bad.range", Error::BadRange),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let s = "{Club: (title: \"Club\", cooldown: 2, target: Object, damage: 1)}";
+        assert!(matches!(from_ron(s), Err(LoadError::Ron(_))));
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
         ability::Ability,
         command::{self, Command},
         effect,
-        movement::Path,
+        movement::{Path, Pathfinder},
         state, Id, PlayerId, State,
     },
     map::{self, distance_hex},
@@ -11,10 +11,7 @@ use crate::{
 
 use super::{
     ability::{RechargeableAbility, Status},
-    check::{
-        check_agent_can_attack, check_agent_can_move, check_command_attack, check_max_distance,
-    },
-    PosHex,
+    check::{check, check_command_attack},
 };
 
 #[allow(dead_code)]
@@ -57,14 +54,12 @@ impl Ai {
     }
 
     fn trim(&mut self, ids: &[usize]) {
-        ids.iter().for_each(|i| {
+        ids.iter().rev().for_each(|i| {
             self.obj_ids.remove(*i);
         });
     }
 
     fn try_throw_bomb(&self, state: &State, agent_id: Id) -> Option<Command> {
-        let agent_pos = state.pos(&agent_id).0;
-
         for target_id in state::enemy_agent_ids(state, self.id) {
             let target_pos = state.pos(&target_id).0;
             for dir in map::dirs().collect::<Vec<_>>() {
@@ -79,9 +74,7 @@ impl Ai {
                     ability: Ability::BombDemonic,
                 }
                 .into();
-                if check_agent_can_attack(state, agent_id).is_ok()
-                    && check_max_distance(agent_pos, pos, 3).is_ok()
-                {
+                if check(state, &command).is_ok() {
                     return Some(command);
                 }
             }
@@ -96,7 +89,7 @@ impl Ai {
             ability: Ability::Summon,
         }
         .into();
-        if check_agent_can_attack(state, agent_id).is_ok() {
+        if check(state, &command).is_ok() {
             return Some(command);
         }
         None
@@ -125,7 +118,7 @@ impl Ai {
                 ability: Ability::Bloodlust,
             }
             .into();
-            if check_agent_can_attack(state, agent_id).is_ok() {
+            if check(state, &command).is_ok() {
                 return Some(command);
             }
         }
@@ -147,8 +140,6 @@ impl Ai {
     }
 
     fn try_to_move_closer(&mut self, state: &State, id: Id) -> Option<Path> {
-        let agent = state.agent(&id);
-        let agent_move_point = agent.move_points.0;
         let agent_pos = state.pos(&id).0;
 
         let enemys = state::enemy_agent_ids(state, self.id);
@@ -164,151 +155,33 @@ impl Ai {
             }
         }
 
-        let radius = state.map().radius();
+        let move_points = state::move_points(state, &id);
+        let mut pathfinder = Pathfinder::new(state.map().radius());
+        pathfinder.fill_map(state, id);
 
         let mut target_pos = None;
-        let mut nearest = 0;
-
-        match (to_pos.q - agent_pos.q > 0, to_pos.r - agent_pos.r > 0) {
-            (true, true) => {
-                for i in 0..=agent_move_point {
-                    let q = agent_pos.q + i;
-                    if q > radius {
-                        break;
-                    }
-
-                    for j in 0..=agent_move_point {
-                        let r = agent_pos.r + j;
-                        if r > radius || q + r > radius || q + r < -radius {
-                            break;
-                        }
-
-                        let pos = PosHex { q, r };
-
-                        if !state::is_tile_blocked_not_check_inboard(state, pos) {
-                            if target_pos.is_some() {
-                                let d = distance_hex(pos, to_pos);
-                                if d < nearest {
-                                    target_pos = Some(pos);
-                                    nearest = d;
-                                }
-                            } else {
-                                nearest = distance_hex(pos, to_pos);
-                                target_pos = Some(pos);
-                            }
-                        }
-                    }
-                }
-            }
-            (true, false) => {
-                for i in 0..=agent_move_point {
-                    let q = agent_pos.q + i;
-                    if q > radius {
-                        break;
-                    }
-
-                    for j in 0..=agent_move_point {
-                        let r = agent_pos.r - j;
-                        if r < -radius || q + r > radius || q + r < -radius {
-                            break;
-                        }
-                        let pos = PosHex { q, r };
-
-                        if !state::is_tile_blocked_not_check_inboard(state, pos) {
-                            if target_pos.is_some() {
-                                let d = distance_hex(pos, to_pos);
-                                if d < nearest {
-                                    target_pos = Some(pos);
-                                    nearest = d;
-                                }
-                            } else {
-                                nearest = distance_hex(pos, to_pos);
-                                target_pos = Some(pos);
-                            }
-                        }
-                    }
-                }
-            }
-            (false, true) => {
-                for i in 0..=agent_move_point {
-                    let q = agent_pos.q - i;
-                    if q < -radius {
-                        break;
-                    }
-
-                    for j in 0..=agent_move_point {
-                        let r = agent_pos.r + j;
-                        if r > radius || q + r > radius || q + r < -radius {
-                            break;
-                        }
-                        let pos = PosHex { q, r };
-
-                        if !state::is_tile_blocked_not_check_inboard(state, pos) {
-                            if target_pos.is_some() {
-                                let d = distance_hex(pos, to_pos);
-                                if d < nearest {
-                                    target_pos = Some(pos);
-                                    nearest = d;
-                                }
-                            } else {
-                                nearest = distance_hex(pos, to_pos);
-                                target_pos = Some(pos);
-                            }
-                        }
-                    }
-                }
+        let mut nearest = min_distance;
+        for pos in pathfinder.map().iter() {
+            if pathfinder.map().tile(pos).cost() > move_points {
+                continue;
             }
-            (false, false) => {
-                for i in 0..=agent_move_point {
-                    let q = agent_pos.q - i;
-                    if q < -radius {
-                        break;
-                    }
-
-                    for j in 0..=agent_move_point {
-                        let r = agent_pos.r - j;
-                        if r < -radius || q + r > radius || q + r < -radius {
-                            break;
-                        }
-                        let pos = PosHex { q, r };
-
-                        if !state::is_tile_blocked_not_check_inboard(state, pos) {
-                            if target_pos.is_some() {
-                                let d = distance_hex(pos, to_pos);
-                                if d < nearest {
-                                    target_pos = Some(pos);
-                                    nearest = d;
-                                }
-                            } else {
-                                nearest = distance_hex(pos, to_pos);
-                                target_pos = Some(pos);
-                            }
-                        }
-                    }
-                }
+            let distance = distance_hex(pos, to_pos);
+            if distance < nearest {
+                nearest = distance;
+                target_pos = Some(pos);
             }
-        };
-
-        if target_pos.is_none() {
-            return None;
-        }
-
-        let path = Path::new(vec![agent_pos, target_pos.unwrap()]);
-        let cost = path.cost_for(state, id);
-        if cost.0 > agent_move_point {
-            return None;
         }
 
-        Some(path)
+        pathfinder.path(target_pos?)
     }
 
     pub fn try_to_move(&mut self, state: &State, agent_id: Id) -> Option<Command> {
         let path_result = self.try_to_move_closer(state, agent_id);
         if let Some(path) = path_result {
-            let command = command::MoveTo { id: agent_id, path };
+            let command = command::MoveTo { id: agent_id, path }.into();
 
-            if check_agent_can_move(state, command.id).is_ok() {
-                return Some(command.into());
+            if check(state, &command).is_ok() {
+                return Some(command);
             }
         }
 
@@ -370,3 +243,49 @@ impl Ai {
         Some(command::EndTurn.into())
     }
 }
+
+/// One AI for every player but the human one (player 0).
+#[derive(Debug, Clone)]
+pub struct Ais(Vec<Ai>);
+
+impl Ais {
+    pub fn new(players_count: i32) -> Self {
+        Self((1..players_count).map(|id| Ai::new(PlayerId(id))).collect())
+    }
+
+    pub fn get_mut(&mut self, player_id: PlayerId) -> &mut Ai {
+        assert_ne!(player_id, PlayerId(0), "Player 0 is the human one");
+        &mut self.0[player_id.0 as usize - 1]
+    }
+}
+
+#[cfg(not(feature = "event"))]
+#[cfg(test)]
+mod tests {
+    use crate::{
+        battle::{
+            ability::{definitions::Target, Ability},
+            state, PlayerId,
+        },
+        map::PosHex,
+    };
+
+    use super::Ai;
+
+    #[test]
+    fn test_ability_commands_follow_definitions() {
+        let summoner_pos = PosHex { q: 0, r: 0 };
+        let mut state = state::state_with_objects(&[
+            (1, "imp_summoner", summoner_pos),
+            (1, "imp", PosHex { q: 1, r: 0 }),
+            (0, "swordsman", PosHex { q: -3, r: 0 }),
+        ]);
+        state.set_player_id(PlayerId(1));
+        let id = state::agent_ids_at(&state, summoner_pos)[0];
+        let ai = Ai::new(PlayerId(1));
+        assert!(ai.try_bloodlust_imp(&state, id).is_some());
+        let definitions = &mut state.ability_definitions.0;
+        definitions.get_mut(&Ability::Bloodlust).unwrap().target = Target::Enemy;
+        assert!(ai.try_bloodlust_imp(&state, id).is_none());
+    }
+}
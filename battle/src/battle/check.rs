@@ -1,15 +1,20 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
     battle::{
         self,
-        ability::{self, Ability},
+        ability::{
+            self,
+            definitions::{self, Area, Definition, Target},
+            Ability,
+        },
         command::{self, Command},
-        state, Attacks, Id, Jokers, Moves, PushStrength, State, Weight,
+        effect::Lasting,
+        state, Attacks, Id, Jokers, Moves, PushStrength, State,
     },
     map::{self, PosHex},
 };
 
-use super::BAD_ID;
-
 pub fn check(state: &State, command: &Command) -> Result<(), Error> {
     if state.battle_result().is_some() {
         return Err(Error::BattleEnded);
@@ -23,7 +28,17 @@ pub fn check(state: &State, command: &Command) -> Result<(), Error> {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// Validates the command no matter which player issues it.
+///
+/// Objects can only be created by the scenario setup, so `Create` commands are rejected.
+pub fn check_strict(state: &State, command: &Command) -> Result<(), Error> {
+    if let Command::Create(_) = command {
+        return Err(Error::CanNotCreateObjects);
+    }
+    check(state, command)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Error {
     NotEnoughMovePoints,
     NotEnoughStrength,
@@ -31,7 +46,9 @@ pub enum Error {
     BadTargetId,
     BadTargetType,
     TileIsBlocked,
+    TileIsImpassable,
     DistanceIsTooBig,
+    NoLineOfSight,
     DistanceIsTooSmall,
     BadDistance,
     CanNotCommandEnemyAgents,
@@ -41,44 +58,52 @@ pub enum Error {
     NoSuchAbility,
     NoTarget,
     BadPos,
+    BadPath,
     BadActorType,
     BattleEnded,
-}
+    CanNotCreateObjects,
+    UnknownPrototype,
+    AgentIsRooted,
 
-const BOMB_THROW_DISTANCE_MAX: i32 = 3;
+    /// A taunted agent can only attack the taunter.
+    AgentIsTaunted,
+}
 
 fn check_command_move_to(state: &State, command: &command::MoveTo) -> Result<(), Error> {
     let agent = try_get_actor(state, command.id)?;
-    let agent_player_id = state.belongs_to(&command.id).0;
-    if agent_player_id != state.player_id() {
-        return Err(Error::CanNotCommandEnemyAgents);
-    }
+    check_agent_belongs_to_correct_player(state, command.id)?;
 
     // Check that the agent can move
     if agent.moves == Moves(0) && agent.jokers == Jokers(0) {
         return Err(Error::NotEnoughMoves);
     }
+    check_not_rooted(state, command.id)?;
 
-    // TODO : Add this constraint
-    // assert_eq!(command.path.tiles().len(), 2);
+    if command.path.tiles().len() < 2 {
+        return Err(Error::BadPath);
+    }
+    if command.path.from() != try_get_actor_pos(state, command.id)? {
+        return Err(Error::BadPos);
+    }
+    for step in command.path.steps() {
+        if map::distance_hex(step.from, step.to) != 1 {
+            return Err(Error::BadPath);
+        }
+        check_not_blocked_and_is_inboard(state, step.to)?;
+        check_is_tile_passable(state, command.id, step.to)?;
+    }
 
-    let cost = map::distance_hex(command.path.from(), command.path.to());
-    if cost > agent.move_points.0 {
+    let cost = command.path.cost_for(state, command.id);
+    if cost > state::move_points(state, &command.id) {
         return Err(Error::NotEnoughMovePoints);
     }
-
-    // for step in command.path.steps() {
-    //     check_not_blocked_and_is_inboard(state, step.to)?;
-    // }
-
-    // let cost = command.path.cost_for(state, command.id);
-    // if cost > agent.move_points {
-    //     return Err(Error::NotEnoughMovePoints);
-    // }
     Ok(())
 }
 
 fn check_command_create(state: &State, command: &command::Create) -> Result<(), Error> {
+    if state.prototype_for(&command.prototype).is_none() {
+        return Err(Error::UnknownPrototype);
+    }
     check_not_blocked_and_is_inboard(state, command.pos)
 }
 
@@ -90,15 +115,20 @@ pub fn check_command_attack(state: &State, command: &command::Attack) -> Result<
         Some(pos) => pos.0,
         None => return Err(Error::BadTargetId),
     };
-    let attacker_pos = state.pos(&command.attacker_id).0;
-    let attacker_agent = state.agent(&command.attacker_id);
+    let attacker_agent = try_get_actor(state, command.attacker_id)?;
+    let attacker_pos = try_get_actor_pos(state, command.attacker_id)?;
     check_max_distance(attacker_pos, target_pos, attacker_agent.attack_distance)?;
-
-    let attacker_player_id = state.belongs_to(&command.attacker_id).0;
-    if attacker_player_id != state.player_id() {
-        return Err(Error::CanNotCommandEnemyAgents);
+    if attacker_agent.attack_distance > 1 {
+        check_line_of_sight(state, attacker_pos, target_pos)?;
+    }
+    if let Some(taunter) = state::taunter(state, &command.attacker_id) {
+        if taunter != command.target_id {
+            return Err(Error::AgentIsTaunted);
+        }
     }
 
+    check_agent_belongs_to_correct_player(state, command.attacker_id)?;
+
     // Check that the agent can attack
     if attacker_agent.attacks == Attacks(0) && attacker_agent.jokers == Jokers(0) {
         return Err(Error::NotEnoughAttacks);
@@ -112,140 +142,79 @@ fn check_command_end_turn(_: &State, _: &command::EndTurn) -> Result<(), Error>
 }
 
 fn check_command_use_ability(state: &State, command: &command::UseAbility) -> Result<(), Error> {
-    check_agent_belongs_to_correct_player(state, command.id)?;
     check_agent_can_attack(state, command.id)?;
+    check_agent_belongs_to_correct_player(state, command.id)?;
     check_agent_ability_ready(state, command.id, &command.ability)?;
+    let definition = state.ability_definition(&command.ability);
+    check_ability_target(state, command.id, command.pos, definition)?;
     match command.ability {
-        Ability::Knockback => check_ability_knockback(state, command.id, command.pos),
-        Ability::Club => check_ability_club(state, command.id, command.pos),
-        Ability::Jump => check_ability_jump(state, command.id, command.pos, 2),
-        Ability::LongJump => check_ability_jump(state, command.id, command.pos, 3),
-        Ability::Poison => check_ability_poison(state, command.id, command.pos),
-        Ability::Bomb
-        | Ability::BombPush
-        | Ability::BombFire
-        | Ability::BombPoison
-        | Ability::BombDemonic => check_ability_bomb_throw(state, command.id, command.pos),
-        Ability::Summon => check_ability_summon(state, command.id, command.pos),
-        Ability::Vanish => check_ability_vanish(state, command.id, command.pos),
-        Ability::Dash => check_ability_dash(state, command.id, command.pos),
-        Ability::Rage => check_ability_rage(state, command.id, command.pos),
-        Ability::Heal | Ability::GreatHeal => check_ability_heal(state, command.id, command.pos),
-        Ability::Bloodlust => check_ability_bloodlust(state, command.id, command.pos),
-        Ability::ExplodePush
-        | Ability::ExplodeDamage
-        | Ability::ExplodeFire
-        | Ability::ExplodePoison => check_ability_explode(state, command.id, command.pos),
-    }
-}
-
-fn check_ability_knockback(state: &State, id: Id, pos: PosHex) -> Result<(), Error> {
-    let strength = PushStrength(Weight::Normal);
-    let selected_pos = state.pos(&id).0;
-    check_min_distance(selected_pos, pos, 1)?;
-    check_max_distance(selected_pos, pos, 1)?;
-    let target_id = match state::blocker_id_at_opt(state, pos) {
-        Some(id) => id,
-        None => return Err(Error::NoTarget),
-    };
-    let target_weight = state.blocker(&target_id).weight;
-    if !strength.can_push(target_weight) {
-        return Err(Error::NotEnoughStrength);
-    }
-    Ok(())
-}
-
-fn check_ability_jump(state: &State, id: Id, pos: PosHex, max_distance: i32) -> Result<(), Error> {
-    let agent_pos = state.pos(&id).0;
-    let dist = map::distance_hex(agent_pos, pos);
-    if dist < 2 {
-        return Err(Error::DistanceIsTooSmall);
-    }
-    if dist > max_distance {
-        return Err(Error::DistanceIsTooBig);
+        Ability::Jump | Ability::LongJump | Ability::Dash => {
+            check_not_rooted(state, command.id)?;
+            check_is_tile_passable(state, command.id, command.pos)
+        }
+        _ => Ok(()),
     }
-    check_not_blocked_and_is_inboard(state, pos)?;
-    Ok(())
 }
 
-fn check_ability_club(state: &State, id: Id, pos: PosHex) -> Result<(), Error> {
-    let selected_pos = state.pos(&id).0;
-    if map::distance_hex(selected_pos, pos) != 1 {
-        return Err(Error::BadDistance);
-    }
-    if state::get_id_by_pos(state, pos).0 == BAD_ID {
-        return Err(Error::NoTarget);
-    }
-    Ok(())
-}
-
-fn check_ability_poison(state: &State, id: Id, pos: PosHex) -> Result<(), Error> {
-    let selected_pos = state.pos(&id).0;
-    let dist = map::distance_hex(selected_pos, pos);
-    if dist < 1 {
-        return Err(Error::DistanceIsTooSmall);
+fn check_ability_target(
+    state: &State,
+    id: Id,
+    pos: PosHex,
+    definition: &Definition,
+) -> Result<(), Error> {
+    let agent_pos = try_get_actor_pos(state, id)?;
+    if definition.target != Target::Actor {
+        check_min_distance(agent_pos, pos, definition.range.min)?;
+        if let Some(max) = definition.range.max {
+            check_max_distance(agent_pos, pos, max)?;
+        }
     }
-    if dist > 3 {
-        return Err(Error::DistanceIsTooBig);
+    check_is_inboard(state, pos)?;
+    match definition.target {
+        Target::Actor => check_object_pos(state, id, pos)?,
+        Target::EmptyTile => check_is_tile_blocked(state, pos)?,
+        Target::Object => {
+            let target_id = state::blocker_id_at_opt(state, pos).ok_or(Error::NoTarget)?;
+            check_can_push(state, target_id, definition)?;
+        }
+        Target::Agent => {
+            state::agent_id_at_opt(state, pos).ok_or(Error::NoTarget)?;
+        }
+        Target::Ally | Target::Enemy => {
+            let target_id = state::agent_id_at_opt(state, pos).ok_or(Error::NoTarget)?;
+            let player_id = state.belongs_to(&id).0;
+            let target_player_id = state.belongs_to(&target_id).0;
+            let is_ally = state::are_allies(state, player_id, target_player_id);
+            if is_ally != (definition.target == Target::Ally) {
+                return Err(Error::BadTargetType);
+            }
+        }
     }
-    if state::blocker_id_at_opt(state, pos).is_none() {
-        return Err(Error::NoTarget);
+    if definition.line_of_sight {
+        check_line_of_sight(state, agent_pos, pos)?;
     }
     Ok(())
 }
 
-fn check_ability_explode(state: &State, id: Id, pos: PosHex) -> Result<(), Error> {
-    check_object_pos(state, id, pos)
-}
-
-fn check_ability_bomb_throw(state: &State, id: Id, pos: PosHex) -> Result<(), Error> {
-    let agent_pos = state.pos(&id).0;
-    check_max_distance(agent_pos, pos, BOMB_THROW_DISTANCE_MAX)?;
-    check_not_blocked_and_is_inboard(state, pos)
-}
-
-fn check_ability_summon(state: &State, id: Id, pos: PosHex) -> Result<(), Error> {
-    check_object_pos(state, id, pos)
-}
-
-fn check_ability_vanish(state: &State, id: Id, pos: PosHex) -> Result<(), Error> {
-    // TODO : Check agent 'is_some' ?
-    if state.parts().agent.get(&id).is_some() {
-        return Err(Error::BadActorType);
-    }
-    let actor_pos = match state.parts().pos.get(&id) {
-        Some(pos) => pos.0,
-        None => return Err(Error::BadActorType),
-    };
-    if pos != actor_pos {
-        return Err(Error::BadPos);
+fn check_not_rooted(state: &State, id: Id) -> Result<(), Error> {
+    if state::has_lasting_effect(state, &id, Lasting::Rooted) {
+        return Err(Error::AgentIsRooted);
     }
     Ok(())
 }
 
-fn check_ability_dash(state: &State, id: Id, pos: PosHex) -> Result<(), Error> {
-    let agent_pos = state.pos(&id).0;
-    check_max_distance(agent_pos, pos, 1)?;
-    check_not_blocked_and_is_inboard(state, pos)
-}
-
-fn check_ability_rage(state: &State, id: Id, pos: PosHex) -> Result<(), Error> {
-    check_object_pos(state, id, pos)
-}
-
-fn check_ability_heal(state: &State, id: Id, pos: PosHex) -> Result<(), Error> {
-    let agent_pos = state.pos(&id).0;
-    check_max_distance(agent_pos, pos, 1)?;
-    if state::agent_id_at_opt(state, pos).is_none() {
-        return Err(Error::NoTarget);
+/// Single target knockbacks must be able to move the target.
+fn check_can_push(state: &State, target_id: Id, definition: &Definition) -> Result<(), Error> {
+    if definition.area != Area::Tile {
+        return Ok(());
     }
-    Ok(())
-}
-
-fn check_ability_bloodlust(state: &State, _id: Id, pos: PosHex) -> Result<(), Error> {
-    // TODO: check that the target belongs to the same player
-    if state::agent_id_at_opt(state, pos).is_none() {
-        return Err(Error::NoTarget);
+    let target_weight = state.blocker(&target_id).weight;
+    for effect in &definition.effects {
+        if let definitions::Effect::Knockback(weight) = *effect {
+            if !PushStrength(weight).can_push(target_weight) {
+                return Err(Error::NotEnoughStrength);
+            }
+        }
     }
     Ok(())
 }
@@ -257,12 +226,22 @@ pub fn try_get_actor(state: &State, id: Id) -> Result<&battle::component::Agent,
     }
 }
 
+fn try_get_actor_pos(state: &State, id: Id) -> Result<PosHex, Error> {
+    match state.parts().pos.get(&id) {
+        Some(pos) => Ok(pos.0),
+        None => Err(Error::BadActorId),
+    }
+}
+
 fn check_agent_ability_ready(
     state: &State,
     id: Id,
     expected_ability: &Ability,
 ) -> Result<(), Error> {
-    let abilities = &state.abilities(&id).0;
+    let abilities = match state.parts().abilities.get(&id) {
+        Some(abilities) => &abilities.0,
+        None => return Err(Error::NoSuchAbility),
+    };
 
     for ability in abilities {
         if ability.ability == *expected_ability {
@@ -278,7 +257,10 @@ fn check_agent_ability_ready(
 }
 
 fn check_agent_belongs_to_correct_player(state: &State, id: Id) -> Result<(), Error> {
-    let agent_player_id = state.belongs_to(&id).0;
+    let agent_player_id = match state.parts().belongs_to.get(&id) {
+        Some(belongs_to) => belongs_to.0,
+        None => return Err(Error::BadActorId),
+    };
     if agent_player_id != state.player_id() {
         return Err(Error::CanNotCommandEnemyAgents);
     }
@@ -317,6 +299,13 @@ pub fn check_max_distance(from: PosHex, to: PosHex, max: i32) -> Result<(), Erro
     Ok(())
 }
 
+pub fn check_line_of_sight(state: &State, from: PosHex, to: PosHex) -> Result<(), Error> {
+    if !state::is_in_line_of_sight(state, from, to) {
+        return Err(Error::NoLineOfSight);
+    }
+    Ok(())
+}
+
 fn check_not_blocked_and_is_inboard(state: &State, pos: PosHex) -> Result<(), Error> {
     check_is_inboard(state, pos)?;
     check_is_tile_blocked(state, pos)
@@ -338,6 +327,13 @@ fn check_is_tile_blocked(state: &State, pos: PosHex) -> Result<(), Error> {
     Ok(())
 }
 
+fn check_is_tile_passable(state: &State, id: Id, pos: PosHex) -> Result<(), Error> {
+    if !state::is_tile_passable_for(state, id, pos) {
+        return Err(Error::TileIsImpassable);
+    }
+    Ok(())
+}
+
 fn check_object_pos(state: &State, id: Id, expected_pos: PosHex) -> Result<(), Error> {
     let real_pos = state.pos(&id).0;
     if real_pos != expected_pos {
@@ -345,3 +341,263 @@ fn check_object_pos(state: &State, id: Id, expected_pos: PosHex) -> Result<(), E
     }
     Ok(())
 }
+
+#[cfg(not(feature = "event"))]
+#[cfg(test)]
+mod tests {
+    use crate::{
+        battle::{
+            ability::{
+                definitions::{Definition, Effect, Range, Target},
+                Ability,
+            },
+            check::{check, check_line_of_sight, Error},
+            command::{self, Command},
+            component::{Abilities, Effects},
+            effect::{Duration, Lasting, Timed},
+            movement::{MovePoints, Path},
+            scenario::Scenario,
+            state, Dodge, Id, Phase, Rounds, State, TileType, Weight,
+        },
+        map::{dirs, Dir, PosHex},
+        utils::SimpleRng,
+    };
+
+    fn free_plain_neighbor(state: &State, pos: PosHex) -> PosHex {
+        dirs()
+            .map(|dir| Dir::get_neighbor_pos(pos, dir))
+            .find(|&pos| {
+                state.map().is_inboard(pos)
+                    && !state::is_tile_blocked(state, pos)
+                    && state.map().tile(pos) == TileType::Plain
+            })
+            .unwrap()
+    }
+
+    fn move_to(id: Id, tiles: Vec<PosHex>) -> Command {
+        command::MoveTo {
+            id,
+            path: Path::new(tiles),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_create_unknown_prototype() {
+        let (state, _) = state::state_with_swordsman();
+        let command = command::Create {
+            prototype: "ghost".into(),
+            pos: PosHex { q: 0, r: 0 },
+            owner: None,
+        };
+        assert_eq!(check(&state, &command.into()), Err(Error::UnknownPrototype));
+    }
+
+    #[test]
+    fn test_bloodlust_targets_allies() {
+        let pos = PosHex { q: 0, r: 0 };
+        let ally_pos = PosHex { q: 1, r: 0 };
+        let enemy_pos = PosHex { q: -1, r: 0 };
+        let mut state = state::state_with_objects(&[
+            (0, "swordsman", pos),
+            (0, "swordsman", ally_pos),
+            (1, "imp", enemy_pos),
+        ]);
+        let id = state::agent_ids_at(&state, pos)[0];
+        let abilities = Abilities(vec![Ability::Bloodlust.into()]);
+        state.parts.abilities.insert(id, abilities);
+        let command = |pos| {
+            let ability = Ability::Bloodlust;
+            command::UseAbility { id, pos, ability }.into()
+        };
+        assert_eq!(check(&state, &command(ally_pos)), Ok(()));
+        let error = Err(Error::BadTargetType);
+        assert_eq!(check(&state, &command(enemy_pos)), error);
+    }
+
+    #[test]
+    fn test_move_to_single_step() {
+        let (state, id) = state::state_with_swordsman();
+        let pos = state.pos(&id).0;
+        let next = free_plain_neighbor(&state, pos);
+        assert_eq!(check(&state, &move_to(id, vec![pos, next])), Ok(()));
+    }
+
+    #[test]
+    fn test_move_to_bad_paths() {
+        let (state, id) = state::state_with_swordsman();
+        let pos = state.pos(&id).0;
+        let next = free_plain_neighbor(&state, pos);
+        let far = PosHex {
+            q: pos.q + 2 * (next.q - pos.q),
+            r: pos.r + 2 * (next.r - pos.r),
+        };
+        assert_eq!(check(&state, &move_to(id, vec![pos])), Err(Error::BadPath));
+        assert_eq!(
+            check(&state, &move_to(id, vec![pos, far])),
+            Err(Error::BadPath)
+        );
+        assert_eq!(
+            check(&state, &move_to(id, vec![next, pos])),
+            Err(Error::BadPos)
+        );
+        assert_eq!(
+            check(&state, &move_to(id, vec![pos, next, pos])),
+            Err(Error::TileIsBlocked)
+        );
+    }
+
+    #[test]
+    fn test_move_to_cost() {
+        let (mut state, id) = state::state_with_swordsman();
+        let pos = state.pos(&id).0;
+        let next = free_plain_neighbor(&state, pos);
+        state.map.set_tile(next, TileType::Rocks);
+        assert_eq!(
+            check(&state, &move_to(id, vec![pos, next])),
+            Err(Error::NotEnoughMovePoints)
+        );
+    }
+
+    #[test]
+    fn test_move_to_water() {
+        let (mut state, id) = state::state_with_swordsman();
+        let pos = state.pos(&id).0;
+        let next = free_plain_neighbor(&state, pos);
+        state.map.set_tile(next, TileType::Water);
+        assert_eq!(
+            check(&state, &move_to(id, vec![pos, next])),
+            Err(Error::TileIsImpassable)
+        );
+        state.parts.blocker.get_mut(&id).unwrap().weight = Weight::Heavy;
+        assert_eq!(check(&state, &move_to(id, vec![pos, next])), Ok(()));
+    }
+
+    #[test]
+    fn test_line_of_sight_and_cover() {
+        let from = PosHex { q: -3, r: 0 };
+        let to = PosHex { q: 0, r: 0 };
+        let state_with_rocks = |q| {
+            let mut scenario = Scenario::default();
+            scenario.tiles.insert(PosHex { q, r: 0 }, TileType::Rocks);
+            state::state_with_scenario(scenario, &mut SimpleRng::seed_from_u32(0))
+        };
+        let state = state_with_rocks(-1);
+        assert_eq!(check_line_of_sight(&state, from, to), Ok(()));
+        assert_eq!(state::cover(&state, from, to), Dodge(1));
+        assert_eq!(state::cover(&state, to, from), Dodge(0));
+        let mut state = state_with_rocks(-2);
+        assert_eq!(
+            check_line_of_sight(&state, from, to),
+            Err(Error::NoLineOfSight)
+        );
+        assert_eq!(state::cover(&state, from, to), Dodge(0));
+        // Only the tiles' properties matter, not their types.
+        state.tiles.0.get_mut(&TileType::Rocks).unwrap().obstructs = false;
+        assert_eq!(check_line_of_sight(&state, from, to), Ok(()));
+    }
+
+    #[test]
+    fn test_custom_stun_shot() {
+        let pos = PosHex { q: 0, r: 0 };
+        let ally = PosHex { q: 2, r: 0 };
+        let near_enemy = PosHex { q: -1, r: 0 };
+        let far_enemy = PosHex { q: 0, r: -3 };
+        let hidden_enemy = PosHex { q: -3, r: 0 };
+        let mut state = state::state_with_objects(&[
+            (0, "swordsman", pos),
+            (0, "swordsman", ally),
+            (1, "imp", near_enemy),
+            (1, "imp", far_enemy),
+            (1, "imp", hidden_enemy),
+        ]);
+        let ability = Ability::Custom("stun_shot".into());
+        let definition = Definition {
+            title: "Stun Shot".into(),
+            description: Vec::new(),
+            cooldown: Rounds(3),
+            range: Range {
+                min: 2,
+                max: Some(4),
+            },
+            line_of_sight: true,
+            target: Target::Enemy,
+            area: Default::default(),
+            effects: vec![Effect::Stun],
+        };
+        state
+            .ability_definitions
+            .0
+            .insert(ability.clone(), definition);
+        let id = state::agent_ids_at(&state, pos)[0];
+        let abilities = Abilities(vec![ability.clone().into()]);
+        state.parts.abilities.insert(id, abilities);
+        let use_at = |pos| {
+            let ability = ability.clone();
+            command::UseAbility { id, pos, ability }.into()
+        };
+        assert_eq!(check(&state, &use_at(far_enemy)), Ok(()));
+        assert_eq!(
+            check(&state, &use_at(near_enemy)),
+            Err(Error::DistanceIsTooSmall)
+        );
+        assert_eq!(
+            check(&state, &use_at(hidden_enemy)),
+            Err(Error::NoLineOfSight)
+        );
+        assert_eq!(check(&state, &use_at(ally)), Err(Error::BadTargetType));
+        let empty = PosHex { q: 0, r: 2 };
+        assert_eq!(check(&state, &use_at(empty)), Err(Error::NoTarget));
+    }
+
+    #[test]
+    fn test_lasting_effects_restrictions() {
+        let pos = PosHex { q: 0, r: 0 };
+        let next = PosHex { q: 0, r: 1 };
+        let taunter_pos = PosHex { q: 1, r: 0 };
+        let other_pos = PosHex { q: -1, r: 0 };
+        let mut state = state::state_with_objects(&[
+            (0, "swordsman", pos),
+            (1, "imp", taunter_pos),
+            (1, "imp", other_pos),
+        ]);
+        let id = state::agent_ids_at(&state, pos)[0];
+        let taunter = state::agent_ids_at(&state, taunter_pos)[0];
+        let other = state::agent_ids_at(&state, other_pos)[0];
+        let set_effect = |state: &mut State, effect, source| {
+            let timed = Timed {
+                duration: Duration::Rounds(Rounds(1)),
+                phase: Phase::from(0),
+                effect,
+                source,
+            };
+            state.parts.effects.insert(id, Effects(vec![timed]));
+        };
+        let attack = |state: &State, target_id| {
+            let attacker_id = id;
+            let command = command::Attack {
+                attacker_id,
+                target_id,
+            };
+            check(state, &command.into())
+        };
+        set_effect(&mut state, Lasting::Slowed, None);
+        let move_points = state.agent(&id).move_points;
+        assert_eq!(
+            state::move_points(&state, &id),
+            MovePoints(move_points.0 / 2)
+        );
+        set_effect(&mut state, Lasting::Rooted, None);
+        assert_eq!(state::move_points(&state, &id), MovePoints(0));
+        assert_eq!(
+            check(&state, &move_to(id, vec![pos, next])),
+            Err(Error::AgentIsRooted)
+        );
+        set_effect(&mut state, Lasting::Taunted, Some(taunter));
+        assert_eq!(check(&state, &move_to(id, vec![pos, next])), Ok(()));
+        assert_eq!(attack(&state, taunter), Ok(()));
+        assert_eq!(attack(&state, other), Err(Error::AgentIsTaunted));
+        state.parts.remove(&taunter);
+        assert_eq!(attack(&state, other), Ok(()));
+    }
+}
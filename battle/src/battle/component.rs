@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt};
 
 use serde::{Deserialize, Serialize};
 
@@ -7,6 +7,7 @@ use crate::{
         self,
         ability::{Ability, PassiveAbility, RechargeableAbility},
         effect::Timed,
+        heroes::HeroId,
         Attacks, Id, Jokers, MovePoints, Moves, Phase, PlayerId, Rounds,
     },
     map,
@@ -15,6 +16,11 @@ use crate::{
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Pos(pub map::PosHex);
 
+/// The direction the agent is looking in. Attacks coming from the sides
+/// or from behind are easier to land.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Facing(pub map::Dir);
+
 /// Blocks the whole tile. Two blocker objects can't coexist in one tile.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Blocker {
@@ -69,6 +75,10 @@ pub struct Agent {
     pub attack_accuracy: battle::Accuracy,
     pub weapon_type: WeaponType,
 
+    /// Armor points that the attacks ignore.
+    #[serde(default)]
+    pub armor_piercing: battle::Strength,
+
     pub move_points: MovePoints,
     pub reactive_attacks: Attacks,
 
@@ -82,6 +92,55 @@ pub struct Agent {
     pub base_jokers: Jokers,
 }
 
+/// Absorbs some of the attack damage, depending on the attacker's weapon type.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Armor {
+    #[serde(default)]
+    pub slash: battle::Strength,
+
+    #[serde(default)]
+    pub smash: battle::Strength,
+
+    #[serde(default)]
+    pub pierce: battle::Strength,
+
+    #[serde(default)]
+    pub claw: battle::Strength,
+}
+
+impl Armor {
+    pub fn against(&self, weapon_type: WeaponType) -> battle::Strength {
+        match weapon_type {
+            WeaponType::Slash => self.slash,
+            WeaponType::Smash => self.smash,
+            WeaponType::Pierce => self.pierce,
+            WeaponType::Claw => self.claw,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Armor::default()
+    }
+}
+
+/// Lists the non-zero resistances, like "slash 1, pierce 2".
+impl fmt::Display for Armor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let resistances = [
+            ("slash", self.slash),
+            ("smash", self.smash),
+            ("pierce", self.pierce),
+            ("claw", self.claw),
+        ];
+        let resistances: Vec<_> = resistances
+            .iter()
+            .filter(|(_, value)| value.0 != 0)
+            .map(|(name, value)| format!("{} {}", name, value.0))
+            .collect();
+        write!(f, "{}", resistances.join(", "))
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Abilities(pub Vec<RechargeableAbility>);
 
@@ -104,6 +163,16 @@ pub struct Schedule {
     pub planned: Vec<PlannedAbility>,
 }
 
+/// Links the agent to its campaign hero.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Hero {
+    pub id: HeroId,
+
+    /// Enemies killed with attacks during this battle.
+    #[serde(default)]
+    pub kills: u32,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Summoner {
     pub count: u32,
@@ -112,31 +181,37 @@ pub struct Summoner {
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, derive_more::From)]
 pub enum Component {
     Pos(Pos),
+    Facing(Facing),
     Strength(Strength),
     Meta(Meta),
     BelongsTo(BelongsTo),
     Agent(Agent),
     Blocker(Blocker),
+    Armor(Armor),
     Abilities(Abilities),
     PassiveAbilities(PassiveAbilities),
     Effects(Effects),
     Schedule(Schedule),
     Summoner(Summoner),
+    Hero(Hero),
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Parts {
     pub strength: HashMap<Id, Strength>,
     pub pos: HashMap<Id, Pos>,
+    pub facing: HashMap<Id, Facing>,
     pub meta: HashMap<Id, Meta>,
     pub belongs_to: HashMap<Id, BelongsTo>,
     pub agent: HashMap<Id, Agent>,
     pub blocker: HashMap<Id, Blocker>,
+    pub armor: HashMap<Id, Armor>,
     pub abilities: HashMap<Id, Abilities>,
     pub passive_abilities: HashMap<Id, PassiveAbilities>,
     pub effects: HashMap<Id, Effects>,
     pub schedule: HashMap<Id, Schedule>,
     pub summoner: HashMap<Id, Summoner>,
+    pub hero: HashMap<Id, Hero>,
     pub next_obj_id: Id,
 }
 
@@ -145,15 +220,18 @@ impl Parts {
         Self {
             strength: HashMap::new(),
             pos: HashMap::new(),
+            facing: HashMap::new(),
             meta: HashMap::new(),
             belongs_to: HashMap::new(),
             agent: HashMap::new(),
             blocker: HashMap::new(),
+            armor: HashMap::new(),
             abilities: HashMap::new(),
             passive_abilities: HashMap::new(),
             effects: HashMap::new(),
             schedule: HashMap::new(),
             summoner: HashMap::new(),
+            hero: HashMap::new(),
             next_obj_id: Default::default(),
         }
     }
@@ -171,47 +249,17 @@ impl Parts {
     pub fn remove(&mut self, id: &Id) {
         self.strength.remove(id);
         self.pos.remove(id);
+        self.facing.remove(id);
         self.meta.remove(id);
         self.belongs_to.remove(id);
         self.agent.remove(id);
         self.blocker.remove(id);
+        self.armor.remove(id);
         self.abilities.remove(id);
         self.passive_abilities.remove(id);
         self.effects.remove(id);
         self.schedule.remove(id);
         self.summoner.remove(id);
-    }
-}
-
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub struct Prototypes(pub HashMap<ObjType, Vec<Component>>);
-
-fn init_component(component: &mut Component) {
-    match component {
-        Component::Agent(agent) => {
-            agent.base_moves = agent.moves;
-            agent.base_attacks = agent.attacks;
-            agent.base_jokers = agent.jokers;
-        }
-        Component::Strength(strength) => {
-            strength.base_strength = strength.strength;
-        }
-        _ => {}
-    }
-}
-
-impl Prototypes {
-    pub fn from_str(s: &str) -> Self {
-        let mut prototypes: Prototypes = ron::de::from_str(s).expect("Can't parse the prototypes");
-        prototypes.init_components();
-        prototypes
-    }
-
-    pub fn init_components(&mut self) {
-        for components in self.0.values_mut() {
-            for component in components {
-                init_component(component);
-            }
-        }
+        self.hero.remove(id);
     }
 }
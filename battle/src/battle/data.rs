@@ -0,0 +1,71 @@
+//! Parts shared by the game data that is loaded from RON files:
+//! prototypes, ability definitions, tiles and scenarios.
+//!
+//! Every kind of data has its own `Error`. `validate` reports all of them at once
+//! as `ValidationError`s, `check` only returns the first one.
+//! Tiles have only a few errors and stop at the first one.
+
+use std::{error, fmt};
+
+use risc0_zkvm::sha::{Digest, Impl, Sha256};
+use serde::Serialize;
+
+/// An error with the path of the field that caused it, like `imp.Agent.moves`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationError<E> {
+    pub path: String,
+    pub error: E,
+}
+
+impl<E: fmt::Display> fmt::Display for ValidationError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.error)
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError<E> {
+    Ron(ron::error::SpannedError),
+    Invalid(Vec<ValidationError<E>>),
+}
+
+impl<E> From<ron::error::SpannedError> for LoadError<E> {
+    fn from(e: ron::error::SpannedError) -> Self {
+        LoadError::Ron(e)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for LoadError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Ron(e) => write!(f, "Bad RON: {}", e),
+            LoadError::Invalid(errors) => {
+                let errors: Vec<_> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", errors.join("; "))
+            }
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> error::Error for LoadError<E> {}
+
+pub(crate) fn first_error<E>(errors: Vec<ValidationError<E>>) -> Result<(), E> {
+    match errors.into_iter().next() {
+        Some(e) => Err(e.error),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn to_result<E>(errors: Vec<ValidationError<E>>) -> Result<(), Vec<ValidationError<E>>> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// The value must be serialized in a fixed order, so maps have to be sorted first.
+pub(crate) fn digest<T: Serialize + ?Sized>(value: &T) -> Digest {
+    let bytes = serde_json::to_vec(value).expect("Can't serialize the data");
+    *Impl::hash_bytes(&bytes)
+}
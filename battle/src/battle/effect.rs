@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::battle::{
     component::{Component, ObjType},
-    Phase, PosHex, PushStrength, Rounds, Strength,
+    Accuracy, Id, Phase, PosHex, PushStrength, Rounds, Strength,
 };
 
 #[derive(Clone, Debug, Copy, PartialEq, Serialize, Deserialize)]
@@ -25,6 +25,10 @@ pub struct Timed {
     pub duration: Duration,
     pub phase: Phase,
     pub effect: Lasting,
+
+    /// The object that caused the effect, if any: taunted agents must attack it.
+    #[serde(default)]
+    pub source: Option<Id>,
 }
 
 /// Instant effects
@@ -37,9 +41,10 @@ pub enum Effect {
     Heal(Heal),
     Wound(Wound),
     Knockback(Knockback),
-    FlyOff(FlyOff), // TODO: flying boulders should make some damage
+    FlyOff(FlyOff),
     Throw(Throw),
     Dodge(Dodge),
+    Collision(Collision),
     Bloodlust,
 }
 
@@ -56,16 +61,25 @@ impl Effect {
             Effect::FlyOff(_) => "Fly off",
             Effect::Throw(_) => "Throw",
             Effect::Dodge(_) => "Dodge",
+            Effect::Collision(_) => "Collision",
             Effect::Bloodlust => "Bloodlust",
         }
     }
 }
 
+pub const BLINDED_ACCURACY_PENALTY: Accuracy = Accuracy(2);
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Lasting {
     Poison,
     Stun,
     Bloodlust,
+    Burning,
+    Slowed,
+    Rooted,
+    Shielded,
+    Blinded,
+    Taunted,
 }
 
 impl Lasting {
@@ -74,6 +88,12 @@ impl Lasting {
             Lasting::Poison => "Poison",
             Lasting::Stun => "Stun",
             Lasting::Bloodlust => "Bloodlust",
+            Lasting::Burning => "Burning",
+            Lasting::Slowed => "Slowed",
+            Lasting::Rooted => "Rooted",
+            Lasting::Shielded => "Shielded",
+            Lasting::Blinded => "Blinded",
+            Lasting::Taunted => "Taunted",
         }
     }
 
@@ -85,14 +105,31 @@ impl Lasting {
             ],
             Lasting::Stun => vec!["Removes all Actions/Moves/Jokers every turn.".into()],
             Lasting::Bloodlust => vec!["Gives three additional Jokers every turn.".into()],
+            Lasting::Burning => vec![
+                "Removes one strength every turn.".into(),
+                "Unlike poison, can kill.".into(),
+            ],
+            Lasting::Slowed => vec!["Halves the move points.".into()],
+            Lasting::Rooted => vec!["Can't move, jump or dash.".into()],
+            Lasting::Shielded => vec!["Absorbs all the damage of the next wound.".into()],
+            Lasting::Blinded => vec![format!(
+                "Reduces the attack accuracy by {}.",
+                BLINDED_ACCURACY_PENALTY.0
+            )],
+            Lasting::Taunted => vec!["Can only attack the agent that caused the taunt.".into()],
         }
     }
 }
 
-// TODO: Move `armor_break` to a separate effect?
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Wound {
+    /// The damage that gets through and is subtracted from the strength.
     pub damage: Strength,
+
+    /// The damage that armor or a shield stopped.
+    #[serde(default)]
+    pub absorbed: Strength,
+
     pub attacker_pos: Option<PosHex>,
 }
 
@@ -132,6 +169,12 @@ pub struct Dodge {
     pub attacker_pos: PosHex,
 }
 
+/// A flying object has crashed into this one.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Collision {
+    pub flyer_pos: PosHex,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Knockback {
     pub from: PosHex,
@@ -6,7 +6,7 @@ use crate::battle::{
     effect::{self, Effect},
     movement::Path,
     state::BattleResult,
-    Id, Moves, PlayerId, PosHex,
+    Id, Moves, PlayerId, PosHex, TileType,
 };
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -37,6 +37,7 @@ pub enum ActiveEvent {
     BeginTurn(BeginTurn),
     UseAbility(UseAbility),
     UsePassiveAbility(UsePassiveAbility),
+    EnterTile(EnterTile),
     MoveTo(MoveTo),
     Attack(Attack),
     EffectTick(EffectTick),
@@ -93,6 +94,14 @@ pub struct UsePassiveAbility {
     pub ability: PassiveAbility,
 }
 
+/// An agent has entered a damaging tile.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnterTile {
+    pub id: Id,
+    pub pos: PosHex,
+    pub tile: TileType,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EffectTick {
     pub id: Id,
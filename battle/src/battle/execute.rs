@@ -1,16 +1,19 @@
 use crate::{
     battle::{
         self,
-        ability::{Ability, PassiveAbility},
-        check::{check, Error},
+        ability::{
+            definitions::{self, Area, Target},
+            Ability, PassiveAbility,
+        },
+        check::{check, check_strict, Error},
         command::{self, Command},
         component::{self, ObjType},
-        effect::{self, Effect},
+        effect::{self, Effect, Lasting},
         event::{self, ActiveEvent, Event},
+        scenario::Rules,
         state::{self, BattleResult, State},
         Id, Phase, PlayerId, PushStrength, Rounds, Strength, Weight,
     },
-    game::Level,
     map::{self, Dir, PosHex},
     utils::{self, SimpleRng},
 };
@@ -20,12 +23,9 @@ use crate::battle::movement::Path;
 #[cfg(feature = "event")]
 use crate::battle::Moves;
 
-use super::state::{
-    apply::{
-        remove_outdated_lasting_effect, remove_outdated_planned_abilities, reset_status,
-        tick_planned_abilities, update_cooldowns, update_reattack, update_reattack_with_stun,
-    },
-    get_id_by_pos,
+use super::state::apply::{
+    count_kill, remove_outdated_lasting_effect, remove_outdated_planned_abilities, reset_status,
+    tick_planned_abilities, turn, update_cooldowns, update_reattack, update_reattack_with_stun,
 };
 
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -46,7 +46,41 @@ pub fn execute(
     if state.player_id().0 == 0 {
         check(state, command)?
     }
+    execute_unchecked(
+        state,
+        command,
+        rng,
+        #[cfg(feature = "event")]
+        cb,
+    );
+    Ok(())
+}
+
+/// Like `execute`, but validates the command for every player
+/// and forbids `Create` commands. Used for replaying untrusted transcripts.
+pub fn execute_strict(
+    state: &mut State,
+    command: &Command,
+    rng: &mut SimpleRng,
+    #[cfg(feature = "event")] cb: Cb,
+) -> Result<(), Error> {
+    check_strict(state, command)?;
+    execute_unchecked(
+        state,
+        command,
+        rng,
+        #[cfg(feature = "event")]
+        cb,
+    );
+    Ok(())
+}
 
+fn execute_unchecked(
+    state: &mut State,
+    command: &Command,
+    rng: &mut SimpleRng,
+    #[cfg(feature = "event")] cb: Cb,
+) {
     #[cfg(feature = "debug")]
     add_debug_commands(state, command);
 
@@ -96,49 +130,123 @@ pub fn execute(
     match command {
         Command::Create(_) => {}
         _ => {
+            // The first player without enemies wins together with its allies.
             for i in 0..state.players_count() {
                 let player_id = PlayerId(i);
                 if state::enemy_agent_count(state, player_id) == 0 {
-                    let result = BattleResult {
-                        winner_id: player_id,
-                        survivor_types: state::players_agent_types(state, PlayerId(0)),
-                        level: state.level().clone(),
-                    };
-
-                    #[cfg(not(feature = "event"))]
-                    state.set_battle_result(result);
-
-                    #[cfg(feature = "event")]
-                    {
-                        let event = Event {
-                            active_event: event::EndBattle { result }.into(),
-                            actor_ids: Vec::new(),
-                            instant_effects: Vec::new(),
-                            timed_effects: Vec::new(),
-                            scheduled_abilities: Vec::new(),
-                        };
-
-                        do_event(
-                            state,
-                            #[cfg(feature = "event")]
-                            cb,
-                            &event,
-                        );
-                    }
+                    let winner_ids = state::allied_player_ids(state, player_id);
+                    end_battle(
+                        state,
+                        #[cfg(feature = "event")]
+                        cb,
+                        winner_ids,
+                    );
+                    break;
                 }
             }
+            // The human player has lost even if the rest are still fighting each other.
+            let human = PlayerId(0);
+            if state.battle_result().is_none() && state::players_agent_ids(state, human).is_empty()
+            {
+                let winner_ids = (0..state.players_count())
+                    .map(PlayerId)
+                    .filter(|&id| !state::are_allies(state, id, human))
+                    .filter(|&id| !state::players_agent_ids(state, id).is_empty())
+                    .collect();
+                end_battle(
+                    state,
+                    #[cfg(feature = "event")]
+                    cb,
+                    winner_ids,
+                );
+            }
+            if state.battle_result().is_none() && state.is_out_of_rounds() {
+                let winner_id = state.round_limit().winner_id;
+                let winner_ids = state::allied_player_ids(state, winner_id);
+                end_battle(
+                    state,
+                    #[cfg(feature = "event")]
+                    cb,
+                    winner_ids,
+                );
+            }
         }
     }
+}
 
-    Ok(())
+fn end_battle(state: &mut State, #[cfg(feature = "event")] cb: Cb, winner_ids: Vec<PlayerId>) {
+    let result = BattleResult {
+        winner_ids,
+        survivor_types: state::players_agent_types(state, PlayerId(0)),
+        survivor_heroes: state::players_heroes(state, PlayerId(0)),
+    };
+
+    #[cfg(not(feature = "event"))]
+    state.set_battle_result(result);
+
+    #[cfg(feature = "event")]
+    {
+        let event = Event {
+            active_event: event::EndBattle { result }.into(),
+            actor_ids: Vec::new(),
+            instant_effects: Vec::new(),
+            timed_effects: Vec::new(),
+            scheduled_abilities: Vec::new(),
+        };
+
+        do_event(state, cb, &event);
+    }
 }
 
 fn do_event(state: &mut State, #[cfg(feature = "event")] cb: Cb, event: &Event) {
+    let poison_cloud_spawners = poison_cloud_spawners(state, &event.instant_effects);
     #[cfg(feature = "event")]
     cb(state, event, ApplyPhase::Pre);
     state.apply(event);
     #[cfg(feature = "event")]
     cb(state, event, ApplyPhase::Post);
+    spawn_poison_clouds(
+        state,
+        #[cfg(feature = "event")]
+        cb,
+        poison_cloud_spawners,
+    );
+}
+
+/// Objects that are killed by these effects and spawn a poison cloud on death.
+fn poison_cloud_spawners(state: &State, effects: &[(Id, Vec<Effect>)]) -> Vec<(Id, PosHex)> {
+    let mut spawners = Vec::new();
+    for (id, effects) in effects {
+        let is_killed = effects.iter().any(|e| matches!(e, Effect::Kill(_)));
+        let is_spawner = state
+            .parts()
+            .passive_abilities
+            .get(id)
+            .is_some_and(|a| a.0.contains(&PassiveAbility::SpawnPoisonCloudOnDeath));
+        if is_killed && is_spawner {
+            spawners.push((*id, state.pos(id).0));
+        }
+    }
+    spawners
+}
+
+fn spawn_poison_clouds(
+    state: &mut State,
+    #[cfg(feature = "event")] cb: Cb,
+    spawners: Vec<(Id, PosHex)>,
+) {
+    for (id, pos) in spawners {
+        let context = create_poison_cloud(state, pos);
+        do_passive_ability(
+            state,
+            #[cfg(feature = "event")]
+            cb,
+            id,
+            pos,
+            PassiveAbility::SpawnPoisonCloudOnDeath,
+            context,
+        );
+    }
 }
 
 #[cfg(feature = "debug")]
@@ -215,41 +323,84 @@ fn execute_move_to(
     //     }
     // }
 
-    #[cfg(feature = "event")]
-    {
-        use crate::battle::movement::Path;
-        use crate::battle::Moves;
-        let path = Path::new(vec![command.path.from(), command.path.to()]);
-        do_move(
+    // Hazards hurt on every tile of the path, not only on the last one,
+    // so the walk is split into segments that end on the hazardous tiles.
+    let tiles = command.path.tiles();
+    let mut segment_start = tiles[0];
+    for (i, &pos) in tiles.iter().enumerate().skip(1) {
+        let is_last = i == tiles.len() - 1;
+        if !is_last && !is_hazard(state, pos) {
+            continue;
+        }
+        let is_first = segment_start == command.path.from();
+        move_segment(
             state,
             #[cfg(feature = "event")]
             cb,
             id,
-            Some(Moves(1)),
-            path,
+            #[cfg(feature = "event")]
+            segment_start,
+            pos,
+            is_first,
         );
+        segment_start = pos;
+        if is_last {
+            // The event build squashes every segment into one step,
+            // so the facing is taken from the original path for both builds.
+            let dir = Dir::get_dir_from_to(tiles[i - 1], pos);
+            turn(state, &id, dir);
+        }
+        try_execute_passive_abilities_on_move(
+            state,
+            #[cfg(feature = "event")]
+            cb,
+            id,
+        );
+        if !state.parts().is_exist(&id) {
+            return;
+        }
+    }
+}
+
+/// Damaging tiles and traps.
+fn is_hazard(state: &State, pos: PosHex) -> bool {
+    let passive_abilities = &state.parts().passive_abilities;
+    state.tile_info(pos).damage.0 > 0
+        || state::ids_at(state, pos)
+            .iter()
+            .any(|id| passive_abilities.get(id).is_some())
+}
+
+/// Only the first segment of a path costs a move.
+fn move_segment(
+    state: &mut State,
+    #[cfg(feature = "event")] cb: Cb,
+    id: Id,
+    #[cfg(feature = "event")] from: PosHex,
+    to: PosHex,
+    is_paid: bool,
+) {
+    #[cfg(feature = "event")]
+    {
+        let cost = if is_paid { Some(Moves(1)) } else { None };
+        do_move(state, cb, id, cost, Path::new(vec![from, to]));
     }
 
     #[cfg(not(feature = "event"))]
     {
         let parts = state.parts_mut();
         let pos = parts.pos.get_mut(&id).unwrap();
-        pos.0 = command.path.to();
+        pos.0 = to;
 
-        let agent = parts.agent.get_mut(&id).unwrap();
-        if agent.moves.0 > 0 {
-            agent.moves.0 -= 1;
-        } else {
-            agent.jokers.0 -= 1;
+        if is_paid {
+            let agent = parts.agent.get_mut(&id).unwrap();
+            if agent.moves.0 > 0 {
+                agent.moves.0 -= 1;
+            } else {
+                agent.jokers.0 -= 1;
+            }
         }
     }
-
-    try_execute_passive_abilities_on_move(
-        state,
-        #[cfg(feature = "event")]
-        cb,
-        id,
-    );
 }
 
 #[cfg(feature = "event")]
@@ -267,7 +418,9 @@ fn do_move(state: &mut State, cb: Cb, id: Id, cost: Option<Moves>, path: Path) {
 }
 
 fn execute_create(state: &mut State, #[cfg(feature = "event")] cb: Cb, command: &command::Create) {
-    let mut components = state.prototype_for(&command.prototype);
+    let mut components = state
+        .prototype_for(&command.prototype)
+        .expect("The prototype is checked");
     if let Some(player_id) = command.owner {
         components.push(component::BelongsTo(player_id).into());
     }
@@ -278,6 +431,9 @@ fn execute_create(state: &mut State, #[cfg(feature = "event")] cb: Cb, command:
         }
         .into(),
     ]);
+    if is_agent(&components) {
+        components.push(initial_facing(command.pos, command.owner).into());
+    }
 
     let id = state.alloc_id();
 
@@ -310,6 +466,23 @@ fn execute_create(state: &mut State, #[cfg(feature = "event")] cb: Cb, command:
     }
 }
 
+fn is_agent(components: &[component::Component]) -> bool {
+    components
+        .iter()
+        .any(|component| matches!(component, component::Component::Agent(_)))
+}
+
+/// Agents enter the battle looking at the center of the map.
+/// The ones that appear right in the center look towards the enemy side.
+fn initial_facing(pos: PosHex, owner: Option<PlayerId>) -> component::Facing {
+    let center = PosHex::new(0, 0);
+    let dir = Dir::towards(pos, center).unwrap_or(match owner {
+        Some(PlayerId(0)) => Dir::East,
+        _ => Dir::West,
+    });
+    component::Facing(dir)
+}
+
 #[derive(PartialEq, Clone, Debug)]
 enum AttackStatus {
     Hit,
@@ -343,9 +516,14 @@ fn execute_attack_internal(
                 context.instant_effects.push((target_id, vec![effect]));
                 AttackStatus::Kill
             } else {
+                let dealt = match effect {
+                    Effect::Wound(ref wound) => wound.damage,
+                    _ => unreachable!(),
+                };
                 context.instant_effects.push((target_id, vec![effect]));
 
-                let c = try_execute_passive_abilities_on_attack(state, attacker_id, target_id);
+                let c =
+                    try_execute_passive_abilities_on_attack(state, attacker_id, target_id, dealt);
                 context.merge_with(c);
 
                 AttackStatus::Hit
@@ -366,6 +544,9 @@ fn execute_attack_internal(
             scheduled_abilities: context.scheduled_abilities,
         };
         do_event(state, cb, &event);
+        if status == AttackStatus::Kill {
+            count_kill(state, &attacker_id);
+        }
 
         // for id in context.moved_actor_ids {
         //     try_execute_passive_abilities_on_move(
@@ -382,39 +563,42 @@ fn execute_attack_internal(
     #[cfg(not(feature = "event"))]
     {
         use crate::battle::state::apply::{
-            apply_effect_timed, apply_effect_wound, apply_event_attack,
+            apply_effect_instant, apply_effect_timed, apply_effect_wound, apply_event_attack,
         };
         let attack_effect = try_attack(state, attacker_id, target_id, rng);
-        apply_event_attack(state, &attacker_id);
+        apply_event_attack(state, &attacker_id, &target_id);
 
         let mut status = AttackStatus::Miss;
 
         if let Some(e) = attack_effect {
             match e {
                 Effect::Kill(_) => {
+                    let spawners = poison_cloud_spawners(state, &[(target_id, vec![e])]);
                     state.parts_mut().remove(&target_id);
+                    spawn_poison_clouds(state, spawners);
+                    count_kill(state, &attacker_id);
                     status = AttackStatus::Kill;
                 }
                 Effect::Wound(w) => {
+                    let context = try_execute_passive_abilities_on_attack(
+                        state,
+                        attacker_id,
+                        target_id,
+                        w.damage,
+                    );
+                    let spawners = poison_cloud_spawners(state, &context.instant_effects);
                     apply_effect_wound(state, &target_id, w.damage.0);
-
-                    if let Some(passive_abilities) =
-                        state.parts().passive_abilities.get(&attacker_id).cloned()
-                    {
-                        for ability in &passive_abilities.0 {
-                            if let PassiveAbility::PoisonAttack = ability {
-                                let owner = state.belongs_to(&target_id).0;
-                                let effect = effect::Timed {
-                                    duration: effect::Duration::Rounds(2.into()),
-                                    phase: Phase::from(owner.0),
-                                    effect: effect::Lasting::Poison,
-                                };
-
-                                apply_effect_timed(state, &target_id, &effect)
-                            }
+                    for (id, effects) in &context.instant_effects {
+                        for effect in effects {
+                            apply_effect_instant(state, id, effect);
                         }
                     }
-
+                    for (id, effects) in &context.timed_effects {
+                        for effect in effects {
+                            apply_effect_timed(state, id, effect);
+                        }
+                    }
+                    spawn_poison_clouds(state, spawners);
                     status = AttackStatus::Hit;
                 }
                 _ => unreachable!(),
@@ -425,10 +609,21 @@ fn execute_attack_internal(
     }
 }
 
+/// Wounds the agent and sets it on fire, so it keeps burning after leaving the tile.
 fn try_execute_passive_ability_burn(state: &mut State, target_id: Id) -> ExecuteContext {
     let mut context = ExecuteContext::default();
-    let target_effects = vec![wound_or_kill(state, &target_id, Strength(1))];
-    context.instant_effects.push((target_id, target_effects));
+    let wound = wound_or_kill(state, &target_id, Strength(1));
+    if let Effect::Wound(_) = wound {
+        let owner = state.belongs_to(&target_id).0;
+        let effect = effect::Timed {
+            duration: effect::Duration::Rounds(2.into()),
+            phase: Phase::from(owner.0),
+            effect: Lasting::Burning,
+            source: None,
+        };
+        context.timed_effects.push((target_id, vec![effect]));
+    }
+    context.instant_effects.push((target_id, vec![wound]));
     context
 }
 
@@ -449,6 +644,7 @@ fn try_execute_passive_ability_poison(state: &State, target_id: Id) -> ExecuteCo
         duration: effect::Duration::Rounds(2.into()),
         phase: Phase::from(owner.0),
         effect: effect::Lasting::Poison,
+        source: None,
     };
     context.timed_effects.push((target_id, vec![effect]));
     context
@@ -483,11 +679,42 @@ fn do_passive_ability(
     );
 }
 
+fn try_execute_tile_damage(state: &mut State, #[cfg(feature = "event")] cb: Cb, id: Id) {
+    if !state.parts().agent.contains_key(&id) {
+        return;
+    }
+    let pos = state.pos(&id).0;
+    let tile = state.map().tile(pos);
+    let damage = state.tiles().get(tile).damage;
+    if damage.0 <= 0 {
+        return;
+    }
+    let event = Event {
+        active_event: event::EnterTile { id, pos, tile }.into(),
+        actor_ids: vec![id],
+        instant_effects: vec![(id, vec![wound_or_kill(state, &id, damage)])],
+        timed_effects: Vec::new(),
+        scheduled_abilities: Vec::new(),
+    };
+    do_event(
+        state,
+        #[cfg(feature = "event")]
+        cb,
+        &event,
+    );
+}
+
 fn try_execute_passive_abilities_on_move(
     state: &mut State,
     #[cfg(feature = "event")] cb: Cb,
     target_id: Id,
 ) {
+    try_execute_tile_damage(
+        state,
+        #[cfg(feature = "event")]
+        cb,
+        target_id,
+    );
     try_execute_passive_abilities_tick(
         state,
         #[cfg(feature = "event")]
@@ -625,11 +852,12 @@ pub fn try_execute_passive_abilities_on_begin_turn(
     }
 }
 
-#[allow(dead_code)]
+/// `dealt` is the damage the attack itself has already done to the target.
 fn try_execute_passive_abilities_on_attack(
-    state: &mut State,
+    state: &State,
     attacker_id: Id,
     target_id: Id,
+    dealt: Strength,
 ) -> ExecuteContext {
     let mut context = ExecuteContext::default();
     if let Some(passive_abilities) = state.parts().passive_abilities.get(&attacker_id) {
@@ -641,14 +869,23 @@ fn try_execute_passive_abilities_on_attack(
                         duration: effect::Duration::Rounds(2.into()),
                         phase: Phase::from(owner.0),
                         effect: effect::Lasting::Poison,
+                        source: None,
                     };
                     context.timed_effects.push((target_id, vec![effect]));
                 }
+                PassiveAbility::HeavyImpact => {
+                    let c = try_execute_passive_ability_heavy_impact(
+                        state,
+                        attacker_id,
+                        target_id,
+                        dealt,
+                    );
+                    context.merge_with(c);
+                }
                 PassiveAbility::Burn
                 | PassiveAbility::SpikeTrap
                 | PassiveAbility::Poison
                 | PassiveAbility::Regenerate
-                | PassiveAbility::HeavyImpact
                 | PassiveAbility::SpawnPoisonCloudOnDeath => (),
             }
         }
@@ -656,10 +893,98 @@ fn try_execute_passive_abilities_on_attack(
     context
 }
 
+/// Throws the target one tile away from the attacker.
+/// Every blocker next to where the target lands is crashed into:
+/// the agents among them get hit and the target gets hit by the heaviest one.
+fn try_execute_passive_ability_heavy_impact(
+    state: &State,
+    attacker_id: Id,
+    target_id: Id,
+    dealt: Strength,
+) -> ExecuteContext {
+    let mut context = ExecuteContext::default();
+    let strength = PushStrength(Weight::Normal);
+    let weight = state.blocker(&target_id).weight;
+    if !strength.can_push(weight) {
+        return context;
+    }
+    let from = state.pos(&target_id).0;
+    let dir = Dir::get_dir_from_to(state.pos(&attacker_id).0, from);
+    let to = Dir::get_neighbor_pos(from, dir);
+    if !state.map().is_inboard(to) {
+        return context;
+    }
+    let mut target_effects = Vec::new();
+    let landing_pos = if state::is_tile_blocked(state, to) {
+        from
+    } else {
+        target_effects.push(effect::FlyOff { from, to, strength }.into());
+        context.moved_actor_ids.push(target_id);
+        to
+    };
+    let mut heaviest_obstacle = None;
+    for dir in map::dirs() {
+        let pos = Dir::get_neighbor_pos(landing_pos, dir);
+        if !state.map().is_inboard(pos) {
+            continue;
+        }
+        let obstacle_id = match state::blocker_id_at_opt(state, pos) {
+            Some(id) if id != target_id && id != attacker_id => id,
+            _ => continue,
+        };
+        let mut effects = vec![effect::Collision {
+            flyer_pos: landing_pos,
+        }
+        .into()];
+        if state.parts().agent.contains_key(&obstacle_id) {
+            effects.push(collision(
+                state,
+                obstacle_id,
+                weight,
+                Strength(0),
+                landing_pos,
+            ));
+        }
+        context.instant_effects.push((obstacle_id, effects));
+        let obstacle_weight = state.blocker(&obstacle_id).weight;
+        if heaviest_obstacle.is_none_or(|(heaviest, _)| obstacle_weight > heaviest) {
+            heaviest_obstacle = Some((obstacle_weight, pos));
+        }
+    }
+    if let Some((obstacle_weight, obstacle_pos)) = heaviest_obstacle {
+        let effect = collision(state, target_id, obstacle_weight, dealt, obstacle_pos);
+        target_effects.push(effect);
+    }
+    if !target_effects.is_empty() {
+        context.instant_effects.push((target_id, target_effects));
+    }
+    context
+}
+
+/// The heavier the object crashed into is, the harder it hits.
+/// `dealt` is the damage the agent has already taken from the same attack.
+fn collision(state: &State, id: Id, weight: Weight, dealt: Strength, other_pos: PosHex) -> Effect {
+    let damage = Strength(weight as i32 + 1);
+    if state.strength(&id).strength.0 - dealt.0 > damage.0 {
+        effect::Wound {
+            damage,
+            absorbed: Strength(0),
+            attacker_pos: Some(other_pos),
+        }
+        .into()
+    } else {
+        effect::Kill {
+            attacker_pos: Some(other_pos),
+        }
+        .into()
+    }
+}
+
 fn try_execute_reaction_attacks(
     state: &mut State,
     #[cfg(feature = "event")] cb: Cb,
     target_id: Id,
+    surprised_id: Option<Id>,
     rng: &mut SimpleRng,
 ) -> AttackStatus {
     let mut status = AttackStatus::Miss;
@@ -674,11 +999,17 @@ fn try_execute_reaction_attacks(
                 if a.attacks.0 == 0 && a.jokers.0 == 0 {
                     continue;
                 }
+                if surprised_id == Some(obj_id) {
+                    continue;
+                }
+                if matches!(state::taunter(state, &obj_id), Some(id) if id != target_id) {
+                    continue;
+                }
 
                 let from = state.pos(&obj_id).0;
                 let to = state.pos(&target_id).0;
                 let dist = map::distance_hex(from, to);
-                if dist > a.attack_distance {
+                if dist > a.attack_distance || !state::is_in_line_of_sight(state, from, to) {
                     continue;
                 }
             }
@@ -721,6 +1052,14 @@ fn execute_attack(
     command: &command::Attack,
     rng: &mut SimpleRng,
 ) {
+    // Agents attacked from behind are caught off guard and can't react.
+    let side = state::attack_side(state, &command.attacker_id, &command.target_id);
+    let surprised_id = if side == state::AttackSide::Rear {
+        Some(command.target_id)
+    } else {
+        None
+    };
+
     execute_attack_internal(
         state,
         #[cfg(feature = "event")]
@@ -736,6 +1075,7 @@ fn execute_attack(
         #[cfg(feature = "event")]
         cb,
         command.attacker_id,
+        surprised_id,
         rng,
     );
 }
@@ -800,7 +1140,7 @@ fn execute_planned_abilities(
             for planned in &schedule.planned {
                 if planned.rounds.0 <= 0 {
                     let c = command::UseAbility {
-                        ability: planned.ability,
+                        ability: planned.ability.clone(),
                         id: obj_id,
                         pos,
                     };
@@ -846,8 +1186,21 @@ pub fn execute_effects(state: &mut State, #[cfg(feature = "event")] cb: Cb) {
                             target_effects.push(wound_or_kill(state, id, Strength(1)));
                         }
                     }
+                    // The fire tile's own damage replaces the tick.
+                    effect::Lasting::Burning => {
+                        let pos = state.pos(id).0;
+                        let burn = PassiveAbility::Burn;
+                        if state::obj_with_passive_ability_at(state, pos, burn).is_none() {
+                            target_effects.push(wound_or_kill(state, id, Strength(1)));
+                        }
+                    }
                     effect::Lasting::Bloodlust => target_effects.push(Effect::Bloodlust),
-                    effect::Lasting::Stun => {}
+                    effect::Lasting::Stun
+                    | effect::Lasting::Slowed
+                    | effect::Lasting::Rooted
+                    | effect::Lasting::Shielded
+                    | effect::Lasting::Blinded
+                    | effect::Lasting::Taunted => {}
                 }
                 let instant_effects = vec![(*id, target_effects)];
                 let event = Event {
@@ -894,9 +1247,8 @@ pub fn execute_effects(state: &mut State, #[cfg(feature = "event")] cb: Cb) {
 }
 
 fn execute_end_turn(state: &mut State, #[cfg(feature = "event")] cb: Cb, _: &command::EndTurn) {
-    let level = state.level();
-    match level {
-        Level::Level0 | Level::Level1 => {
+    match state.rules() {
+        Rules::Basic => {
             let ids = state::players_agent_ids(state, state.player_id());
             update_reattack(state, &ids);
 
@@ -908,7 +1260,7 @@ fn execute_end_turn(state: &mut State, #[cfg(feature = "event")] cb: Cb, _: &com
             update_cooldowns(state, player_id_new);
         }
 
-        Level::Level2 => {
+        Rules::PlannedAbilities => {
             let ids = state::players_agent_ids(state, state.player_id());
             update_reattack(state, &ids);
             remove_outdated_planned_abilities(state);
@@ -921,7 +1273,7 @@ fn execute_end_turn(state: &mut State, #[cfg(feature = "event")] cb: Cb, _: &com
             tick_planned_abilities(state);
         }
 
-        Level::Level3 | Level::Level4 | Level::Level5 => {
+        Rules::Full => {
             let ids = state::players_agent_ids(state, state.player_id());
             update_reattack_with_stun(state, &ids);
             remove_outdated_planned_abilities(state);
@@ -952,6 +1304,10 @@ fn execute_end_turn(state: &mut State, #[cfg(feature = "event")] cb: Cb, _: &com
             }
         }
     }
+
+    if state.player_id().0 == 0 {
+        state.add_round();
+    }
 }
 
 fn start_fire(state: &mut State, pos: PosHex) -> ExecuteContext {
@@ -964,7 +1320,7 @@ fn start_fire(state: &mut State, pos: PosHex) -> ExecuteContext {
     if let Some(id) = state::obj_with_passive_ability_at(state, pos, PassiveAbility::Burn) {
         context.scheduled_abilities.push((id, vec![vanish]));
     } else {
-        let effect_create = effect_create_object(state, &"fire".into(), pos);
+        let effect_create = effect_create_object(state, &FIRE.into(), pos);
         let id = state.alloc_id();
         context.instant_effects.push((id, vec![effect_create]));
         context.scheduled_abilities.push((id, vec![vanish]));
@@ -985,7 +1341,7 @@ fn create_poison_cloud(state: &mut State, pos: PosHex) -> ExecuteContext {
     if let Some(id) = state::obj_with_passive_ability_at(state, pos, PassiveAbility::Poison) {
         context.scheduled_abilities.push((id, vec![vanish]));
     } else {
-        let effect_create = effect_create_object(state, &"poison_cloud".into(), pos);
+        let effect_create = effect_create_object(state, &POISON_CLOUD.into(), pos);
 
         let id = state.alloc_id();
         context.instant_effects.push((id, vec![effect_create]));
@@ -1037,52 +1393,6 @@ impl ExecuteContext {
     }
 }
 
-fn execute_use_ability_knockback(
-    state: &mut State,
-    command: &command::UseAbility,
-) -> ExecuteContext {
-    let from = command.pos;
-    let mut context = ExecuteContext::default();
-    let id = get_id_by_pos(state, command.pos);
-    let strength = PushStrength(Weight::Normal);
-    let blocker_weight = state.blocker(&id).weight;
-    let to = if strength.can_push(blocker_weight) {
-        let actor_pos = state.pos(&command.id).0;
-        let dir = Dir::get_dir_from_to(actor_pos, command.pos);
-        Dir::get_neighbor_pos(command.pos, dir)
-    } else {
-        from
-    };
-
-    if to != from || !state::is_tile_blocked(state, to) {
-        let effect = effect::Knockback { from, to, strength }.into();
-        context.instant_effects.push((id, vec![effect]));
-        context.moved_actor_ids.push(id);
-    }
-    context.actor_ids.push(id);
-    context
-}
-
-fn execute_use_ability_club(state: &mut State, command: &command::UseAbility) -> ExecuteContext {
-    let mut context = ExecuteContext::default();
-    let id = get_id_by_pos(state, command.pos);
-    if let Some(x) = state.parts().belongs_to.get(&id) {
-        let owner = x.0;
-        let phase = Phase::from(owner.0);
-        let effect = effect::Timed {
-            duration: effect::Duration::Rounds(Rounds(1)),
-            phase,
-            effect: effect::Lasting::Stun,
-        };
-        context.timed_effects.push((id, vec![effect]));
-        context.instant_effects.push((id, vec![Effect::Stun]));
-
-        //  extend_or_crate_sub_vec(&mut context.instant_effects, id, vec![Effect::Stun]);
-    }
-    context.actor_ids.push(id);
-    context
-}
-
 fn execute_use_ability_explode_fire(
     state: &mut State,
     command: &command::UseAbility,
@@ -1123,18 +1433,6 @@ fn execute_use_ability_rage(_: &mut State, _: &command::UseAbility) -> ExecuteCo
     ExecuteContext::default()
 }
 
-fn execute_use_ability_heal(
-    state: &mut State,
-    command: &command::UseAbility,
-    strength: Strength,
-) -> ExecuteContext {
-    let mut context = ExecuteContext::default();
-    let id = get_id_by_pos(state, command.pos);
-    let effect = effect::Heal { strength }.into();
-    context.instant_effects.push((id, vec![effect]));
-    context
-}
-
 fn execute_use_ability_vanish(command: &command::UseAbility) -> ExecuteContext {
     let mut context = ExecuteContext::default();
     let effects = vec![Effect::Vanish];
@@ -1161,17 +1459,55 @@ fn execute_use_ability_explode_poison(
 }
 
 fn wound_or_kill(state: &State, id: &Id, damage: battle::Strength) -> Effect {
-    if state.strength(&id).strength > damage {
+    wound_or_kill_by(state, id, damage, Strength(0), None)
+}
+
+/// Armor absorbs some of the damage. A shield absorbs all of it
+/// and is removed by the wound.
+fn wound_or_kill_by(
+    state: &State,
+    id: &Id,
+    damage: battle::Strength,
+    armor: battle::Strength,
+    attacker_pos: Option<PosHex>,
+) -> Effect {
+    let absorbed = if state::has_lasting_effect(state, id, Lasting::Shielded) {
+        damage
+    } else {
+        utils::clamp_max(armor, damage)
+    };
+    let damage = Strength(damage.0 - absorbed.0);
+    if state.strength(id).strength > damage {
         effect::Wound {
             damage,
-            attacker_pos: None,
+            absorbed,
+            attacker_pos,
         }
         .into()
     } else {
-        effect::Kill { attacker_pos: None }.into()
+        effect::Kill { attacker_pos }.into()
     }
 }
 
+/// The target's armor against the attacker's weapon, reduced by the armor piercing.
+fn target_armor(state: &State, attacker_id: Id, target_id: Id) -> battle::Strength {
+    let agent = state.agent(&attacker_id);
+    let armor = match state.parts().armor.get(&target_id) {
+        Some(armor) => armor.against(agent.weapon_type),
+        None => return Strength(0),
+    };
+    Strength(utils::clamp_min(armor.0 - agent.armor_piercing.0, 0))
+}
+
+/// The target's tile and cover make it harder to hit.
+fn target_dodge(state: &State, attacker_id: Id, target_id: Id) -> battle::Dodge {
+    let attacker_pos = state.pos(&attacker_id).0;
+    let target_pos = state.pos(&target_id).0;
+    let tile_dodge = state.tile_info(target_pos).dodge;
+    let cover = state::cover(state, attacker_pos, target_pos);
+    battle::Dodge(tile_dodge.0 + cover.0)
+}
+
 fn try_attack(
     state: &State,
     attacker_id: Id,
@@ -1189,7 +1525,12 @@ fn try_attack(
     // let agent_target = state.agent(&target_id);
     let target_strength = state.strength(&target_id).strength;
 
-    let k = agent_attacker.attack_accuracy.0 - attacker_wounds;
+    let k = state::attack_accuracy(state, &attacker_id).0
+        + state::attack_side(state, &attacker_id, &target_id)
+            .accuracy_bonus()
+            .0
+        - attacker_wounds
+        - target_dodge(state, attacker_id, target_id).0;
 
     let r = rng.gen_range(0, k + if target_strength.0 > 2 { 1 } else { 2 });
 
@@ -1211,16 +1552,14 @@ fn try_attack(
         damage_raw,
         agent_attacker.attack_strength.0,
     ));
-    let effect = if target_strength > damage {
-        effect::Wound {
-            damage,
-            attacker_pos,
-        }
-        .into()
-    } else {
-        effect::Kill { attacker_pos }.into()
-    };
-    Some(effect)
+    let armor = target_armor(state, attacker_id, target_id);
+    Some(wound_or_kill_by(
+        state,
+        &target_id,
+        damage,
+        armor,
+        attacker_pos,
+    ))
 }
 
 fn execute_use_ability_explode_damage(
@@ -1282,23 +1621,15 @@ fn execute_use_ability_explode_push(
     context
 }
 
-fn execute_use_ability_poison(state: &mut State, command: &command::UseAbility) -> ExecuteContext {
-    let mut context = ExecuteContext::default();
-    let id = get_id_by_pos(state, command.pos);
-    let owner = state.belongs_to(&id).0;
-    let phase = Phase::from(owner.0);
-    let effect = effect::Timed {
-        duration: effect::Duration::Rounds(2.into()),
-        phase,
-        effect: effect::Lasting::Poison,
-    };
-    context.timed_effects.push((id, vec![effect]));
-    context.actor_ids.push(id);
-    context
+/// The created types are known, `prototypes::check` makes sure of that.
+fn created_components(state: &State, prototype: &ObjType) -> Vec<component::Component> {
+    state
+        .prototype_for(prototype)
+        .unwrap_or_else(|| panic!("No prototype for '{}'", prototype.0))
 }
 
 fn effect_create_object(state: &State, prototype: &ObjType, pos: PosHex) -> Effect {
-    let mut components = state.prototype_for(prototype);
+    let mut components = created_components(state, prototype);
     components.extend_from_slice(&[
         component::Pos(pos).into(),
         component::Meta {
@@ -1322,12 +1653,15 @@ fn effect_create_agent(
     pos: PosHex,
 ) -> Effect {
     let name = prototype.clone();
-    let mut components = state.prototype_for(prototype);
+    let mut components = created_components(state, prototype);
     components.extend_from_slice(&[
         component::Pos(pos).into(),
         component::Meta { name }.into(),
         component::BelongsTo(player_id).into(),
     ]);
+    if is_agent(&components) {
+        components.push(initial_facing(pos, Some(player_id)).into());
+    }
     effect::Create {
         pos,
         prototype: prototype.clone(),
@@ -1337,15 +1671,42 @@ fn effect_create_agent(
     .into()
 }
 
+const FIRE: &str = "fire";
+const POISON_CLOUD: &str = "poison_cloud";
+const SUMMONED: [&str; 3] = ["imp", "toxic_imp", "imp_bomber"];
+
+/// Object types that the ability creates, bombs come first.
+/// `prototypes::check` makes sure that all of them exist.
+pub(crate) fn created_by_ability(ability: &Ability) -> &'static [&'static str] {
+    match ability {
+        Ability::Bomb => &["bomb_damage"],
+        Ability::BombPush => &["bomb_push"],
+        Ability::BombFire => &["bomb_fire", FIRE],
+        Ability::BombPoison => &["bomb_poison", POISON_CLOUD],
+        Ability::BombDemonic => &["bomb_demonic"],
+        Ability::Summon => &SUMMONED,
+        Ability::ExplodeFire => &[FIRE],
+        Ability::ExplodePoison => &[POISON_CLOUD],
+        _ => &[],
+    }
+}
+
+pub(crate) fn created_by_passive_ability(ability: PassiveAbility) -> &'static [&'static str] {
+    match ability {
+        PassiveAbility::SpawnPoisonCloudOnDeath => &[POISON_CLOUD],
+        _ => &[],
+    }
+}
+
 fn throw_bomb(
     state: &mut State,
     command: &command::UseAbility,
-    prototype: &ObjType,
     rounds: Rounds,
     ability: Ability,
 ) -> ExecuteContext {
     let mut context = ExecuteContext::default();
-    let effect_create = effect_create_object(state, prototype, command.pos);
+    let prototype = created_by_ability(&command.ability)[0].into();
+    let effect_create = effect_create_object(state, &prototype, command.pos);
     let id = state.alloc_id();
     let effects = vec![effect_create];
     context.instant_effects.push((id, effects));
@@ -1366,7 +1727,7 @@ fn execute_use_ability_bomb_push(
     command: &command::UseAbility,
 ) -> ExecuteContext {
     let ability = Ability::ExplodePush;
-    throw_bomb(state, command, &"bomb_push".into(), Rounds(0), ability)
+    throw_bomb(state, command, Rounds(0), ability)
 }
 
 fn execute_use_ability_bomb_damage(
@@ -1374,7 +1735,7 @@ fn execute_use_ability_bomb_damage(
     command: &command::UseAbility,
 ) -> ExecuteContext {
     let ability = Ability::ExplodeDamage;
-    throw_bomb(state, command, &"bomb_damage".into(), Rounds(1), ability)
+    throw_bomb(state, command, Rounds(1), ability)
 }
 
 fn execute_use_ability_bomb_fire(
@@ -1382,7 +1743,7 @@ fn execute_use_ability_bomb_fire(
     command: &command::UseAbility,
 ) -> ExecuteContext {
     let ability = Ability::ExplodeFire;
-    throw_bomb(state, command, &"bomb_fire".into(), Rounds(1), ability)
+    throw_bomb(state, command, Rounds(1), ability)
 }
 
 fn execute_use_ability_bomb_poison(
@@ -1390,7 +1751,7 @@ fn execute_use_ability_bomb_poison(
     command: &command::UseAbility,
 ) -> ExecuteContext {
     let ability = Ability::ExplodePoison;
-    throw_bomb(state, command, &"bomb_poison".into(), Rounds(1), ability)
+    throw_bomb(state, command, Rounds(1), ability)
 }
 
 fn execute_use_ability_bomb_demonic(
@@ -1398,7 +1759,7 @@ fn execute_use_ability_bomb_demonic(
     command: &command::UseAbility,
 ) -> ExecuteContext {
     let ability = Ability::ExplodeDamage;
-    throw_bomb(state, command, &"bomb_demonic".into(), Rounds(1), ability)
+    throw_bomb(state, command, Rounds(1), ability)
 }
 
 fn execute_use_ability_summon(
@@ -1408,12 +1769,11 @@ fn execute_use_ability_summon(
 ) -> ExecuteContext {
     let mut context = ExecuteContext::default();
     let max_summoned_count = state.parts().summoner.get(&command.id).unwrap().count;
-    let available_typenames: [ObjType; 3] = ["imp".into(), "toxic_imp".into(), "imp_bomber".into()];
 
     let mut new_agents = Vec::new();
     for pos in state::free_neighbor_positions(state, command.pos, max_summoned_count as usize) {
-        let i = rng.gen_range(0, 3) as usize;
-        let prototype = available_typenames[i].clone();
+        let i = rng.gen_range(0, SUMMONED.len() as i32) as usize;
+        let prototype: ObjType = SUMMONED[i].into();
 
         let effect_create = effect_create_agent(state, &prototype, state.player_id(), pos);
         let id = state.alloc_id();
@@ -1430,22 +1790,97 @@ fn execute_use_ability_summon(
     context
 }
 
-fn execute_use_ability_bloodlust(
-    state: &mut State,
+/// Objects in the ability's area that its effects apply to.
+fn ability_targets(
+    state: &State,
     command: &command::UseAbility,
-) -> ExecuteContext {
+    target: Target,
+    area: Area,
+) -> Vec<Id> {
+    let parts = state.parts();
+    // Bombs don't belong to anyone, so they have no allies.
+    let player_id = parts.belongs_to.get(&command.id).map(|owner| owner.0);
+    let is_ally = |id: &Id| match (player_id, parts.belongs_to.get(id)) {
+        (Some(player_id), Some(owner)) => state::are_allies(state, player_id, owner.0),
+        _ => false,
+    };
+    let mut ids: Vec<Id> = parts
+        .pos
+        .iter()
+        .filter(|(_, pos)| map::distance_hex(command.pos, pos.0) <= area.radius())
+        .map(|(&id, _)| id)
+        .filter(|id| match target {
+            Target::Object => parts.blocker.contains_key(id),
+            Target::Agent | Target::EmptyTile => parts.agent.contains_key(id),
+            Target::Actor | Target::Ally => parts.agent.contains_key(id) && is_ally(id),
+            Target::Enemy => parts.agent.contains_key(id) && !is_ally(id),
+        })
+        .collect();
+    ids.sort();
+    ids
+}
+
+fn execute_ability_effects(state: &State, command: &command::UseAbility) -> ExecuteContext {
     let mut context = ExecuteContext::default();
-    let id = get_id_by_pos(state, command.pos);
-    if let Some(x) = state.parts().belongs_to.get(&id) {
-        let phase = Phase::from(x.0 .0);
-        let effect = effect::Timed {
-            duration: effect::Duration::Rounds(Rounds(3)),
-            phase,
-            effect: effect::Lasting::Bloodlust,
+    let definition = state.ability_definition(&command.ability);
+    if definition.effects.is_empty() {
+        return context;
+    }
+    // Knockbacks push the objects away from the actor or the area's center.
+    let actor_pos = state.pos(&command.id).0;
+    for id in ability_targets(state, command, definition.target, definition.area) {
+        let pos = state.pos(&id).0;
+        let pusher_pos = if pos == command.pos {
+            actor_pos
+        } else {
+            command.pos
         };
-        context.timed_effects.push((id, vec![effect]));
+        let is_agent = state.parts().agent.contains_key(&id);
+        let mut instant_effects = Vec::new();
+        let mut timed_effects = Vec::new();
+        for effect in &definition.effects {
+            match *effect {
+                definitions::Effect::Knockback(weight) => {
+                    let strength = PushStrength(weight);
+                    if map::distance_hex(pusher_pos, pos) != 1
+                        || !strength.can_push(state.blocker(&id).weight)
+                    {
+                        continue;
+                    }
+                    let to = Dir::get_dir_pos(pusher_pos, pos);
+                    if state.map().is_inboard(to) && !state::is_tile_blocked(state, to) {
+                        let from = pos;
+                        instant_effects.push(effect::Knockback { from, to, strength }.into());
+                        context.moved_actor_ids.push(id);
+                    }
+                }
+                _ if !is_agent => {}
+                definitions::Effect::Wound(damage) => {
+                    instant_effects.push(wound_or_kill(state, &id, damage));
+                }
+                definitions::Effect::Heal(strength) => {
+                    instant_effects.push(effect::Heal { strength }.into());
+                }
+                definitions::Effect::Stun => instant_effects.push(Effect::Stun),
+                definitions::Effect::Lasting(effect, rounds) => {
+                    let owner = state.belongs_to(&id).0;
+                    timed_effects.push(effect::Timed {
+                        duration: effect::Duration::Rounds(rounds),
+                        phase: Phase::from(owner.0),
+                        effect,
+                        source: Some(command.id),
+                    });
+                }
+            }
+        }
+        if !instant_effects.is_empty() {
+            context.instant_effects.push((id, instant_effects));
+        }
+        if !timed_effects.is_empty() {
+            context.timed_effects.push((id, timed_effects));
+        }
+        context.actor_ids.push(id);
     }
-    context.actor_ids.push(id);
     context
 }
 
@@ -1456,33 +1891,35 @@ fn execute_use_ability(
     rng: &mut SimpleRng,
 ) {
     let mut context = match command.ability {
-        Ability::Knockback => execute_use_ability_knockback(state, command),
-        Ability::Club => execute_use_ability_club(state, command),
         Ability::Jump => execute_use_ability_jump(state, command),
         Ability::LongJump => execute_use_ability_long_jump(state, command),
         Ability::Dash => execute_use_ability_dash(state, command),
         Ability::Rage => execute_use_ability_rage(state, command),
-        Ability::Heal => execute_use_ability_heal(state, command, Strength(2)),
-        Ability::GreatHeal => execute_use_ability_heal(state, command, Strength(3)),
         Ability::Vanish => execute_use_ability_vanish(command),
         Ability::ExplodeFire => execute_use_ability_explode_fire(state, command),
         Ability::ExplodePoison => execute_use_ability_explode_poison(state, command),
         Ability::ExplodePush => execute_use_ability_explode_push(state, command),
         Ability::ExplodeDamage => execute_use_ability_explode_damage(state, command),
-        Ability::Poison => execute_use_ability_poison(state, command),
         Ability::Bomb => execute_use_ability_bomb_damage(state, command),
         Ability::BombPush => execute_use_ability_bomb_push(state, command),
         Ability::BombFire => execute_use_ability_bomb_fire(state, command),
         Ability::BombPoison => execute_use_ability_bomb_poison(state, command),
         Ability::BombDemonic => execute_use_ability_bomb_demonic(state, command),
         Ability::Summon => execute_use_ability_summon(state, command, rng),
-        Ability::Bloodlust => execute_use_ability_bloodlust(state, command),
+        Ability::Knockback
+        | Ability::Club
+        | Ability::Poison
+        | Ability::Heal
+        | Ability::GreatHeal
+        | Ability::Bloodlust
+        | Ability::Custom(_) => ExecuteContext::default(),
     };
+    context.merge_with(execute_ability_effects(state, command));
     context.actor_ids.push(command.id);
     let active_event = event::UseAbility {
         id: command.id,
         pos: command.pos,
-        ability: command.ability,
+        ability: command.ability.clone(),
     }
     .into();
     let event = Event {
@@ -1513,22 +1950,45 @@ fn execute_use_ability(
     // }
 }
 
-pub fn hit_chance(state: &State, attacker_id: Id, _target_id: Id) -> (i32, i32) {
+pub fn hit_chance(state: &State, attacker_id: Id, target_id: Id) -> (i32, i32) {
     let agent_attacker = state.agent(&attacker_id);
     let attack_strength = state.strength(&attacker_id);
+    let target_dodge = target_dodge(state, attacker_id, target_id);
 
     let attacker_wounds = utils::clamp_max(
         attack_strength.base_strength.0 - attack_strength.strength.0,
         3,
     );
 
-    let k_min = agent_attacker.attack_accuracy.0 - attacker_wounds;
+    let side_bonus = state::attack_side(state, &attacker_id, &target_id).accuracy_bonus();
+    let k_min = state::attack_accuracy(state, &attacker_id).0 + side_bonus.0
+        - attacker_wounds
+        - target_dodge.0;
     let k_max = k_min + agent_attacker.attack_strength.0;
     (k_min, k_max)
 }
 
 #[cfg(test)]
 mod tests {
+    #[cfg(not(feature = "event"))]
+    use crate::{
+        battle::{
+            ability::{
+                definitions::{Area, Definition, Effect as AbilityEffect, Range, Target},
+                Ability, PassiveAbility,
+            },
+            command,
+            component::{Abilities, PassiveAbilities},
+            event::{ActiveEvent, Event},
+            heroes::{Hero, HeroId, HeroObject, Perk},
+            movement::Path,
+            scenario::{Object, Scenario},
+            state, Moves, Phase, PlayerId, PushStrength, Rounds, State, Strength, TileType, Weight,
+        },
+        campaign,
+        map::{dirs, Dir},
+        utils::SimpleRng,
+    };
     use crate::{
         battle::{
             effect::{self, Effect},
@@ -1538,6 +1998,12 @@ mod tests {
     };
 
     use super::ExecuteContext;
+    #[cfg(not(feature = "event"))]
+    use super::{
+        do_event, execute, execute_effects, execute_use_ability, hit_chance, target_armor,
+        try_execute_passive_abilities_on_attack, try_execute_passive_ability_burn, wound_or_kill,
+        wound_or_kill_by,
+    };
 
     // TODO: Don't create Id's manually? Use a mocked State instead.
 
@@ -1594,4 +2060,511 @@ mod tests {
         context1.merge_with(context2);
         assert_eq!(context_expected, context1);
     }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_enter_damaging_tile() {
+        let (mut state, id) = state::state_with_swordsman();
+        let pos = state.pos(&id).0;
+        let next = dirs()
+            .map(|dir| Dir::get_neighbor_pos(pos, dir))
+            .find(|&pos| state::is_tile_plain_and_completely_free(&state, pos))
+            .unwrap();
+        state.map.set_tile(next, TileType::Lava);
+        let command = command::MoveTo {
+            id,
+            path: Path::new(vec![pos, next]),
+        };
+        let mut rng = SimpleRng::seed_from_u32(0);
+        execute(&mut state, &command.into(), &mut rng).unwrap();
+        assert_eq!(state.pos(&id).0, next);
+        assert_eq!(state.strength(&id).strength, Strength(1));
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_walk_through_damaging_tile() {
+        let start = PosHex { q: 0, r: 0 };
+        let lava = PosHex { q: 1, r: 0 };
+        let end = PosHex { q: 2, r: 0 };
+        let mut state = state::state_with_objects(&[
+            (0, "swordsman", start),
+            (1, "imp", PosHex { q: -3, r: 0 }),
+        ]);
+        state.map.set_tile(lava, TileType::Lava);
+        let id = state::agent_ids_at(&state, start)[0];
+        let command = command::MoveTo {
+            id,
+            path: Path::new(vec![start, lava, end]),
+        };
+        let mut rng = SimpleRng::seed_from_u32(0);
+        execute(&mut state, &command.into(), &mut rng).unwrap();
+        assert_eq!(state.pos(&id).0, end);
+        assert_eq!(state.strength(&id).strength, Strength(1));
+        assert_eq!(state.agent(&id).moves, Moves(0));
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_spawn_poison_cloud_on_death() {
+        let pos = PosHex { q: 0, r: 0 };
+        let mut state = state::state_with_objects(&[(1, "imp", pos)]);
+        let id = state::agent_ids_at(&state, pos)[0];
+        let abilities = PassiveAbilities(vec![PassiveAbility::SpawnPoisonCloudOnDeath]);
+        state.parts.passive_abilities.insert(id, abilities);
+        let event = Event {
+            active_event: ActiveEvent::Create,
+            actor_ids: Vec::new(),
+            instant_effects: vec![(id, vec![effect::Kill { attacker_pos: None }.into()])],
+            timed_effects: Vec::new(),
+            scheduled_abilities: Vec::new(),
+        };
+        do_event(&mut state, &event);
+        assert!(state::agent_ids_at(&state, pos).is_empty());
+        assert!(state::obj_with_passive_ability_at(&state, pos, PassiveAbility::Poison).is_some());
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_heavy_impact_collision() {
+        let attacker_pos = PosHex { q: -1, r: 0 };
+        let target_pos = PosHex { q: 0, r: 0 };
+        let landing_pos = PosHex { q: 1, r: 0 };
+        let heavy_impact = || PassiveAbilities(vec![PassiveAbility::HeavyImpact]);
+
+        // The target flies off and crashes into every blocker next to the landing tile.
+        let obstacle_pos = PosHex { q: 2, r: 0 };
+        let boulder_pos = PosHex { q: 1, r: -1 };
+        let mut state = state::state_with_objects(&[
+            (0, "hammerman", attacker_pos),
+            (1, "imp", target_pos),
+            (1, "imp", obstacle_pos),
+            (1, "boulder", boulder_pos),
+        ]);
+        let attacker_id = state::agent_ids_at(&state, attacker_pos)[0];
+        let target_id = state::agent_ids_at(&state, target_pos)[0];
+        let obstacle_id = state::agent_ids_at(&state, obstacle_pos)[0];
+        let boulder_id = state::blocker_id_at_opt(&state, boulder_pos).unwrap();
+        state
+            .parts
+            .passive_abilities
+            .insert(attacker_id, heavy_impact());
+        let context =
+            try_execute_passive_abilities_on_attack(&state, attacker_id, target_id, Strength(0));
+        let crash: Effect = effect::Collision {
+            flyer_pos: landing_pos,
+        }
+        .into();
+        let fly_off = effect::FlyOff {
+            from: target_pos,
+            to: landing_pos,
+            strength: PushStrength(Weight::Normal),
+        };
+        let obstacle_wound = effect::Wound {
+            damage: Strength(1),
+            absorbed: Strength(0),
+            attacker_pos: Some(landing_pos),
+        };
+        let target_wound = effect::Wound {
+            damage: Strength(2),
+            absorbed: Strength(0),
+            attacker_pos: Some(boulder_pos),
+        };
+        let expected = [
+            (obstacle_id, vec![crash.clone(), obstacle_wound.into()]),
+            (boulder_id, vec![crash]),
+            (target_id, vec![fly_off.into(), target_wound.into()]),
+        ];
+        assert_eq!(context.instant_effects.len(), expected.len());
+        for entry in &expected {
+            assert!(context.instant_effects.contains(entry));
+        }
+
+        // The landing tile is taken, so the target stays and crashes into its occupant.
+        // The collision finishes off the target that the attack has already wounded.
+        let mut state = state::state_with_objects(&[
+            (0, "hammerman", attacker_pos),
+            (1, "imp", target_pos),
+            (1, "imp", landing_pos),
+        ]);
+        let attacker_id = state::agent_ids_at(&state, attacker_pos)[0];
+        let target_id = state::agent_ids_at(&state, target_pos)[0];
+        let obstacle_id = state::agent_ids_at(&state, landing_pos)[0];
+        state
+            .parts
+            .passive_abilities
+            .insert(attacker_id, heavy_impact());
+        let context =
+            try_execute_passive_abilities_on_attack(&state, attacker_id, target_id, Strength(2));
+        let crash = effect::Collision {
+            flyer_pos: target_pos,
+        };
+        let wound = effect::Wound {
+            damage: Strength(1),
+            absorbed: Strength(0),
+            attacker_pos: Some(target_pos),
+        };
+        let kill = effect::Kill {
+            attacker_pos: Some(landing_pos),
+        };
+        let expected = vec![
+            (obstacle_id, vec![crash.into(), wound.into()]),
+            (target_id, vec![kill.into()]),
+        ];
+        assert_eq!(context.instant_effects, expected);
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_custom_area_heal() {
+        let pos = PosHex { q: 0, r: 0 };
+        let ally_pos = PosHex { q: 1, r: 0 };
+        let enemy_pos = PosHex { q: -1, r: 0 };
+        let mut state = state::state_with_objects(&[
+            (0, "swordsman", pos),
+            (0, "swordsman", ally_pos),
+            (1, "imp", enemy_pos),
+        ]);
+        let ability = Ability::Custom("healing_circle".into());
+        let definition = Definition {
+            title: "Healing Circle".into(),
+            description: Vec::new(),
+            cooldown: Rounds(2),
+            range: Range::default(),
+            line_of_sight: false,
+            target: Target::Actor,
+            area: Area::Circle(1),
+            effects: vec![AbilityEffect::Heal(Strength(1))],
+        };
+        state
+            .ability_definitions
+            .0
+            .insert(ability.clone(), definition);
+        let id = state::agent_ids_at(&state, pos)[0];
+        let abilities = Abilities(vec![ability.clone().into()]);
+        state.parts.abilities.insert(id, abilities);
+        let ids: Vec<_> = [pos, ally_pos, enemy_pos]
+            .iter()
+            .map(|&pos| state::agent_ids_at(&state, pos)[0])
+            .collect();
+        for id in &ids {
+            state.parts.strength.get_mut(id).unwrap().strength = Strength(1);
+        }
+        let command = command::UseAbility { id, pos, ability };
+        execute(
+            &mut state,
+            &command.into(),
+            &mut SimpleRng::seed_from_u32(0),
+        )
+        .unwrap();
+        let strength = |id| state.strength(id).strength;
+        assert_eq!(strength(&ids[0]), Strength(2));
+        assert_eq!(strength(&ids[1]), Strength(2));
+        assert_eq!(strength(&ids[2]), Strength(1));
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_ownerless_area_effects() {
+        let bomb_pos = PosHex { q: 0, r: 0 };
+        let ally_pos = PosHex { q: 1, r: 0 };
+        let enemy_pos = PosHex { q: -1, r: 0 };
+        let mut state =
+            state::state_with_objects(&[(0, "swordsman", ally_pos), (1, "imp", enemy_pos)]);
+        let mut rng = SimpleRng::seed_from_u32(0);
+        let create = command::Create {
+            prototype: "bomb_poison".into(),
+            pos: bomb_pos,
+            owner: None,
+        };
+        execute(&mut state, &create.into(), &mut rng).unwrap();
+        let bomb_id = state::blocker_id_at(&state, bomb_pos);
+        let definitions = &mut state.ability_definitions.0;
+        let definition = definitions.get_mut(&Ability::ExplodePoison).unwrap();
+        definition.target = Target::Enemy;
+        definition.area = Area::Circle(1);
+        definition.effects = vec![AbilityEffect::Wound(Strength(1))];
+        let ids: Vec<_> = [ally_pos, enemy_pos]
+            .iter()
+            .map(|&pos| state::agent_ids_at(&state, pos)[0])
+            .collect();
+        let strengths: Vec<_> = ids.iter().map(|id| state.strength(id).strength).collect();
+        let command = command::UseAbility {
+            id: bomb_id,
+            pos: bomb_pos,
+            ability: Ability::ExplodePoison,
+        };
+        execute_use_ability(&mut state, &command, &mut rng);
+        for (id, strength) in ids.iter().zip(strengths) {
+            assert_eq!(state.strength(id).strength, Strength(strength.0 - 1));
+        }
+    }
+
+    #[cfg(not(feature = "event"))]
+    fn add_lasting_effect(state: &mut State, id: Id, effect: effect::Lasting) {
+        let timed = effect::Timed {
+            duration: effect::Duration::Rounds(Rounds(2)),
+            phase: Phase::from(state.belongs_to(&id).0 .0),
+            effect,
+            source: None,
+        };
+        let event = Event {
+            active_event: ActiveEvent::Create,
+            actor_ids: Vec::new(),
+            instant_effects: Vec::new(),
+            timed_effects: vec![(id, vec![timed])],
+            scheduled_abilities: Vec::new(),
+        };
+        do_event(state, &event);
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_shield_absorbs_next_wound() {
+        let pos = PosHex { q: 0, r: 0 };
+        let mut state = state::state_with_objects(&[(0, "swordsman", pos)]);
+        let id = state::agent_ids_at(&state, pos)[0];
+        let strength = state.strength(&id).strength;
+        add_lasting_effect(&mut state, id, effect::Lasting::Shielded);
+        let attacks = state.agent(&id).attacks;
+        let wound = wound_or_kill(&state, &id, strength);
+        let absorbed = effect::Wound {
+            damage: Strength(0),
+            absorbed: strength,
+            attacker_pos: None,
+        };
+        assert_eq!(wound, absorbed.into());
+        let event = Event {
+            active_event: ActiveEvent::Create,
+            actor_ids: Vec::new(),
+            instant_effects: vec![(id, vec![wound])],
+            timed_effects: Vec::new(),
+            scheduled_abilities: Vec::new(),
+        };
+        do_event(&mut state, &event);
+        assert_eq!(state.strength(&id).strength, strength);
+        assert_eq!(state.agent(&id).attacks, attacks);
+        assert!(!state::has_lasting_effect(
+            &state,
+            &id,
+            effect::Lasting::Shielded
+        ));
+        let kill = effect::Kill { attacker_pos: None };
+        assert_eq!(wound_or_kill(&state, &id, strength), kill.into());
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_fire_sets_agents_on_fire() {
+        let pos = PosHex { q: 0, r: 0 };
+        let mut state = state::state_with_objects(&[(0, "swordsman", pos)]);
+        let id = state::agent_ids_at(&state, pos)[0];
+        let context = try_execute_passive_ability_burn(&mut state, id);
+        assert_eq!(context.timed_effects.len(), 1);
+        assert_eq!(
+            context.timed_effects[0].1[0].effect,
+            effect::Lasting::Burning
+        );
+        add_lasting_effect(&mut state, id, effect::Lasting::Burning);
+        let strength = state.strength(&id).strength;
+        execute_effects(&mut state);
+        assert_eq!(state.strength(&id).strength, Strength(strength.0 - 1));
+        // Unlike poison, burning can kill.
+        state.parts.strength.get_mut(&id).unwrap().strength = Strength(1);
+        execute_effects(&mut state);
+        assert!(!state.parts().is_exist(&id));
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_human_loses_when_wiped_out() {
+        let human_pos = PosHex { q: 0, r: 0 };
+        let mut scenario = Scenario {
+            players_count: 3,
+            ..Scenario::default()
+        };
+        for (owner, q) in [(0, 0), (1, 2), (2, -2)] {
+            scenario.objects.push(Object {
+                owner: Some(PlayerId(owner)),
+                typename: "swordsman".into(),
+                pos: PosHex { q, r: 0 },
+            });
+        }
+        let mut rng = SimpleRng::seed_from_u32(0);
+        let mut state = state::state_with_scenario(scenario, &mut rng);
+        let id = state::agent_ids_at(&state, human_pos)[0];
+        state.parts_mut().remove(&id);
+        execute(&mut state, &command::EndTurn.into(), &mut rng).unwrap();
+        let result = state.battle_result().as_ref().unwrap();
+        assert!(!result.is_won_by(PlayerId(0)));
+        assert_eq!(result.winner_ids, vec![PlayerId(1), PlayerId(2)]);
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_burning_does_not_tick_in_fire() {
+        let pos = PosHex { q: 0, r: 0 };
+        let fire_pos = PosHex { q: 1, r: 0 };
+        let mut state = state::state_with_objects(&[(0, "swordsman", pos), (0, "fire", fire_pos)]);
+        let id = state::agent_ids_at(&state, pos)[0];
+        let fire_id = state::obj_with_passive_ability_at(&state, fire_pos, PassiveAbility::Burn);
+        state.parts.pos.get_mut(&fire_id.unwrap()).unwrap().0 = pos;
+        add_lasting_effect(&mut state, id, effect::Lasting::Burning);
+        let strength = state.strength(&id).strength;
+        execute_effects(&mut state);
+        assert_eq!(state.strength(&id).strength, strength);
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_armor_absorbs_damage() {
+        let target_pos = PosHex { q: 0, r: 0 };
+        let swordsman_pos = PosHex { q: 1, r: 0 };
+        let hammerman_pos = PosHex { q: -1, r: 0 };
+        let spearman_pos = PosHex { q: 0, r: 1 };
+        let state = state::state_with_objects(&[
+            (1, "heavy_swordsman", target_pos),
+            (0, "swordsman", swordsman_pos),
+            (0, "hammerman", hammerman_pos),
+            (0, "elite_spearman", spearman_pos),
+        ]);
+        let id_at = |pos| state::agent_ids_at(&state, pos)[0];
+        let target_id = id_at(target_pos);
+        let armor = |pos| target_armor(&state, id_at(pos), target_id);
+        assert_eq!(armor(swordsman_pos), Strength(1));
+        // Hammers aren't resisted and the elite spearman's attacks pierce the armor.
+        assert_eq!(armor(hammerman_pos), Strength(0));
+        assert_eq!(armor(spearman_pos), Strength(0));
+        let wound = wound_or_kill_by(&state, &target_id, Strength(2), Strength(1), None);
+        let expected = effect::Wound {
+            damage: Strength(1),
+            absorbed: Strength(1),
+            attacker_pos: None,
+        };
+        assert_eq!(wound, expected.into());
+        let wound = wound_or_kill_by(&state, &target_id, Strength(1), Strength(3), None);
+        let expected = effect::Wound {
+            damage: Strength(0),
+            absorbed: Strength(1),
+            attacker_pos: None,
+        };
+        assert_eq!(wound, expected.into());
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_flank_and_rear_attacks() {
+        let target_pos = PosHex { q: 0, r: 0 };
+        let front_pos = Dir::get_neighbor_pos(target_pos, Dir::West);
+        let flank_pos = Dir::get_neighbor_pos(target_pos, Dir::NorthEast);
+        let rear_pos = Dir::get_neighbor_pos(target_pos, Dir::East);
+        let state = state::state_with_objects(&[
+            (1, "swordsman", target_pos),
+            (0, "swordsman", front_pos),
+            (0, "swordsman", flank_pos),
+            (0, "swordsman", rear_pos),
+        ]);
+        let id_at = |pos| state::agent_ids_at(&state, pos)[0];
+        let target_id = id_at(target_pos);
+        // The enemy in the center of the map looks towards player 0's side.
+        assert_eq!(state.parts().facing[&target_id].0, Dir::West);
+        let side = |pos| state::attack_side(&state, &id_at(pos), &target_id);
+        assert_eq!(side(front_pos), state::AttackSide::Front);
+        assert_eq!(side(flank_pos), state::AttackSide::Flank);
+        assert_eq!(side(rear_pos), state::AttackSide::Rear);
+        let chance = |pos| hit_chance(&state, id_at(pos), target_id).0;
+        assert_eq!(chance(flank_pos), chance(front_pos) + 1);
+        assert_eq!(chance(rear_pos), chance(front_pos) + 2);
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_no_reaction_to_rear_attacks() {
+        let target_pos = PosHex { q: 0, r: 0 };
+        let flank_pos = Dir::get_neighbor_pos(target_pos, Dir::NorthEast);
+        let rear_pos = Dir::get_neighbor_pos(target_pos, Dir::East);
+        let mut state = state::state_with_objects(&[
+            (1, "swordsman", target_pos),
+            (0, "swordsman", flank_pos),
+            (0, "swordsman", rear_pos),
+        ]);
+        let mut rng = SimpleRng::seed_from_u32(0);
+        let id_at = |state: &State, pos| state::agent_ids_at(state, pos)[0];
+        let target_id = id_at(&state, target_pos);
+        state.parts.strength.get_mut(&target_id).unwrap().strength = Strength(10);
+        // Enough attacks to react even after being wounded.
+        state.parts.agent.get_mut(&target_id).unwrap().attacks.0 = 3;
+        let attack = |state: &mut State, rng: &mut SimpleRng, pos| {
+            let command = command::Attack {
+                attacker_id: id_at(state, pos),
+                target_id,
+            };
+            execute(state, &command.into(), rng).unwrap();
+        };
+        let facing = |state: &State, id| state.parts().facing[&id].0;
+        attack(&mut state, &mut rng, rear_pos);
+        // The attacker turns to face its target, but the target doesn't react.
+        assert_eq!(facing(&state, id_at(&state, rear_pos)), Dir::West);
+        assert_eq!(facing(&state, target_id), Dir::West);
+        // A reactive attack from the flank turns the target around.
+        attack(&mut state, &mut rng, flank_pos);
+        assert_eq!(facing(&state, target_id), Dir::NorthEast);
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_create_veterans() {
+        let scenario = campaign::campaign_01().nodes()[0]
+            .scenario()
+            .unwrap()
+            .clone();
+        let mut rng = SimpleRng::seed_from_u32(0);
+        let mut state = state::state_with_scenario(scenario, &mut rng);
+        let perks = vec![Perk::Tough, Perk::Accurate];
+        let veteran = HeroObject {
+            wounds: Strength(2),
+            ..HeroObject::veteran(Hero::Swordsman, HeroId(7), perks)
+        };
+        let recruit = HeroObject::new(Hero::Swordsman, 1);
+        state.create_heroes(&[veteran, recruit], &mut rng);
+        let ids = state::players_agent_ids(&state, PlayerId(0));
+        assert_eq!(ids.len(), 2);
+        let heroes = state::players_heroes(&state, PlayerId(0));
+        assert_eq!(heroes.len(), 1);
+        assert_eq!(heroes[0].id, HeroId(7));
+        let (veteran_id, recruit_id) = if state.parts().hero.contains_key(&ids[0]) {
+            (ids[0], ids[1])
+        } else {
+            (ids[1], ids[0])
+        };
+        let strength = |id| state.strength(&id).base_strength.0;
+        assert_eq!(strength(veteran_id), strength(recruit_id) + 1);
+        let accuracy = |id| state.agent(&id).attack_accuracy.0;
+        assert_eq!(accuracy(veteran_id), accuracy(recruit_id) + 1);
+        // The veteran is still recovering from the wounds.
+        let current = |id| state.strength(&id).strength.0;
+        assert_eq!(current(veteran_id), current(recruit_id) - 1);
+        assert_eq!(heroes[0].wounds(), Strength(2));
+    }
+
+    #[cfg(not(feature = "event"))]
+    #[test]
+    fn test_facing_follows_movement() {
+        let from = PosHex { q: -2, r: 0 };
+        let mut state = state::state_with_objects(&[(0, "swordsman", from)]);
+        let id = state::agent_ids_at(&state, from)[0];
+        assert_eq!(state.parts().facing[&id].0, Dir::SouthEast);
+        let to = Dir::get_neighbor_pos(from, Dir::NorthWest);
+        let command = command::MoveTo {
+            id,
+            path: Path::new(vec![from, to]),
+        };
+        execute(
+            &mut state,
+            &command.into(),
+            &mut SimpleRng::seed_from_u32(0),
+        )
+        .unwrap();
+        assert_eq!(state.parts().facing[&id].0, Dir::NorthWest);
+    }
 }
@@ -9,17 +9,19 @@ pub use crate::{
 
 pub mod ability;
 pub mod ai;
+pub mod check;
 pub mod command;
 pub mod component;
+pub mod data;
 pub mod effect;
 pub mod event;
 pub mod execute;
 pub mod heroes;
 pub mod movement;
+pub mod prototypes;
 pub mod scenario;
 pub mod state;
-
-mod check;
+pub mod tiles;
 
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct PlayerId(pub i32);
@@ -129,10 +131,31 @@ pub struct Accuracy(pub i32);
 #[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, PartialOrd)]
 pub struct Dodge(pub i32);
 
-#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum TileType {
     #[default]
     Plain,
 
     Rocks,
+
+    Water,
+
+    Forest,
+
+    Swamp,
+
+    Lava,
+}
+
+impl TileType {
+    pub fn all() -> [TileType; 6] {
+        [
+            TileType::Plain,
+            TileType::Rocks,
+            TileType::Water,
+            TileType::Forest,
+            TileType::Swamp,
+            TileType::Lava,
+        ]
+    }
 }
@@ -3,7 +3,7 @@ use std::{collections::VecDeque, slice::Windows};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    battle::{ability::PassiveAbility, state, Id, State, TileType},
+    battle::{ability::PassiveAbility, state, Id, State},
     map::{dirs, Dir, HexMap, PosHex},
 };
 
@@ -56,10 +56,7 @@ pub fn tile_cost(state: &State, _: Id, _: PosHex, pos: PosHex) -> MovePoints {
         }
     }
     // just tile's cost
-    match state.map().tile(pos) {
-        TileType::Plain => MovePoints(1),
-        TileType::Rocks => MovePoints(3),
-    }
+    state.tile_info(pos).move_cost
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -85,11 +82,10 @@ impl Path {
     }
 
     pub fn truncate(&self, state: &State, id: Id) -> Option<Self> {
-        let agent = state.agent(&id);
         let mut new_path = Vec::new();
         let mut cost = MovePoints(0);
         new_path.push(self.tiles[0]);
-        let move_points = agent.move_points;
+        let move_points = state::move_points(state, &id);
         for Step { from, to } in self.steps() {
             cost.0 += tile_cost(state, id, from, to).0;
             if cost > move_points {
@@ -199,7 +195,10 @@ impl Pathfinder {
         assert!(self.map.is_inboard(pos));
         for dir in dirs() {
             let neighbor_pos = Dir::get_neighbor_pos(pos, dir);
-            if self.map.is_inboard(neighbor_pos) && !state::is_tile_blocked(state, neighbor_pos) {
+            if self.map.is_inboard(neighbor_pos)
+                && !state::is_tile_blocked(state, neighbor_pos)
+                && state::is_tile_passable_for(state, id, neighbor_pos)
+            {
                 self.process_neighbor_pos(state, id, pos, neighbor_pos);
             }
         }
@@ -0,0 +1,679 @@
+//! Object prototypes.
+//!
+//! Prototypes are stored as a RON map from object types to entries:
+//!
+//! - `extends`: an optional object type to inherit the components from;
+//! - `components`: a list of components, like `[Blocker(()), Strength((strength: 3))]`.
+//!   Only `Blocker`, `Strength`, `Agent`, `Armor`, `Summoner`, `Abilities`
+//!   and `PassiveAbilities` are allowed, the rest are added by the engine.
+//!
+//! A component that is already inherited is merged field by field,
+//! so `Agent((attacks: 1))` only changes the number of attacks.
+//! `Abilities` and `PassiveAbilities` lists are replaced as a whole.
+//! Missing `base_*` fields of `Agent` are the same as the initial values,
+//! missing `armor_piercing` and `Armor` fields are zero.
+//!
+//! `from_ron` resolves the inheritance and validates the result.
+//! The engine only deals with the resolved `Prototypes`.
+
+use std::{collections::HashMap, fmt};
+
+use risc0_zkvm::sha::Digest;
+use serde::{Deserialize, Serialize};
+
+use crate::battle::{
+    self,
+    ability::Ability,
+    component::{
+        Abilities, Agent, Armor, Blocker, Component, ObjType, PassiveAbilities, Strength, Summoner,
+        WeaponType,
+    },
+    data,
+    execute::{created_by_ability, created_by_passive_ability},
+    Accuracy, Attacks, Jokers, MovePoints, Moves, Weight,
+};
+
+/// Components of every object type with the inheritance already resolved.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Prototypes(pub HashMap<ObjType, Vec<Component>>);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Error {
+    UnknownPrototype(ObjType),
+    InheritanceCycle,
+    DuplicateComponent,
+
+    /// Components like `Pos` or `BelongsTo` are added when an object is created.
+    UnexpectedComponent,
+
+    /// Agents must also have `Strength` and `Blocker`.
+    MissingComponent,
+
+    MissingField,
+    OutOfRange(i32),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownPrototype(name) => write!(f, "there's no prototype '{}'", name.0),
+            Error::InheritanceCycle => write!(f, "the prototype inherits from itself"),
+            Error::DuplicateComponent => write!(f, "the component is listed twice"),
+            Error::UnexpectedComponent => write!(f, "the component is added by the engine"),
+            Error::MissingComponent => write!(f, "agents must have this component"),
+            Error::MissingField => write!(f, "the field is missing"),
+            Error::OutOfRange(n) => write!(f, "{} is out of range", n),
+        }
+    }
+}
+
+pub type ValidationError = data::ValidationError<Error>;
+
+pub type LoadError = data::LoadError<Error>;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    #[serde(default)]
+    extends: Option<ObjType>,
+
+    #[serde(default)]
+    components: Vec<Patch>,
+}
+
+/// A component with some of the fields possibly missing.
+#[derive(Clone, Debug, Deserialize)]
+enum Patch {
+    Blocker(BlockerPatch),
+    Strength(StrengthPatch),
+    Agent(AgentPatch),
+    Armor(ArmorPatch),
+    Summoner(SummonerPatch),
+    Abilities(Abilities),
+    PassiveAbilities(PassiveAbilities),
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BlockerPatch {
+    weight: Option<Weight>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StrengthPatch {
+    strength: Option<battle::Strength>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AgentPatch {
+    moves: Option<Moves>,
+    attacks: Option<Attacks>,
+    jokers: Option<Jokers>,
+    attack_strength: Option<battle::Strength>,
+    attack_distance: Option<i32>,
+    attack_accuracy: Option<Accuracy>,
+    weapon_type: Option<WeaponType>,
+    armor_piercing: Option<battle::Strength>,
+    move_points: Option<MovePoints>,
+    reactive_attacks: Option<Attacks>,
+    base_moves: Option<Moves>,
+    base_attacks: Option<Attacks>,
+    base_jokers: Option<Jokers>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ArmorPatch {
+    slash: Option<battle::Strength>,
+    smash: Option<battle::Strength>,
+    pierce: Option<battle::Strength>,
+    claw: Option<battle::Strength>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SummonerPatch {
+    count: Option<u32>,
+}
+
+impl Patch {
+    fn name(&self) -> &'static str {
+        match self {
+            Patch::Blocker(_) => "Blocker",
+            Patch::Strength(_) => "Strength",
+            Patch::Agent(_) => "Agent",
+            Patch::Armor(_) => "Armor",
+            Patch::Summoner(_) => "Summoner",
+            Patch::Abilities(_) => "Abilities",
+            Patch::PassiveAbilities(_) => "PassiveAbilities",
+        }
+    }
+
+    /// Overrides the fields that are present in `other`, a patch of the same component.
+    fn merge(&mut self, other: Patch) {
+        match (self, other) {
+            (Patch::Blocker(a), Patch::Blocker(b)) => {
+                a.weight = b.weight.or(a.weight);
+            }
+            (Patch::Strength(a), Patch::Strength(b)) => {
+                a.strength = b.strength.or(a.strength);
+            }
+            (Patch::Agent(a), Patch::Agent(b)) => {
+                a.moves = b.moves.or(a.moves);
+                a.attacks = b.attacks.or(a.attacks);
+                a.jokers = b.jokers.or(a.jokers);
+                a.attack_strength = b.attack_strength.or(a.attack_strength);
+                a.attack_distance = b.attack_distance.or(a.attack_distance);
+                a.attack_accuracy = b.attack_accuracy.or(a.attack_accuracy);
+                a.weapon_type = b.weapon_type.or(a.weapon_type);
+                a.armor_piercing = b.armor_piercing.or(a.armor_piercing);
+                a.move_points = b.move_points.or(a.move_points);
+                a.reactive_attacks = b.reactive_attacks.or(a.reactive_attacks);
+                a.base_moves = b.base_moves.or(a.base_moves);
+                a.base_attacks = b.base_attacks.or(a.base_attacks);
+                a.base_jokers = b.base_jokers.or(a.base_jokers);
+            }
+            (Patch::Armor(a), Patch::Armor(b)) => {
+                a.slash = b.slash.or(a.slash);
+                a.smash = b.smash.or(a.smash);
+                a.pierce = b.pierce.or(a.pierce);
+                a.claw = b.claw.or(a.claw);
+            }
+            (Patch::Summoner(a), Patch::Summoner(b)) => {
+                a.count = b.count.or(a.count);
+            }
+            (this, other) => *this = other,
+        }
+    }
+
+    fn missing_fields(&self) -> Vec<&'static str> {
+        let fields: Vec<(&str, bool)> = match self {
+            Patch::Blocker(_)
+            | Patch::Armor(_)
+            | Patch::Abilities(_)
+            | Patch::PassiveAbilities(_) => Vec::new(),
+            Patch::Strength(p) => vec![("strength", p.strength.is_some())],
+            Patch::Agent(p) => vec![
+                ("moves", p.moves.is_some()),
+                ("attacks", p.attacks.is_some()),
+                ("jokers", p.jokers.is_some()),
+                ("attack_strength", p.attack_strength.is_some()),
+                ("attack_distance", p.attack_distance.is_some()),
+                ("attack_accuracy", p.attack_accuracy.is_some()),
+                ("weapon_type", p.weapon_type.is_some()),
+                ("move_points", p.move_points.is_some()),
+                ("reactive_attacks", p.reactive_attacks.is_some()),
+            ],
+            Patch::Summoner(p) => vec![("count", p.count.is_some())],
+        };
+        fields
+            .into_iter()
+            .filter(|(_, is_present)| !is_present)
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Returns `None` if some of the required fields are missing.
+    fn to_component(&self) -> Option<Component> {
+        let component = match self {
+            Patch::Blocker(p) => Blocker {
+                weight: p.weight.unwrap_or_default(),
+            }
+            .into(),
+            Patch::Strength(p) => Strength {
+                base_strength: p.strength?,
+                strength: p.strength?,
+            }
+            .into(),
+            Patch::Agent(p) => Agent {
+                moves: p.moves?,
+                attacks: p.attacks?,
+                jokers: p.jokers?,
+                attack_strength: p.attack_strength?,
+                attack_distance: p.attack_distance?,
+                attack_accuracy: p.attack_accuracy?,
+                weapon_type: p.weapon_type?,
+                armor_piercing: p.armor_piercing.unwrap_or_default(),
+                move_points: p.move_points?,
+                reactive_attacks: p.reactive_attacks?,
+                base_moves: p.base_moves.or(p.moves)?,
+                base_attacks: p.base_attacks.or(p.attacks)?,
+                base_jokers: p.base_jokers.or(p.jokers)?,
+            }
+            .into(),
+            Patch::Armor(p) => Armor {
+                slash: p.slash.unwrap_or_default(),
+                smash: p.smash.unwrap_or_default(),
+                pierce: p.pierce.unwrap_or_default(),
+                claw: p.claw.unwrap_or_default(),
+            }
+            .into(),
+            Patch::Summoner(p) => Summoner { count: p.count? }.into(),
+            Patch::Abilities(abilities) => abilities.clone().into(),
+            Patch::PassiveAbilities(abilities) => abilities.clone().into(),
+        };
+        Some(component)
+    }
+}
+
+/// Reads the prototypes, resolves the inheritance and validates the result.
+pub fn from_ron(s: &str) -> Result<Prototypes, LoadError> {
+    let entries: HashMap<ObjType, Entry> = ron::de::from_str(s)?;
+    let prototypes = resolve(&entries).map_err(LoadError::Invalid)?;
+    prototypes.validate().map_err(LoadError::Invalid)?;
+    Ok(prototypes)
+}
+
+fn sorted_names<T>(map: &HashMap<ObjType, T>) -> Vec<&ObjType> {
+    let mut names: Vec<_> = map.keys().collect();
+    names.sort_by_key(|name| &name.0);
+    names
+}
+
+/// Returns the entry's ancestors starting from the root and the entry itself.
+fn lineage<'a>(
+    entries: &'a HashMap<ObjType, Entry>,
+    name: &'a ObjType,
+) -> Result<Vec<&'a ObjType>, ValidationError> {
+    let mut lineage = vec![name];
+    let mut child = name;
+    while let Some(parent) = &entries[child].extends {
+        let path = format!("{}.extends", child.0);
+        if lineage.contains(&parent) {
+            let error = Error::InheritanceCycle;
+            return Err(ValidationError { path, error });
+        }
+        if !entries.contains_key(parent) {
+            let error = Error::UnknownPrototype(parent.clone());
+            return Err(ValidationError { path, error });
+        }
+        lineage.push(parent);
+        child = parent;
+    }
+    lineage.reverse();
+    Ok(lineage)
+}
+
+fn resolve(entries: &HashMap<ObjType, Entry>) -> Result<Prototypes, Vec<ValidationError>> {
+    let mut errors = Vec::new();
+    let mut report = |path: String, error| {
+        let error = ValidationError { path, error };
+        // Broken ancestors are reported once, not for every descendant.
+        if !errors.contains(&error) {
+            errors.push(error);
+        }
+    };
+    let mut prototypes = HashMap::new();
+    for name in sorted_names(entries) {
+        let mut names = Vec::new();
+        for patch in &entries[name].components {
+            if names.contains(&patch.name()) {
+                let path = format!("{}.{}", name.0, patch.name());
+                report(path, Error::DuplicateComponent);
+            }
+            names.push(patch.name());
+        }
+        let lineage = match lineage(entries, name) {
+            Ok(lineage) => lineage,
+            Err(e) => {
+                report(e.path, e.error);
+                continue;
+            }
+        };
+        let mut patches: Vec<Patch> = Vec::new();
+        for ancestor in lineage {
+            for patch in &entries[ancestor].components {
+                match patches.iter_mut().find(|p| p.name() == patch.name()) {
+                    Some(p) => p.merge(patch.clone()),
+                    None => patches.push(patch.clone()),
+                }
+            }
+        }
+        let mut components = Vec::new();
+        for patch in &patches {
+            for field in patch.missing_fields() {
+                let path = format!("{}.{}.{}", name.0, patch.name(), field);
+                report(path, Error::MissingField);
+            }
+            components.extend(patch.to_component());
+        }
+        prototypes.insert(name.clone(), components);
+    }
+    if errors.is_empty() {
+        Ok(Prototypes(prototypes))
+    } else {
+        Err(errors)
+    }
+}
+
+fn component_name(component: &Component) -> &'static str {
+    match component {
+        Component::Pos(_) => "Pos",
+        Component::Facing(_) => "Facing",
+        Component::Strength(_) => "Strength",
+        Component::Meta(_) => "Meta",
+        Component::BelongsTo(_) => "BelongsTo",
+        Component::Agent(_) => "Agent",
+        Component::Blocker(_) => "Blocker",
+        Component::Armor(_) => "Armor",
+        Component::Abilities(_) => "Abilities",
+        Component::PassiveAbilities(_) => "PassiveAbilities",
+        Component::Effects(_) => "Effects",
+        Component::Schedule(_) => "Schedule",
+        Component::Summoner(_) => "Summoner",
+        Component::Hero(_) => "Hero",
+    }
+}
+
+impl Prototypes {
+    pub fn check(&self) -> Result<(), Error> {
+        data::first_error(self.errors())
+    }
+
+    /// Like `check`, but reports all the errors with their paths.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        data::to_result(self.errors())
+    }
+
+    pub fn check_known(&self, name: &ObjType) -> Result<(), Error> {
+        if self.0.contains_key(name) {
+            Ok(())
+        } else {
+            Err(Error::UnknownPrototype(name.clone()))
+        }
+    }
+
+    fn errors(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let mut report = |path: String, error| errors.push(ValidationError { path, error });
+        for name in sorted_names(&self.0) {
+            let path = |s: &str| format!("{}.{}", name.0, s);
+            let mut names = Vec::new();
+            // Fields with their values and minimal allowed values.
+            let mut ranges = Vec::new();
+            // Object types with the paths of abilities that create them.
+            let mut created = Vec::new();
+            let mut needs_summoner = false;
+            for component in &self.0[name] {
+                let component_name = component_name(component);
+                if names.contains(&component_name) {
+                    report(path(component_name), Error::DuplicateComponent);
+                }
+                names.push(component_name);
+                match component {
+                    Component::Strength(c) => {
+                        ranges.push(("Strength.strength", c.strength.0, 1));
+                        ranges.push(("Strength.base_strength", c.base_strength.0, 1));
+                    }
+                    Component::Agent(c) => {
+                        ranges.push(("Agent.moves", c.moves.0, 0));
+                        ranges.push(("Agent.attacks", c.attacks.0, 0));
+                        ranges.push(("Agent.jokers", c.jokers.0, 0));
+                        ranges.push(("Agent.attack_strength", c.attack_strength.0, 0));
+                        ranges.push(("Agent.attack_distance", c.attack_distance, 0));
+                        ranges.push(("Agent.attack_accuracy", c.attack_accuracy.0, 0));
+                        ranges.push(("Agent.armor_piercing", c.armor_piercing.0, 0));
+                        ranges.push(("Agent.move_points", c.move_points.0, 0));
+                        ranges.push(("Agent.reactive_attacks", c.reactive_attacks.0, 0));
+                        ranges.push(("Agent.base_moves", c.base_moves.0, 0));
+                        ranges.push(("Agent.base_attacks", c.base_attacks.0, 0));
+                        ranges.push(("Agent.base_jokers", c.base_jokers.0, 0));
+                    }
+                    Component::Armor(c) => {
+                        ranges.push(("Armor.slash", c.slash.0, 0));
+                        ranges.push(("Armor.smash", c.smash.0, 0));
+                        ranges.push(("Armor.pierce", c.pierce.0, 0));
+                        ranges.push(("Armor.claw", c.claw.0, 0));
+                    }
+                    Component::Abilities(c) => {
+                        for (i, ability) in c.0.iter().enumerate() {
+                            let path = path(&format!("Abilities[{}]", i));
+                            needs_summoner |= ability.ability == Ability::Summon;
+                            for &name in created_by_ability(&ability.ability) {
+                                created.push((path.clone(), name));
+                            }
+                        }
+                    }
+                    Component::PassiveAbilities(c) => {
+                        for (i, &ability) in c.0.iter().enumerate() {
+                            let path = path(&format!("PassiveAbilities[{}]", i));
+                            for &name in created_by_passive_ability(ability) {
+                                created.push((path.clone(), name));
+                            }
+                        }
+                    }
+                    Component::Blocker(_) | Component::Summoner(_) => {}
+                    Component::Pos(_)
+                    | Component::Facing(_)
+                    | Component::Meta(_)
+                    | Component::BelongsTo(_)
+                    | Component::Effects(_)
+                    | Component::Schedule(_)
+                    | Component::Hero(_) => {
+                        report(path(component_name), Error::UnexpectedComponent);
+                    }
+                }
+            }
+            for (field, value, min) in ranges {
+                if value < min {
+                    report(path(field), Error::OutOfRange(value));
+                }
+            }
+            if names.contains(&"Agent") {
+                for required in ["Strength", "Blocker"] {
+                    if !names.contains(&required) {
+                        report(path(required), Error::MissingComponent);
+                    }
+                }
+            }
+            if needs_summoner && !names.contains(&"Summoner") {
+                report(path("Summoner"), Error::MissingComponent);
+            }
+            for (path, created) in created {
+                if let Err(error) = self.check_known(&created.into()) {
+                    report(path, error);
+                }
+            }
+        }
+        errors
+    }
+
+    /// A hash of the prototypes that doesn't depend on the iteration order of the map.
+    pub fn digest(&self) -> Digest {
+        let mut prototypes: Vec<_> = self.0.iter().collect();
+        prototypes.sort_by_key(|(name, _)| &name.0);
+        data::digest(&prototypes)
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn shipped() -> Prototypes {
+    let s = include_str!("../../../assets/objects.ron");
+    from_ron(s).expect("Can't load the prototypes")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::battle::{
+        ability::Ability,
+        component::{Armor, Component, ObjType, WeaponType},
+        Accuracy, Attacks, Strength,
+    };
+
+    use super::{from_ron, shipped, Error, LoadError, ValidationError};
+
+    fn agent(components: &[Component]) -> &crate::battle::component::Agent {
+        components
+            .iter()
+            .find_map(|c| match c {
+                Component::Agent(agent) => Some(agent),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn errors(s: &str) -> Vec<ValidationError> {
+        match from_ron(s) {
+            Err(LoadError::Invalid(errors)) => errors,
+            result => panic!("Unexpected result: {:?}", result),
+        }
+    }
+
+    fn error(path: &str, error: Error) -> ValidationError {
+        ValidationError {
+            path: path.into(),
+            error,
+        }
+    }
+
+    #[test]
+    fn test_shipped() {
+        let prototypes = shipped();
+        assert_eq!(prototypes.validate(), Ok(()));
+        let elite = &prototypes.0[&ObjType::from("elite_spearman")];
+        let agent = agent(elite);
+        assert_eq!(agent.attack_accuracy, Accuracy(5));
+        assert_eq!(agent.attack_distance, 2);
+        assert!(elite.iter().any(|c| match c {
+            Component::Abilities(abilities) => abilities.0[0].ability == Ability::LongJump,
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let s = "#![enable(unwrap_newtypes, implicit_some)]
+        {
+            \"imp\": (components: [
+                Blocker(()),
+                Strength((strength: 3)),
+                Agent((
+                    moves: 1,
+                    attacks: 1,
+                    jokers: 0,
+                    reactive_attacks: 1,
+                    attack_distance: 1,
+                    attack_strength: 1,
+                    attack_accuracy: 3,
+                    weapon_type: Claw,
+                    move_points: 2,
+                    base_attacks: 2,
+                )),
+            ]),
+            \"big_imp\": (extends: \"imp\", components: [Agent((attacks: 3))]),
+            \"bigger_imp\": (extends: \"big_imp\", components: [Agent((jokers: 1))]),
+        }";
+        let prototypes = from_ron(s).unwrap();
+        let imp = agent(&prototypes.0[&ObjType::from("bigger_imp")]);
+        assert_eq!(imp.attacks, Attacks(3));
+        assert_eq!(imp.base_attacks, Attacks(2));
+        assert_eq!(imp.jokers.0, 1);
+        assert_eq!(imp.base_jokers.0, 1);
+        assert_eq!(imp.attack_accuracy, Accuracy(3));
+        assert_eq!(prototypes.0[&ObjType::from("bigger_imp")].len(), 3);
+    }
+
+    #[test]
+    fn test_armor() {
+        let armor = |components: &[Component]| {
+            components.iter().find_map(|c| match c {
+                Component::Armor(armor) => Some(armor.clone()),
+                _ => None,
+            })
+        };
+        let prototypes = shipped();
+        assert_eq!(armor(&prototypes.0[&ObjType::from("swordsman")]), None);
+        let heavy = armor(&prototypes.0[&ObjType::from("heavy_swordsman")]).unwrap();
+        assert_eq!(heavy.against(WeaponType::Slash), Strength(1));
+        assert_eq!(heavy.against(WeaponType::Smash), Strength(0));
+        let s = "#![enable(unwrap_newtypes, implicit_some)]
+        {
+            \"wall\": (components: [Blocker(()), Armor((slash: 2, smash: 1))]),
+            \"thick_wall\": (extends: \"wall\", components: [Armor((smash: 3))]),
+            \"bad_wall\": (extends: \"wall\", components: [Armor((claw: -1))]),
+        }";
+        assert_eq!(
+            errors(s),
+            vec![error("bad_wall.Armor.claw", Error::OutOfRange(-1))]
+        );
+        let s = s.replace("claw: -1", "claw: 1");
+        let prototypes = from_ron(&s).unwrap();
+        let expected = Armor {
+            slash: Strength(2),
+            smash: Strength(3),
+            ..Default::default()
+        };
+        let thick_wall = &prototypes.0[&ObjType::from("thick_wall")];
+        assert_eq!(armor(thick_wall), Some(expected));
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let s = "{\"boulder\": (components: [Blocker((weight: Heavy, dodge: 1))])}";
+        assert!(matches!(from_ron(s), Err(LoadError::Ron(_))));
+    }
+
+    #[test]
+    fn test_errors() {
+        let s = "#![enable(unwrap_newtypes, implicit_some)]
+        {
+            \"a\": (extends: \"b\"),
+            \"b\": (extends: \"a\"),
+            \"c\": (extends: \"d\"),
+            \"e\": (components: [Strength(()), Blocker(()), Blocker(())]),
+        }";
+        assert_eq!(
+            errors(s),
+            vec![
+                error("b.extends", Error::InheritanceCycle),
+                error("a.extends", Error::InheritanceCycle),
+                error("c.extends", Error::UnknownPrototype("d".into())),
+                error("e.Blocker", Error::DuplicateComponent),
+                error("e.Strength.strength", Error::MissingField),
+            ]
+        );
+        let s = "#![enable(unwrap_newtypes, implicit_some)]
+        {
+            \"summoner\": (components: [
+                Strength((strength: 0)),
+                Agent((
+                    moves: -1,
+                    attacks: 1,
+                    jokers: 0,
+                    reactive_attacks: 1,
+                    attack_distance: 1,
+                    attack_strength: 1,
+                    attack_accuracy: 3,
+                    weapon_type: Claw,
+                    move_points: 2,
+                )),
+                Abilities([(ability: Summon, status: Ready)]),
+            ]),
+        }";
+        assert_eq!(
+            errors(s),
+            vec![
+                error("summoner.Strength.strength", Error::OutOfRange(0)),
+                error("summoner.Strength.base_strength", Error::OutOfRange(0)),
+                error("summoner.Agent.moves", Error::OutOfRange(-1)),
+                error("summoner.Agent.base_moves", Error::OutOfRange(-1)),
+                error("summoner.Blocker", Error::MissingComponent),
+                error("summoner.Summoner", Error::MissingComponent),
+                error(
+                    "summoner.Abilities[0]",
+                    Error::UnknownPrototype("imp".into())
+                ),
+                error(
+                    "summoner.Abilities[0]",
+                    Error::UnknownPrototype("toxic_imp".into())
+                ),
+                error(
+                    "summoner.Abilities[0]",
+                    Error::UnknownPrototype("imp_bomber".into())
+                ),
+            ]
+        );
+    }
+}
@@ -1,16 +1,25 @@
+use risc0_zkvm::sha::Digest;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
 
 use crate::{
     battle::{
         component::ObjType,
+        data,
         state::{self, State},
-        PlayerId, TileType,
+        PlayerId, Rounds, TileType,
     },
     map::{self, PosHex},
     utils::SimpleRng,
 };
 
+pub use self::format::VERSION;
+
+pub mod format;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum BattleType {
     Skirmish,
@@ -32,82 +41,281 @@ pub struct Object {
     pub pos: PosHex,
 }
 
+/// Ends a battle that nobody has won in time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RoundLimit {
+    pub max_rounds: Rounds,
+
+    /// Usually the defending side.
+    pub winner_id: PlayerId,
+}
+
+impl Default for RoundLimit {
+    fn default() -> Self {
+        Self {
+            max_rounds: Rounds(10),
+            winner_id: PlayerId(1),
+        }
+    }
+}
+
+/// What happens at the end of every turn.
+/// The first battles of a campaign use simpler rules.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Rules {
+    /// Only reattacks, statuses and cooldowns are updated.
+    Basic,
+
+    /// Planned abilities (bombs, fire, etc) tick too.
+    PlannedAbilities,
+
+    /// Stuns, lasting effects and passive abilities work as well.
+    #[default]
+    Full,
+}
+
+/// Which side a player fights for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Team {
+    /// Fights everyone else, like neutral monsters or free-for-all players.
+    #[default]
+    Alone,
+
+    /// Players of the same team are allies.
+    Allied(i32),
+}
+
+/// Players without an entry in `teams` fight alone.
+pub fn are_allies(teams: &[Team], a: PlayerId, b: PlayerId) -> bool {
+    if a == b {
+        return true;
+    }
+    let team = |player_id: PlayerId| teams.get(player_id.0 as usize).copied();
+    match (team(a), team(b)) {
+        (Some(Team::Allied(a)), Some(Team::Allied(b))) => a == b,
+        _ => false,
+    }
+}
+
 // TODO: Split into `Scenario` (exact info) and `ScenarioTemplate`?
+/// See the `format` module for the file format and its versions.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Scenario {
+    pub version: u32,
+
     pub map_radius: i32,
+
+    /// Player 0 is the human one, all the others are controlled by the AI.
     pub players_count: i32,
 
+    /// Teams of the players by their ids.
+    pub teams: Vec<Team>,
+
     // TODO: rename it to `randomized_tiles` later (not only `TileType::Rocks`)
     pub rocky_tiles_count: i32,
 
+    #[serde(with = "format::tiles")]
     pub tiles: HashMap<PosHex, TileType>,
 
     pub randomized_objects: Vec<ObjectsGroup>,
 
     pub objects: Vec<Object>,
+
+    pub round_limit: RoundLimit,
+
+    pub rules: Rules,
 }
 
-#[derive(Clone, Debug, derive_more::From)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Error {
+    UnsupportedVersion(u32),
     MapIsTooSmall,
     PosOutsideOfMap(PosHex),
     NoPlayerAgents,
     NoEnemyAgents,
     UnsupportedPlayersCount(i32),
+    UnknownPlayer(PlayerId),
+    BadRoundLimit,
+    ObjectsOverlap(PosHex),
+
+    /// Exact objects, like the randomized ones, can only be placed on plain tiles.
+    ObjectOnBlockedTile(PosHex),
+
+    /// Only the first `SECTORS_COUNT` players have map sectors for lines.
+    NoSectorForPlayer(PlayerId),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pos = |pos: &PosHex| format!("({}, {})", pos.q, pos.r);
+        match self {
+            Error::UnsupportedVersion(version) => {
+                write!(
+                    f,
+                    "version {} isn't supported, expected {}",
+                    version, VERSION
+                )
+            }
+            Error::MapIsTooSmall => write!(f, "the map radius must be at least 3"),
+            Error::PosOutsideOfMap(p) => write!(f, "{} is outside of the map", pos(p)),
+            Error::NoPlayerAgents => write!(f, "there are no player's agents"),
+            Error::NoEnemyAgents => write!(f, "there are no enemy agents"),
+            Error::UnsupportedPlayersCount(n) => write!(f, "{} players aren't supported", n),
+            Error::UnknownPlayer(id) => write!(f, "there's no player {}", id.0),
+            Error::BadRoundLimit => write!(f, "the battle must last at least one round"),
+            Error::ObjectsOverlap(p) => write!(f, "another object is already at {}", pos(p)),
+            Error::ObjectOnBlockedTile(p) => write!(f, "the tile at {} isn't plain", pos(p)),
+            Error::NoSectorForPlayer(id) => {
+                write!(f, "player {} has no sector to place a line in", id.0)
+            }
+        }
+    }
 }
 
+/// Paths are like `objects[2].pos`.
+pub type ValidationError = data::ValidationError<Error>;
+
 impl Scenario {
+    /// Checks everything but the presence of agents, which may be added later
+    /// (like the campaign's heroes).
+    pub fn check_layout(&self) -> Result<(), Error> {
+        data::first_error(self.layout_errors())
+    }
+
     pub fn check(&self) -> Result<(), Error> {
-        if self.players_count != 2 {
-            return Err(Error::UnsupportedPlayersCount(self.players_count));
+        data::first_error(self.errors())
+    }
+
+    /// Like `check_layout`, but reports all the errors with their paths.
+    pub fn validate_layout(&self) -> Result<(), Vec<ValidationError>> {
+        data::to_result(self.layout_errors())
+    }
+
+    /// Like `check`, but reports all the errors with their paths.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        data::to_result(self.errors())
+    }
+
+    fn layout_errors(&self) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        let mut report = |path: String, error| errors.push(ValidationError { path, error });
+        if self.version != VERSION {
+            report("version".into(), Error::UnsupportedVersion(self.version));
+        }
+        if self.players_count < 2 {
+            let error = Error::UnsupportedPlayersCount(self.players_count);
+            report("players_count".into(), error);
+        }
+        let is_known = |owner: Option<PlayerId>| match owner {
+            Some(PlayerId(id)) => id >= 0 && id < self.players_count,
+            None => true,
+        };
+        if self.teams.len() > self.players_count.max(0) as usize {
+            let player_id = PlayerId(self.teams.len() as i32 - 1);
+            report("teams".into(), Error::UnknownPlayer(player_id));
+        }
+        for (i, group) in self.randomized_objects.iter().enumerate() {
+            let path = format!("randomized_objects[{}].owner", i);
+            if !is_known(group.owner) {
+                report(path, Error::UnknownPlayer(group.owner.unwrap()));
+            } else if let (Some(owner), Some(_)) = (group.owner, group.line) {
+                if owner.0 >= SECTORS_COUNT {
+                    report(path, Error::NoSectorForPlayer(owner));
+                }
+            }
         }
         if self.map_radius < 3 {
-            return Err(Error::MapIsTooSmall);
+            report("map_radius".into(), Error::MapIsTooSmall);
+        }
+        if self.round_limit.max_rounds.0 < 1 {
+            report("round_limit.max_rounds".into(), Error::BadRoundLimit);
+        }
+        let winner_id = self.round_limit.winner_id;
+        if !is_known(Some(winner_id)) {
+            report(
+                "round_limit.winner_id".into(),
+                Error::UnknownPlayer(winner_id),
+            );
         }
         let origin = PosHex { q: 0, r: 0 };
-        for obj in &self.objects {
-            let dist = map::distance_hex(origin, obj.pos);
-            if dist > self.map_radius {
-                return Err(Error::PosOutsideOfMap(obj.pos));
+        let is_inboard = |pos| map::distance_hex(origin, pos) <= self.map_radius;
+        let mut tiles: Vec<_> = self.tiles.keys().copied().collect();
+        tiles.sort_by_key(|pos| (pos.q, pos.r));
+        for pos in tiles {
+            if !is_inboard(pos) {
+                report("tiles".into(), Error::PosOutsideOfMap(pos));
+            }
+        }
+        let mut occupied = HashSet::new();
+        for (i, obj) in self.objects.iter().enumerate() {
+            if !is_known(obj.owner) {
+                let path = format!("objects[{}].owner", i);
+                report(path, Error::UnknownPlayer(obj.owner.unwrap()));
+            }
+            let path = format!("objects[{}].pos", i);
+            if !is_inboard(obj.pos) {
+                report(path, Error::PosOutsideOfMap(obj.pos));
+                continue;
+            }
+            if !occupied.insert(obj.pos) {
+                report(path, Error::ObjectsOverlap(obj.pos));
+                continue;
+            }
+            let tile = self.tiles.get(&obj.pos).copied().unwrap_or_default();
+            if tile != TileType::Plain {
+                report(path, Error::ObjectOnBlockedTile(obj.pos));
             }
         }
-        let any_exact_player_agents = self
-            .objects
-            .iter()
-            .any(|obj| obj.owner == Some(PlayerId(0)));
-        let any_random_player_agents = self
-            .randomized_objects
-            .iter()
-            .any(|obj| obj.owner == Some(PlayerId(0)));
-        if !any_exact_player_agents && !any_random_player_agents {
-            return Err(Error::NoPlayerAgents);
+        errors
+    }
+
+    fn errors(&self) -> Vec<ValidationError> {
+        let mut errors = self.layout_errors();
+        let has_agents = |is_owner: &dyn Fn(PlayerId) -> bool| {
+            let is_owner = |owner: Option<PlayerId>| owner.is_some_and(is_owner);
+            self.objects.iter().any(|obj| is_owner(obj.owner))
+                || self
+                    .randomized_objects
+                    .iter()
+                    .any(|obj| is_owner(obj.owner))
+        };
+        let human = PlayerId(0);
+        if !has_agents(&|owner| owner == human) {
+            errors.push(ValidationError {
+                path: "objects".into(),
+                error: Error::NoPlayerAgents,
+            });
         }
-        let any_exact_enemy_agents = self
-            .objects
-            .iter()
-            .any(|obj| obj.owner == Some(PlayerId(1)));
-        let any_random_enemy_agents = self
-            .randomized_objects
-            .iter()
-            .any(|obj| obj.owner == Some(PlayerId(1)));
-        if !any_exact_enemy_agents && !any_random_enemy_agents {
-            return Err(Error::NoEnemyAgents);
+        if !has_agents(&|owner| !are_allies(&self.teams, human, owner)) {
+            errors.push(ValidationError {
+                path: "objects".into(),
+                error: Error::NoEnemyAgents,
+            });
         }
-        Ok(())
+        errors
+    }
+
+    /// A hash of the scenario. The tiles are serialized sorted,
+    /// so it doesn't depend on the iteration order of the map.
+    pub fn digest(&self) -> Digest {
+        data::digest(self)
     }
 }
 
 impl Default for Scenario {
     fn default() -> Self {
         Self {
+            version: VERSION,
             map_radius: 5,
             players_count: 2,
+            teams: Vec::new(),
             rocky_tiles_count: 0,
             tiles: HashMap::new(),
             randomized_objects: Vec::new(),
             objects: Vec::new(),
+            round_limit: RoundLimit::default(),
+            rules: Rules::default(),
         }
     }
 }
@@ -158,43 +366,52 @@ impl Line {
         }
     }
 
+    /// Distances from the map's edge, the upper bound is exclusive.
+    /// The ranges aren't empty for any radius the validation allows.
     pub fn get_range(self, radius: i32) -> (i32, i32) {
-        match (self, radius) {
-            (Line::Back, 3) => (0, 1),
-            (Line::Middle, 3) => (1, 2),
-            (Line::Front, 3) => (1, 4),
-            (Line::Any, 3) => (0, 4),
-            (Line::Back, 4) => (0, 2),
-            (Line::Middle, 4) => (1, 3),
-            (Line::Front, 4) => (2, 5),
-            (Line::Any, 4) => (0, 5),
-            (Line::Back, 5) => (0, 2),
-            (Line::Middle, 5) => (1, 3),
-            (Line::Front, 5) => (2, 6),
-            (Line::Any, 5) => (0, 6),
-            (_, _) => unimplemented!(),
+        match self {
+            Line::Back => (0, radius / 2),
+            Line::Middle => (1, radius / 2 + 1),
+            Line::Front => (radius / 2, radius + 1),
+            Line::Any => (0, radius + 1),
         }
     }
 }
 
+/// Players 0 and 1 start at the west and east edges of the map,
+/// players 2 and 3 at the northern and southern ones.
+pub const SECTORS_COUNT: i32 = 4;
+
 fn random_free_sector_pos(
     state: &State,
     player_id: PlayerId,
     line: Line,
     rng: &mut SimpleRng,
 ) -> Option<PosHex> {
+    assert!(player_id.0 < SECTORS_COUNT, "No sector for {:?}", player_id);
     let attempts = 30;
     let radius = state.map().radius();
     let (min, max) = line.get_range(radius);
     for _ in 0..attempts {
-        let q = radius - rng.gen_range(min, max);
-        let pos = PosHex {
-            q: match player_id.0 {
-                0 => -q,
-                1 => q,
-                _ => unimplemented!(),
+        let depth = radius - rng.gen_range(min, max);
+        let across = rng.gen_range(-radius, radius + 1);
+        let pos = match player_id.0 {
+            0 => PosHex {
+                q: -depth,
+                r: across,
+            },
+            1 => PosHex {
+                q: depth,
+                r: across,
+            },
+            2 => PosHex {
+                q: across,
+                r: -depth,
+            },
+            _ => PosHex {
+                q: across,
+                r: depth,
             },
-            r: rng.gen_range(-radius, radius + 1),
         };
 
         if state::is_tile_completely_free(state, pos) {
@@ -218,8 +435,15 @@ pub fn random_pos(
 
 #[cfg(test)]
 mod tests {
+    use crate::{
+        battle::{component::ObjType, PlayerId, TileType},
+        map::PosHex,
+    };
 
-    use super::middle_range;
+    use super::{
+        are_allies, middle_range, Error, Line, Object, ObjectsGroup, Rules, Scenario, Team,
+        ValidationError,
+    };
 
     #[test]
     fn test_middle_range() {
@@ -232,4 +456,180 @@ mod tests {
         assert_eq!(middle_range(0, 9), (2, 6));
         assert_eq!(middle_range(0, 10), (3, 7));
     }
+
+    #[test]
+    fn test_line_ranges() {
+        assert_eq!(Line::Back.get_range(3), (0, 1));
+        assert_eq!(Line::Middle.get_range(3), (1, 2));
+        assert_eq!(Line::Front.get_range(3), (1, 4));
+        assert_eq!(Line::Front.get_range(4), (2, 5));
+        assert_eq!(Line::Any.get_range(5), (0, 6));
+        for radius in 3..20 {
+            for line in [Line::Any, Line::Front, Line::Middle, Line::Back] {
+                let (min, max) = line.get_range(radius);
+                assert!(0 <= min && min < max && max <= radius + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_sector_for_player() {
+        let group = |owner| ObjectsGroup {
+            owner: Some(PlayerId(owner)),
+            typename: ObjType("imp".into()),
+            line: Some(Line::Front),
+            count: 1,
+        };
+        let mut scenario = Scenario {
+            players_count: 5,
+            randomized_objects: vec![group(3)],
+            ..Default::default()
+        };
+        assert_eq!(scenario.check_layout(), Ok(()));
+        scenario.randomized_objects.push(group(4));
+        let error = Error::NoSectorForPlayer(PlayerId(4));
+        assert_eq!(scenario.check_layout(), Err(error));
+    }
+
+    #[test]
+    fn test_round_limit_winner() {
+        let mut scenario = Scenario {
+            players_count: 3,
+            ..Default::default()
+        };
+        scenario.round_limit.winner_id = PlayerId(2);
+        assert_eq!(scenario.check_layout(), Ok(()));
+        scenario.round_limit.winner_id = PlayerId(3);
+        let error = ValidationError {
+            path: "round_limit.winner_id".into(),
+            error: Error::UnknownPlayer(PlayerId(3)),
+        };
+        assert_eq!(scenario.validate_layout(), Err(vec![error]));
+    }
+
+    #[test]
+    fn test_check_layout() {
+        let object = |q, r| Object {
+            owner: Some(PlayerId(1)),
+            typename: ObjType("imp".into()),
+            pos: PosHex { q, r },
+        };
+        let mut scenario = Scenario {
+            map_radius: 3,
+            objects: vec![object(0, 0), object(0, 1)],
+            ..Default::default()
+        };
+        scenario
+            .tiles
+            .insert(PosHex { q: 1, r: 1 }, TileType::Rocks);
+        assert_eq!(scenario.check_layout(), Ok(()));
+
+        let mut bad = scenario.clone();
+        bad.objects.push(object(0, 1));
+        assert_eq!(
+            bad.check_layout(),
+            Err(Error::ObjectsOverlap(PosHex { q: 0, r: 1 }))
+        );
+
+        let mut bad = scenario.clone();
+        bad.objects.push(object(1, 1));
+        assert_eq!(
+            bad.check_layout(),
+            Err(Error::ObjectOnBlockedTile(PosHex { q: 1, r: 1 }))
+        );
+
+        let mut bad = scenario.clone();
+        bad.objects.push(object(4, 0));
+        assert_eq!(
+            bad.check_layout(),
+            Err(Error::PosOutsideOfMap(PosHex { q: 4, r: 0 }))
+        );
+
+        let mut bad = scenario;
+        bad.tiles.insert(PosHex { q: 0, r: -4 }, TileType::Rocks);
+        assert_eq!(
+            bad.check_layout(),
+            Err(Error::PosOutsideOfMap(PosHex { q: 0, r: -4 }))
+        );
+    }
+
+    #[test]
+    fn test_teams() {
+        let teams = [Team::Allied(0), Team::Alone, Team::Allied(0), Team::Alone];
+        assert!(are_allies(&teams, PlayerId(0), PlayerId(2)));
+        assert!(are_allies(&teams, PlayerId(1), PlayerId(1)));
+        assert!(!are_allies(&teams, PlayerId(0), PlayerId(1)));
+        assert!(!are_allies(&teams, PlayerId(1), PlayerId(3)));
+        assert!(!are_allies(&teams, PlayerId(0), PlayerId(4)));
+
+        let object = |owner| Object {
+            owner: Some(PlayerId(owner)),
+            typename: ObjType("imp".into()),
+            pos: PosHex { q: 0, r: owner },
+        };
+        let mut scenario = Scenario {
+            players_count: 3,
+            teams: vec![Team::Allied(0), Team::Allied(0)],
+            objects: vec![object(0), object(1)],
+            ..Default::default()
+        };
+        assert_eq!(scenario.check(), Err(Error::NoEnemyAgents));
+        scenario.objects.push(object(2));
+        assert_eq!(scenario.check(), Ok(()));
+        scenario.objects.push(object(3));
+        assert_eq!(scenario.check(), Err(Error::UnknownPlayer(PlayerId(3))));
+    }
+
+    #[test]
+    fn test_validate_paths() {
+        let imp = |q, r| Object {
+            owner: Some(PlayerId(1)),
+            typename: ObjType("imp".into()),
+            pos: PosHex { q, r },
+        };
+        let scenario = Scenario {
+            version: 0,
+            map_radius: 3,
+            objects: vec![imp(0, 0), imp(5, 0), imp(0, 0)],
+            ..Default::default()
+        };
+        let error = |path: &str, error| ValidationError {
+            path: path.into(),
+            error,
+        };
+        assert_eq!(
+            scenario.validate(),
+            Err(vec![
+                error("version", Error::UnsupportedVersion(0)),
+                error(
+                    "objects[1].pos",
+                    Error::PosOutsideOfMap(PosHex { q: 5, r: 0 })
+                ),
+                error(
+                    "objects[2].pos",
+                    Error::ObjectsOverlap(PosHex { q: 0, r: 0 })
+                ),
+                error("objects", Error::NoPlayerAgents),
+            ])
+        );
+        assert_eq!(
+            scenario.validate().unwrap_err()[1].to_string(),
+            "objects[1].pos: (5, 0) is outside of the map"
+        );
+    }
+
+    #[test]
+    fn test_digest() {
+        let mut scenario = Scenario::default();
+        for q in -2..=2 {
+            scenario.tiles.insert(PosHex { q, r: 0 }, TileType::Rocks);
+        }
+        let mut same = Scenario::default();
+        for q in (-2..=2).rev() {
+            same.tiles.insert(PosHex { q, r: 0 }, TileType::Rocks);
+        }
+        assert_eq!(scenario.digest(), same.digest());
+        same.rules = Rules::Basic;
+        assert_ne!(scenario.digest(), same.digest());
+    }
 }
@@ -0,0 +1,206 @@
+//! The scenario file format.
+//!
+//! Scenarios are stored as RON or JSON with the same structure:
+//!
+//! - `version`: the format version, see `VERSION`;
+//! - `map_radius`, `players_count` and `rocky_tiles_count`: numbers;
+//! - `teams`: `Alone` or `Allied(team)` for every player in order,
+//!   missing players fight alone;
+//! - `tiles`: a list of `(pos, tile)` pairs, like `[((q: 0, r: 1), Rocks)]`.
+//!   Tiles are `Plain`, `Rocks`, `Water`, `Forest`, `Swamp` or `Lava`,
+//!   see `TileType::info` for their modifiers;
+//! - `randomized_objects`: a list of `(owner, typename, line, count)` groups
+//!   placed randomly;
+//! - `objects`: a list of `(owner, typename, pos)` objects placed exactly;
+//! - `round_limit`: `(max_rounds, winner_id)`;
+//! - `rules`: `Basic`, `PlannedAbilities` or `Full`.
+//!
+//! Every field is optional, missing ones get the `Scenario::default()` values.
+//!
+//! Versions:
+//!
+//! - 0: files without a `version` field. `tiles` is a map from positions to tiles,
+//!   so it can't be written as JSON (JSON keys are strings);
+//! - 1: `tiles` is a list of pairs.
+//!
+//! `from_ron` and `from_json` migrate older versions to the current one.
+//! Scenarios embedded into other data (campaign plans, transcripts)
+//! are always read as the current version.
+
+use serde::Deserialize;
+use std::{collections::HashMap, error, fmt};
+
+use crate::{
+    battle::{
+        scenario::{Object, ObjectsGroup, RoundLimit, Rules, Scenario},
+        TileType,
+    },
+    map::PosHex,
+};
+
+pub const VERSION: u32 = 1;
+
+/// Serializes the tiles as a list of pairs sorted by position.
+pub mod tiles {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    use crate::{battle::TileType, map::PosHex};
+
+    pub fn serialize<S: Serializer>(
+        tiles: &HashMap<PosHex, TileType>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut tiles: Vec<_> = tiles.iter().collect();
+        tiles.sort_by_key(|(pos, _)| (pos.q, pos.r));
+        tiles.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<PosHex, TileType>, D::Error> {
+        let tiles: Vec<(PosHex, TileType)> = Vec::deserialize(deserializer)?;
+        Ok(tiles.into_iter().collect())
+    }
+}
+
+#[derive(Debug, derive_more::From)]
+pub enum LoadError {
+    Ron(ron::error::SpannedError),
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Ron(e) => write!(f, "Bad RON: {}", e),
+            LoadError::Json(e) => write!(f, "Bad JSON: {}", e),
+            LoadError::UnsupportedVersion(version) => write!(
+                f,
+                "Scenario version {} isn't supported, the latest is {}",
+                version, VERSION
+            ),
+        }
+    }
+}
+
+impl error::Error for LoadError {}
+
+#[derive(Deserialize)]
+struct Header {
+    /// Files without a version predate versioning.
+    #[serde(default)]
+    version: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct ScenarioV0 {
+    map_radius: i32,
+    players_count: i32,
+    rocky_tiles_count: i32,
+    tiles: HashMap<PosHex, TileType>,
+    randomized_objects: Vec<ObjectsGroup>,
+    objects: Vec<Object>,
+    round_limit: RoundLimit,
+    rules: Rules,
+}
+
+impl Default for ScenarioV0 {
+    fn default() -> Self {
+        let s = Scenario::default();
+        Self {
+            map_radius: s.map_radius,
+            players_count: s.players_count,
+            rocky_tiles_count: s.rocky_tiles_count,
+            tiles: s.tiles,
+            randomized_objects: s.randomized_objects,
+            objects: s.objects,
+            round_limit: s.round_limit,
+            rules: s.rules,
+        }
+    }
+}
+
+impl From<ScenarioV0> for Scenario {
+    fn from(s: ScenarioV0) -> Self {
+        Self {
+            version: VERSION,
+            map_radius: s.map_radius,
+            players_count: s.players_count,
+            teams: Vec::new(),
+            rocky_tiles_count: s.rocky_tiles_count,
+            tiles: s.tiles,
+            randomized_objects: s.randomized_objects,
+            objects: s.objects,
+            round_limit: s.round_limit,
+            rules: s.rules,
+        }
+    }
+}
+
+/// Reads a RON scenario of any known version.
+pub fn from_ron(s: &str) -> Result<Scenario, LoadError> {
+    let header: Header = ron::de::from_str(s)?;
+    match header.version {
+        0 => Ok(ron::de::from_str::<ScenarioV0>(s)?.into()),
+        VERSION => Ok(ron::de::from_str(s)?),
+        version => Err(LoadError::UnsupportedVersion(version)),
+    }
+}
+
+/// Reads a JSON scenario of any known version.
+pub fn from_json(s: &str) -> Result<Scenario, LoadError> {
+    let header: Header = serde_json::from_str(s)?;
+    match header.version {
+        0 => Ok(serde_json::from_str::<ScenarioV0>(s)?.into()),
+        VERSION => Ok(serde_json::from_str(s)?),
+        version => Err(LoadError::UnsupportedVersion(version)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        battle::{component::ObjType, scenario::Scenario, PlayerId, TileType},
+        map::PosHex,
+    };
+
+    use super::{from_json, from_ron, LoadError, VERSION};
+
+    #[test]
+    fn test_migrate_v0() {
+        let s = "(
+            map_radius: 4,
+            tiles: {(q: 0, r: 1): Rocks},
+            objects: [(owner: Some((1)), typename: \"imp\", pos: (q: 1, r: 0))],
+        )";
+        let scenario = from_ron(s).unwrap();
+        assert_eq!(scenario.version, VERSION);
+        assert_eq!(scenario.map_radius, 4);
+        assert_eq!(scenario.tiles[&PosHex { q: 0, r: 1 }], TileType::Rocks);
+        assert_eq!(scenario.objects[0].owner, Some(PlayerId(1)));
+        assert_eq!(scenario.objects[0].typename, ObjType("imp".into()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut scenario = Scenario::default();
+        scenario
+            .tiles
+            .insert(PosHex { q: 1, r: -1 }, TileType::Rocks);
+        let json = serde_json::to_string(&scenario).unwrap();
+        assert_eq!(from_json(&json).unwrap(), scenario);
+        let ron = ron::ser::to_string(&scenario).unwrap();
+        assert_eq!(from_ron(&ron).unwrap(), scenario);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        assert!(matches!(
+            from_json(r#"{"version": 100}"#),
+            Err(LoadError::UnsupportedVersion(100))
+        ));
+    }
+}
@@ -3,12 +3,17 @@ use crate::{
         self,
         ability::{self, Ability, PassiveAbility},
         component::ObjType,
-        effect, Id, PlayerId, TileType,
+        effect::{self, Lasting},
+        scenario, Accuracy, Dodge, Id, MovePoints, PlayerId, TileType, Weight,
     },
     map::{self, PosHex},
 };
 
-pub use self::private::{BattleResult, State};
+#[cfg(test)]
+#[cfg(not(feature = "event"))]
+use crate::utils::SimpleRng;
+
+pub use self::private::{BattleResult, State, SurvivorHero};
 
 use super::BAD_ID;
 
@@ -48,6 +53,15 @@ pub fn is_tile_plain_and_completely_free(state: &State, pos: PosHex) -> bool {
     state.parts().pos.iter().all(|(_, x)| x.0 != pos)
 }
 
+/// Checks if the tile's type lets the object in, ignoring other objects.
+pub fn is_tile_passable_for(state: &State, id: Id, pos: PosHex) -> bool {
+    let weight = match state.parts().blocker.get(&id) {
+        Some(blocker) => blocker.weight,
+        None => Weight::Normal,
+    };
+    state.tile_info(pos).is_passable_for(weight)
+}
+
 pub fn is_tile_completely_free(state: &State, pos: PosHex) -> bool {
     if !state.map().is_inboard(pos) {
         return false;
@@ -66,13 +80,91 @@ pub fn is_lasting_effect_over(state: &State, id: &Id, timed_effect: &effect::Tim
     timed_effect.duration.is_over()
 }
 
+pub fn lasting_effect<'a>(state: &'a State, id: &Id, effect: Lasting) -> Option<&'a effect::Timed> {
+    let effects = state.parts().effects.get(id)?;
+    effects.0.iter().find(|timed| timed.effect == effect)
+}
+
+pub fn has_lasting_effect(state: &State, id: &Id, effect: Lasting) -> bool {
+    lasting_effect(state, id, effect).is_some()
+}
+
+/// Move points with `Slowed` and `Rooted` taken into account.
+pub fn move_points(state: &State, id: &Id) -> MovePoints {
+    let move_points = state.agent(id).move_points;
+    if has_lasting_effect(state, id, Lasting::Rooted) {
+        MovePoints(0)
+    } else if has_lasting_effect(state, id, Lasting::Slowed) {
+        MovePoints(move_points.0 / 2)
+    } else {
+        move_points
+    }
+}
+
+/// Attack accuracy with `Blinded` taken into account.
+pub fn attack_accuracy(state: &State, id: &Id) -> Accuracy {
+    let accuracy = state.agent(id).attack_accuracy;
+    if has_lasting_effect(state, id, Lasting::Blinded) {
+        Accuracy(accuracy.0 - effect::BLINDED_ACCURACY_PENALTY.0)
+    } else {
+        accuracy
+    }
+}
+
+/// The agent that a taunted agent must attack, if it's still alive.
+pub fn taunter(state: &State, id: &Id) -> Option<Id> {
+    let taunter = lasting_effect(state, id, Lasting::Taunted)?.source?;
+    if state.parts().agent.contains_key(&taunter) {
+        Some(taunter)
+    } else {
+        None
+    }
+}
+
+/// The side of the target that an attack comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttackSide {
+    Front,
+    Flank,
+    Rear,
+}
+
+impl AttackSide {
+    pub fn accuracy_bonus(self) -> Accuracy {
+        match self {
+            AttackSide::Front => Accuracy(0),
+            AttackSide::Flank => Accuracy(1),
+            AttackSide::Rear => Accuracy(2),
+        }
+    }
+}
+
+/// Objects without a facing have no flanks: all attacks hit their front.
+pub fn attack_side(state: &State, attacker_id: &Id, target_id: &Id) -> AttackSide {
+    let facing = match state.parts().facing.get(target_id) {
+        Some(facing) => facing.0,
+        None => return AttackSide::Front,
+    };
+    let target_pos = state.pos(target_id).0;
+    let attacker_pos = state.pos(attacker_id).0;
+    let dir = match map::Dir::towards(target_pos, attacker_pos) {
+        Some(dir) => dir,
+        None => return AttackSide::Front,
+    };
+    match facing.turns_between(dir) {
+        0 | 1 => AttackSide::Front,
+        2 => AttackSide::Flank,
+        _ => AttackSide::Rear,
+    }
+}
+
 /// Are there any enemy agents on the adjacent tiles?
 pub fn check_enemies_around(state: &State, pos: PosHex, player_id: PlayerId) -> bool {
     for dir in map::dirs() {
         let neighbor_pos = map::Dir::get_neighbor_pos(pos, dir);
         if let Some(id) = agent_id_at_opt(state, neighbor_pos) {
             let neighbor_player_id = state.belongs_to(&id).0;
-            if neighbor_player_id != player_id {
+            if !are_allies(state, neighbor_player_id, player_id) {
                 return true;
             }
         }
@@ -122,6 +214,40 @@ pub fn blocker_id_at_opt(state: &State, pos: PosHex) -> Option<Id> {
     }
 }
 
+fn is_tile_obstructing(state: &State, pos: PosHex) -> bool {
+    state.tile_info(pos).obstructs || !blocker_ids_at(state, pos).is_empty()
+}
+
+/// Checks that no obstructing tiles or blockers stand between the two positions.
+///
+/// The tile right in front of the target doesn't obstruct the line,
+/// it can only give cover to the target.
+pub fn is_in_line_of_sight(state: &State, from: PosHex, to: PosHex) -> bool {
+    let line = map::line(from, to);
+    if line.len() < 4 {
+        return true;
+    }
+    line[1..line.len() - 2]
+        .iter()
+        .all(|&pos| !is_tile_obstructing(state, pos))
+}
+
+/// An obstructing tile or a heavy blocker in front of the target
+/// make it harder to hit from afar.
+pub fn cover(state: &State, from: PosHex, to: PosHex) -> Dodge {
+    let line = map::line(from, to);
+    if line.len() < 3 {
+        return Dodge(0);
+    }
+    let pos = line[line.len() - 2];
+    let is_heavy = |id: &Id| state.blocker(id).weight >= Weight::Heavy;
+    if state.tile_info(pos).obstructs || blocker_ids_at(state, pos).iter().any(is_heavy) {
+        Dodge(1)
+    } else {
+        Dodge(0)
+    }
+}
+
 pub fn agent_id_at_opt(state: &State, pos: PosHex) -> Option<Id> {
     let ids = agent_ids_at(state, pos);
     if ids.len() == 1 {
@@ -173,10 +299,14 @@ pub fn players_agent_ids(state: &State, player_id: PlayerId) -> Vec<Id> {
     ids
 }
 
+pub fn are_allies(state: &State, a: PlayerId, b: PlayerId) -> bool {
+    scenario::are_allies(state.teams(), a, b)
+}
+
 pub fn enemy_agent_ids(state: &State, player_id: PlayerId) -> Vec<Id> {
     let mut ids = vec![];
     for k in state.parts().agent.keys() {
-        if state.belongs_to(k).0 != player_id {
+        if !are_allies(state, state.belongs_to(k).0, player_id) {
             ids.push(*k)
         }
     }
@@ -187,7 +317,7 @@ pub fn enemy_agent_ids(state: &State, player_id: PlayerId) -> Vec<Id> {
 pub fn enemy_agent_ids_unsort(state: &State, player_id: PlayerId) -> Vec<Id> {
     let mut ids = vec![];
     for k in state.parts().agent.keys() {
-        if state.belongs_to(k).0 != player_id {
+        if !are_allies(state, state.belongs_to(k).0, player_id) {
             ids.push(*k)
         }
     }
@@ -197,13 +327,21 @@ pub fn enemy_agent_ids_unsort(state: &State, player_id: PlayerId) -> Vec<Id> {
 pub fn enemy_agent_count(state: &State, player_id: PlayerId) -> u8 {
     let mut count = 0;
     for k in state.parts().agent.keys() {
-        if state.belongs_to(k).0 != player_id {
+        if !are_allies(state, state.belongs_to(k).0, player_id) {
             count += 1
         }
     }
     count
 }
 
+/// The player and its allies.
+pub fn allied_player_ids(state: &State, player_id: PlayerId) -> Vec<PlayerId> {
+    (0..state.players_count())
+        .map(PlayerId)
+        .filter(|&id| are_allies(state, id, player_id))
+        .collect()
+}
+
 pub fn free_neighbor_positions(state: &State, origin: PosHex, count: usize) -> Vec<PosHex> {
     let mut positions = Vec::new();
     for dir in map::dirs().collect::<Vec<_>>() {
@@ -241,6 +379,22 @@ pub fn players_agent_types(state: &State, player_id: PlayerId) -> Vec<ObjType> {
         .collect()
 }
 
+pub fn players_heroes(state: &State, player_id: PlayerId) -> Vec<SurvivorHero> {
+    let mut heroes = Vec::new();
+    for id in players_agent_ids(state, player_id) {
+        if let Some(hero) = state.parts().hero.get(&id) {
+            let strength = state.strength(&id);
+            heroes.push(SurvivorHero {
+                id: hero.id,
+                kills: hero.kills,
+                strength: strength.strength,
+                base_strength: strength.base_strength,
+            });
+        }
+    }
+    heroes
+}
+
 pub fn can_agent_use_ability(state: &State, id: &Id, ability: &Ability) -> bool {
     let agent_player_id = state.belongs_to(id).0;
     let agent = state.agent(id);
@@ -251,3 +405,53 @@ pub fn can_agent_use_ability(state: &State, id: &Id, ability: &Ability) -> bool
     let is_ready = r_ability.status == ability::Status::Ready;
     is_player_agent && is_ready && has_actions
 }
+
+/// A state with only the given `(owner, type, position)` objects, used as a test fixture.
+#[cfg(test)]
+#[cfg(not(feature = "event"))]
+pub(crate) fn state_with_objects(objects: &[(i32, &str, PosHex)]) -> State {
+    let mut scenario = scenario::Scenario::default();
+    for &(owner, typename, pos) in objects {
+        scenario.objects.push(scenario::Object {
+            owner: Some(PlayerId(owner)),
+            typename: typename.into(),
+            pos,
+        });
+    }
+    state_with_scenario(scenario, &mut SimpleRng::seed_from_u32(0))
+}
+
+/// The first campaign battle with a single swordsman hero, used as a test fixture.
+#[cfg(test)]
+#[cfg(not(feature = "event"))]
+pub(crate) fn state_with_swordsman() -> (State, Id) {
+    use crate::{
+        battle::heroes::{Hero, HeroObject},
+        campaign,
+    };
+
+    let scenario = campaign::campaign_01().nodes()[0]
+        .scenario()
+        .unwrap()
+        .clone();
+    let mut rng = SimpleRng::seed_from_u32(0);
+    let mut state = state_with_scenario(scenario, &mut rng);
+    state.create_heroes(&[HeroObject::new(Hero::Swordsman, 1)], &mut rng);
+    let id = players_agent_ids(&state, PlayerId(0))[0];
+    (state, id)
+}
+
+/// A state for the scenario with the shipped assets, used as a test fixture.
+#[cfg(test)]
+#[cfg(not(feature = "event"))]
+pub(crate) fn state_with_scenario(scenario: scenario::Scenario, rng: &mut SimpleRng) -> State {
+    use crate::battle::{ability::definitions, prototypes, tiles};
+
+    State::new(
+        scenario,
+        prototypes::shipped(),
+        definitions::shipped(),
+        tiles::shipped(),
+        rng,
+    )
+}
@@ -1,9 +1,13 @@
-use crate::battle::{
-    ability::{self, Ability},
-    component::{self, Component, Parts, PlannedAbility},
-    effect::{self, Duration, Effect},
-    event::{self, ActiveEvent, Event},
-    state, Id, Phase, PlayerId, State,
+use crate::{
+    battle::{
+        ability::{self, Ability},
+        component::{self, Component, Parts, PlannedAbility},
+        effect::{self, Duration, Effect},
+        event::{self, ActiveEvent, Event},
+        heroes::Perk,
+        state, Id, Phase, PlayerId, State,
+    },
+    map::Dir,
 };
 
 pub fn apply(state: &mut State, event: &Event) {
@@ -29,12 +33,13 @@ fn apply_event(state: &mut State, event: &Event) {
     match event.active_event {
         ActiveEvent::Create => {}
         ActiveEvent::MoveTo(ref ev) => apply_event_move_to(state, ev),
-        ActiveEvent::Attack(ref ev) => apply_event_attack(state, &ev.attacker_id),
+        ActiveEvent::Attack(ref ev) => apply_event_attack(state, &ev.attacker_id, &ev.target_id),
         ActiveEvent::EndTurn(ref ev) => apply_event_end_turn(state, ev.player_id),
         ActiveEvent::EndBattle(ref ev) => apply_event_end_battle(state, ev),
         ActiveEvent::BeginTurn(ref ev) => apply_event_begin_turn(state, ev),
         ActiveEvent::UseAbility(ref ev) => apply_event_use_ability(state, ev),
         ActiveEvent::UsePassiveAbility(_)
+        | ActiveEvent::EnterTile(_)
         | ActiveEvent::EffectTick(_)
         | ActiveEvent::EffectEnd(_) => {}
     }
@@ -47,6 +52,13 @@ pub fn add_components(state: &mut State, id: &Id, components: &[Component]) {
     }
 }
 
+/// Objects without a facing (boulders, bombs, etc) can't be turned.
+pub fn turn(state: &mut State, id: &Id, dir: Dir) {
+    if let Some(facing) = state.parts_mut().facing.get_mut(id) {
+        facing.0 = dir;
+    }
+}
+
 fn apply_event_move_to(state: &mut State, event: &event::MoveTo) {
     let parts = state.parts_mut();
     let pos = parts.pos.get_mut(&event.id).unwrap();
@@ -62,7 +74,12 @@ fn apply_event_move_to(state: &mut State, event: &event::MoveTo) {
     }
 }
 
-pub fn apply_event_attack(state: &mut State, attacker_id: &Id) {
+pub fn apply_event_attack(state: &mut State, attacker_id: &Id, target_id: &Id) {
+    let attacker_pos = state.parts().pos[attacker_id].0;
+    let target_pos = state.parts().pos[target_id].0;
+    if let Some(dir) = Dir::towards(attacker_pos, target_pos) {
+        turn(state, attacker_id, dir);
+    }
     let parts = state.parts_mut();
     let agent = parts.agent.get_mut(&attacker_id).unwrap();
     if agent.attacks.0 > 0 {
@@ -281,13 +298,14 @@ fn apply_event_begin_turn(state: &mut State, event: &event::BeginTurn) {
 
 pub fn apply_event_use_ability(state: &mut State, event: &event::UseAbility) {
     let id = event.id;
+    let cooldown = state.ability_definition(&event.ability).cooldown;
     let parts = state.parts_mut();
 
     if let Some(abilities) = parts.abilities.get_mut(&id) {
         for r_ability in &mut abilities.0 {
             if r_ability.ability == event.ability {
                 assert_eq!(r_ability.status, ability::Status::Ready);
-                r_ability.status = ability::Status::Cooldown(r_ability.ability.base_cooldown());
+                r_ability.status = ability::Status::Cooldown(cooldown);
             }
         }
     }
@@ -323,6 +341,9 @@ fn add_component(parts: &mut Parts, id: Id, component: &Component) {
         Component::Pos(c) => {
             parts.pos.insert(id, c.clone());
         }
+        Component::Facing(c) => {
+            parts.facing.insert(id, *c);
+        }
         Component::Strength(c) => {
             parts.strength.insert(id, c.clone());
         }
@@ -338,6 +359,9 @@ fn add_component(parts: &mut Parts, id: Id, component: &Component) {
         Component::Blocker(c) => {
             parts.blocker.insert(id, c.clone());
         }
+        Component::Armor(c) => {
+            parts.armor.insert(id, c.clone());
+        }
         Component::Abilities(c) => {
             parts.abilities.insert(id, c.clone());
         }
@@ -353,6 +377,30 @@ fn add_component(parts: &mut Parts, id: Id, component: &Component) {
         Component::Summoner(c) => {
             parts.summoner.insert(id, c.clone());
         }
+        Component::Hero(c) => {
+            parts.hero.insert(id, c.clone());
+        }
+    }
+}
+
+pub fn apply_perk(state: &mut State, id: &Id, perk: Perk) {
+    let parts = state.parts_mut();
+    match perk {
+        Perk::Tough => {
+            let strength = parts.strength.get_mut(id).unwrap();
+            strength.base_strength.0 += 1;
+            strength.strength.0 += 1;
+        }
+        Perk::Accurate => parts.agent.get_mut(id).unwrap().attack_accuracy.0 += 1,
+        Perk::Strong => parts.agent.get_mut(id).unwrap().attack_strength.0 += 1,
+        Perk::Swift => parts.agent.get_mut(id).unwrap().move_points.0 += 1,
+    }
+}
+
+/// Only the heroes keep track of their kills.
+pub fn count_kill(state: &mut State, killer_id: &Id) {
+    if let Some(hero) = state.parts_mut().hero.get_mut(killer_id) {
+        hero.kills += 1;
     }
 }
 
@@ -389,7 +437,7 @@ pub fn apply_effect_timed(state: &mut State, id: &Id, timed_effect: &effect::Tim
     }
 }
 
-fn apply_effect_instant(state: &mut State, id: &Id, effect: &Effect) {
+pub fn apply_effect_instant(state: &mut State, id: &Id, effect: &Effect) {
     match effect {
         Effect::Create(effect) => apply_effect_create(state, id, effect),
         Effect::Kill(_) => apply_effect_kill(state, id),
@@ -401,7 +449,7 @@ fn apply_effect_instant(state: &mut State, id: &Id, effect: &Effect) {
         Effect::FlyOff(effect) => apply_effect_fly_off(state, id, effect),
         Effect::Throw(effect) => apply_effect_throw(state, id, effect),
         Effect::Bloodlust => apply_effect_bloodlust(state, id),
-        Effect::Dodge(_) => {}
+        Effect::Dodge(_) | Effect::Collision(_) => {}
     }
 }
 
@@ -446,8 +494,13 @@ pub fn apply_effect_wound(state: &mut State, id: &Id, damage: i32) {
     let parts = state.parts_mut();
     parts.strength.get_mut(id).unwrap().strength.0 -= damage;
 
+    // The shield has already absorbed this wound's damage.
+    if let Some(effects) = parts.effects.get_mut(id) {
+        effects.0.retain(|e| e.effect != effect::Lasting::Shielded);
+    }
+
     // TODO: Check why attack - 1?
-    {
+    if damage > 0 {
         let agent = parts.agent.get_mut(id).unwrap();
         agent.attacks.0 -= 1;
         if agent.attacks.0 < 0 {
@@ -1,20 +1,33 @@
+use std::collections::HashMap;
+
+use risc0_zkvm::sha::{Digest, Impl, Sha256};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     battle::{
+        self,
+        ability::{
+            definitions::{Definition, Definitions},
+            Ability,
+        },
         command::{self},
         component::{
-            Abilities, Agent, BelongsTo, Blocker, Component, Meta, ObjType, Parts, Pos, Strength,
+            self, Abilities, Agent, Armor, BelongsTo, Blocker, Component, Effects, Facing, Meta,
+            ObjType, Parts, PassiveAbilities, Pos, Schedule, Strength, Summoner,
         },
         event::Event,
         execute,
-        heroes::{prototype_for, Hero, HeroObject},
-        scenario::{self, Scenario},
-        state::apply::apply,
-        Id, PlayerId, TileType,
+        heroes::{Hero, HeroId, HeroObject},
+        prototypes::Prototypes,
+        scenario::{self, RoundLimit, Rules, Scenario, Team},
+        state::{
+            self,
+            apply::{apply, apply_perk},
+        },
+        tiles::{TileInfo, Tiles},
+        Id, PlayerId, Rounds, TileType,
     },
-    game::Level,
-    map,
+    map::{self, PosHex},
     utils::SimpleRng,
 };
 
@@ -23,9 +36,37 @@ use crate::battle::command::Command;
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct BattleResult {
-    pub winner_id: PlayerId,
+    /// All the players of the winning side.
+    pub winner_ids: Vec<PlayerId>,
+
+    /// Human player's survivors.
     pub survivor_types: Vec<ObjType>,
-    pub level: Level,
+
+    /// Human player's surviving campaign heroes.
+    #[serde(default)]
+    pub survivor_heroes: Vec<SurvivorHero>,
+}
+
+/// How a campaign hero has finished the battle.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SurvivorHero {
+    pub id: HeroId,
+    pub kills: u32,
+    pub strength: battle::Strength,
+    pub base_strength: battle::Strength,
+}
+
+impl SurvivorHero {
+    /// Wounds that the hero carries into the next battle.
+    pub fn wounds(&self) -> battle::Strength {
+        battle::Strength((self.base_strength.0 - self.strength.0).max(0))
+    }
+}
+
+impl BattleResult {
+    pub fn is_won_by(&self, player_id: PlayerId) -> bool {
+        self.winner_ids.contains(&player_id)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -33,10 +74,19 @@ pub struct State {
     pub parts: Parts,
     pub map: map::HexMap<TileType>,
     pub players_count: i32,
+    pub teams: Vec<Team>,
 
     pub player_id: PlayerId,
     pub battle_result: Option<BattleResult>,
-    pub level: Level,
+    pub scenario_digest: Digest,
+    pub prototypes: Prototypes,
+    pub ability_definitions: Definitions,
+    pub tiles: Tiles,
+    pub rules: Rules,
+
+    /// The number of completed rounds.
+    pub round: Rounds,
+    pub round_limit: RoundLimit,
 
     #[cfg(feature = "debug")]
     pub commands: Vec<Vec<Command>>,
@@ -49,7 +99,9 @@ pub struct State {
 impl State {
     pub fn new(
         scenario: Scenario,
-        level: Level,
+        prototypes: Prototypes,
+        ability_definitions: Definitions,
+        tiles: Tiles,
         rng: &mut SimpleRng,
         #[cfg(feature = "event")] cb: execute::Cb,
     ) -> Self {
@@ -64,11 +116,32 @@ impl State {
             turn_id: 0,
             #[cfg(feature = "debug")]
             heroes: vec![],
-            level,
+            scenario_digest: scenario.digest(),
+            prototypes,
+            ability_definitions,
+            tiles,
+            rules: scenario.rules,
             players_count: scenario.players_count,
+            teams: scenario.teams.clone(),
+            round: Rounds(0),
+            round_limit: scenario.round_limit,
         };
-        this.create_terrain(&scenario, rng);
-        this.create_objects(
+        for (&pos, &tile) in &scenario.tiles {
+            this.map.set_tile(pos, tile);
+        }
+        // Exact objects go first, so the random ones can't take their places.
+        for object in &scenario.objects {
+            this.create_object(
+                &object.typename,
+                object.pos,
+                object.owner,
+                rng,
+                #[cfg(feature = "event")]
+                cb,
+            );
+        }
+        this.create_random_terrain(&scenario, rng);
+        this.create_random_objects(
             &scenario,
             rng,
             #[cfg(feature = "event")]
@@ -78,8 +151,7 @@ impl State {
         this
     }
 
-    // TODO: Handle Scenario::exact_tiles
-    fn create_terrain(&mut self, scenario: &Scenario, rng: &mut SimpleRng) {
+    fn create_random_terrain(&mut self, scenario: &Scenario, rng: &mut SimpleRng) {
         for _ in 0..scenario.rocky_tiles_count {
             let pos = match scenario::random_free_pos(self, rng) {
                 Some(pos) => pos,
@@ -89,18 +161,13 @@ impl State {
         }
     }
 
-    // TODO: Handle Scenario::objects
-    fn create_objects(
+    fn create_random_objects(
         &mut self,
         scenario: &Scenario,
         rng: &mut SimpleRng,
         #[cfg(feature = "event")] cb: execute::Cb,
     ) {
-        let player_id_initial = self.player_id();
-        for group in scenario.randomized_objects.clone() {
-            if let Some(player_id) = group.owner {
-                self.set_player_id(player_id);
-            }
+        for group in &scenario.randomized_objects {
             for _ in 0..group.count {
                 let pos = match scenario::random_pos(self, group.owner, group.line, rng) {
                     Some(pos) => pos,
@@ -109,41 +176,44 @@ impl State {
                         continue;
                     }
                 };
-                let command = command::Create {
-                    prototype: group.typename.clone(),
+                self.create_object(
+                    &group.typename,
                     pos,
-                    owner: group.owner,
-                }
-                .into();
-                execute::execute(
-                    self,
-                    &command,
+                    group.owner,
                     rng,
                     #[cfg(feature = "event")]
                     cb,
-                )
-                .expect("Can't create an object");
+                );
             }
         }
-        for group in scenario.objects.clone() {
-            if let Some(player_id) = group.owner {
-                self.set_player_id(player_id);
-            }
-            let command = command::Create {
-                prototype: group.typename.clone(),
-                pos: group.pos,
-                owner: group.owner,
-            }
-            .into();
-            execute::execute(
-                self,
-                &command,
-                rng,
-                #[cfg(feature = "event")]
-                cb,
-            )
-            .expect("Can't create an object");
+    }
+
+    fn create_object(
+        &mut self,
+        typename: &ObjType,
+        pos: map::PosHex,
+        owner: Option<PlayerId>,
+        rng: &mut SimpleRng,
+        #[cfg(feature = "event")] cb: execute::Cb,
+    ) {
+        let player_id_initial = self.player_id();
+        if let Some(player_id) = owner {
+            self.set_player_id(player_id);
         }
+        let command = command::Create {
+            prototype: typename.clone(),
+            pos,
+            owner,
+        }
+        .into();
+        execute::execute(
+            self,
+            &command,
+            rng,
+            #[cfg(feature = "event")]
+            cb,
+        )
+        .expect("Can't create an object");
         self.set_player_id(player_id_initial);
     }
 
@@ -154,7 +224,7 @@ impl State {
         #[cfg(feature = "event")] cb: execute::Cb,
     ) {
         for group in heros {
-            let hero = Hero::from_index(group.index);
+            let hero = Hero::from_index(group.index).expect("Unknown hero index");
             let name = hero.name();
             let line = hero.line();
 
@@ -180,6 +250,20 @@ impl State {
                     cb,
                 )
                 .expect("Can't create an object");
+                if let Some(hero_id) = group.id {
+                    let id = state::agent_id_at_opt(self, pos).expect("Can't find the hero");
+                    let hero = component::Hero {
+                        id: hero_id,
+                        kills: 0,
+                    };
+                    self.parts.hero.insert(id, hero);
+                    for &perk in &group.perks {
+                        apply_perk(self, &id, perk);
+                    }
+                    // Injured heroes are still able to fight, even if barely.
+                    let strength = self.parts.strength.get_mut(&id).unwrap();
+                    strength.strength.0 = (strength.strength.0 - group.wounds.0).max(1);
+                }
             }
         }
 
@@ -206,8 +290,12 @@ impl State {
         &self.parts
     }
 
-    pub fn level(&self) -> &Level {
-        &self.level
+    pub fn scenario_digest(&self) -> Digest {
+        self.scenario_digest
+    }
+
+    pub fn rules(&self) -> Rules {
+        self.rules
     }
 
     pub fn strength(&self, id: &Id) -> &Strength {
@@ -246,13 +334,118 @@ impl State {
         self.players_count
     }
 
-    pub(crate) fn prototype_for(&self, name: &ObjType) -> Vec<Component> {
-        prototype_for(&name.0)
+    pub fn teams(&self) -> &[Team] {
+        &self.teams
+    }
+
+    pub fn prototypes(&self) -> &Prototypes {
+        &self.prototypes
+    }
+
+    pub fn ability_definitions(&self) -> &Definitions {
+        &self.ability_definitions
+    }
+
+    pub fn tiles(&self) -> &Tiles {
+        &self.tiles
+    }
+
+    pub fn tile_info(&self, pos: PosHex) -> &TileInfo {
+        self.tiles.get(self.map().tile(pos))
+    }
+
+    pub fn ability_definition(&self, ability: &Ability) -> &Definition {
+        self.ability_definitions.get(ability)
+    }
+
+    pub(crate) fn prototype_for(&self, name: &ObjType) -> Option<Vec<Component>> {
+        self.prototypes.0.get(name).cloned()
     }
 
     pub fn battle_result(&self) -> &Option<BattleResult> {
         &self.battle_result
     }
+
+    pub fn round(&self) -> Rounds {
+        self.round
+    }
+
+    pub fn round_limit(&self) -> RoundLimit {
+        self.round_limit
+    }
+
+    pub fn is_out_of_rounds(&self) -> bool {
+        self.round.0 >= self.round_limit.max_rounds.0
+    }
+
+    /// A hash of the battle state that doesn't depend on the iteration order of `Parts`.
+    ///
+    /// Debug-only fields are not included.
+    pub fn digest(&self) -> Digest {
+        let parts = &self.parts;
+        let canonical = CanonicalState {
+            strength: sorted(&parts.strength),
+            pos: sorted(&parts.pos),
+            facing: sorted(&parts.facing),
+            meta: sorted(&parts.meta),
+            belongs_to: sorted(&parts.belongs_to),
+            agent: sorted(&parts.agent),
+            blocker: sorted(&parts.blocker),
+            armor: sorted(&parts.armor),
+            abilities: sorted(&parts.abilities),
+            passive_abilities: sorted(&parts.passive_abilities),
+            effects: sorted(&parts.effects),
+            schedule: sorted(&parts.schedule),
+            summoner: sorted(&parts.summoner),
+            hero: sorted(&parts.hero),
+            next_obj_id: parts.next_obj_id,
+            map: &self.map,
+            players_count: self.players_count,
+            teams: &self.teams,
+            player_id: self.player_id,
+            battle_result: &self.battle_result,
+            scenario_digest: self.scenario_digest,
+            rules: self.rules,
+            round: self.round,
+            round_limit: self.round_limit,
+        };
+        let bytes = serde_json::to_vec(&canonical).expect("Can't serialize the state");
+        *Impl::hash_bytes(&bytes)
+    }
+}
+
+#[derive(Serialize)]
+struct CanonicalState<'a> {
+    strength: Vec<(&'a Id, &'a Strength)>,
+    pos: Vec<(&'a Id, &'a Pos)>,
+    facing: Vec<(&'a Id, &'a Facing)>,
+    meta: Vec<(&'a Id, &'a Meta)>,
+    belongs_to: Vec<(&'a Id, &'a BelongsTo)>,
+    agent: Vec<(&'a Id, &'a Agent)>,
+    blocker: Vec<(&'a Id, &'a Blocker)>,
+    armor: Vec<(&'a Id, &'a Armor)>,
+    abilities: Vec<(&'a Id, &'a Abilities)>,
+    passive_abilities: Vec<(&'a Id, &'a PassiveAbilities)>,
+    effects: Vec<(&'a Id, &'a Effects)>,
+    schedule: Vec<(&'a Id, &'a Schedule)>,
+    summoner: Vec<(&'a Id, &'a Summoner)>,
+    hero: Vec<(&'a Id, &'a component::Hero)>,
+    next_obj_id: Id,
+    map: &'a map::HexMap<TileType>,
+    players_count: i32,
+    teams: &'a [Team],
+    player_id: PlayerId,
+    battle_result: &'a Option<BattleResult>,
+    scenario_digest: Digest,
+    rules: Rules,
+    round: Rounds,
+    round_limit: RoundLimit,
+}
+
+fn sorted<T>(map: &HashMap<Id, T>) -> Vec<(&Id, &T)> {
+    let mut items: Vec<_> = map.iter().collect();
+    items.sort_by_key(|(id, _)| **id);
+    items
 }
 
 /// Public mutators. Be careful with them!
@@ -269,6 +462,10 @@ impl State {
         self.battle_result = Some(result);
     }
 
+    pub(crate) fn add_round(&mut self) {
+        self.round.0 += 1;
+    }
+
     pub(crate) fn alloc_id(&mut self) -> Id {
         self.parts.alloc_id()
     }
@@ -0,0 +1,152 @@
+//! Movement and combat properties of the tile types.
+//!
+//! Stored as a RON map from tile types to their properties, every tile type must be listed.
+//! Missing fields are the same as the plain tile's: `(move_cost: 1)` and nothing else.
+
+use std::{collections::BTreeMap, error, fmt};
+
+use risc0_zkvm::sha::Digest;
+use serde::{Deserialize, Serialize};
+
+use crate::battle::{data, movement::MovePoints, Dodge, Strength, TileType, Weight};
+
+/// Movement and combat modifiers of a tile type.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TileInfo {
+    /// Move points needed to enter the tile.
+    pub move_cost: MovePoints,
+
+    /// Lighter objects can't enter the tile.
+    pub min_weight: Option<Weight>,
+
+    /// Makes attacks against the objects on the tile less accurate.
+    pub dodge: Dodge,
+
+    /// Dealt to the agents entering the tile.
+    pub damage: Strength,
+
+    /// Blocks the line of sight and gives cover to the objects behind the tile.
+    pub obstructs: bool,
+}
+
+impl Default for TileInfo {
+    fn default() -> Self {
+        Self {
+            move_cost: MovePoints(1),
+            min_weight: None,
+            dodge: Dodge(0),
+            damage: Strength(0),
+            obstructs: false,
+        }
+    }
+}
+
+impl TileInfo {
+    pub fn is_passable_for(&self, weight: Weight) -> bool {
+        match self.min_weight {
+            Some(min_weight) => weight >= min_weight,
+            None => true,
+        }
+    }
+}
+
+/// Properties of every tile type.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Tiles(pub BTreeMap<TileType, TileInfo>);
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Error {
+    MissingTileType(TileType),
+    BadMoveCost(TileType),
+    NegativeDamage(TileType),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MissingTileType(tile) => write!(f, "{:?} tiles aren't described", tile),
+            Error::BadMoveCost(tile) => write!(f, "{:?} tiles must cost at least 1 move", tile),
+            Error::NegativeDamage(tile) => write!(f, "{:?} tiles have negative damage", tile),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+#[derive(Debug, derive_more::From)]
+pub enum LoadError {
+    Ron(ron::error::SpannedError),
+    Invalid(Error),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Ron(e) => write!(f, "Bad RON: {}", e),
+            LoadError::Invalid(e) => write!(f, "Bad tiles: {}", e),
+        }
+    }
+}
+
+impl error::Error for LoadError {}
+
+pub fn from_ron(s: &str) -> Result<Tiles, LoadError> {
+    let tiles: Tiles = ron::de::from_str(s)?;
+    tiles.check()?;
+    Ok(tiles)
+}
+
+impl Tiles {
+    pub fn check(&self) -> Result<(), Error> {
+        for tile in TileType::all() {
+            let info = self.0.get(&tile).ok_or(Error::MissingTileType(tile))?;
+            if info.move_cost.0 < 1 {
+                return Err(Error::BadMoveCost(tile));
+            }
+            if info.damage.0 < 0 {
+                return Err(Error::NegativeDamage(tile));
+            }
+        }
+        Ok(())
+    }
+
+    /// Panics if the tile type is missing, so `check` must be called first.
+    pub fn get(&self, tile: TileType) -> &TileInfo {
+        &self.0[&tile]
+    }
+
+    pub fn digest(&self) -> Digest {
+        data::digest(&self.0)
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn shipped() -> Tiles {
+    let s = include_str!("../../../assets/tiles.ron");
+    from_ron(s).expect("Can't load the tiles")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_ron, shipped, Error, LoadError};
+    use crate::battle::{movement::MovePoints, Strength, TileType, Weight};
+
+    #[test]
+    fn test_shipped() {
+        let tiles = shipped();
+        assert_eq!(tiles.get(TileType::Plain).move_cost, MovePoints(1));
+        assert_eq!(tiles.get(TileType::Lava).damage, Strength(2));
+        assert_eq!(tiles.get(TileType::Water).min_weight, Some(Weight::Heavy));
+    }
+
+    #[test]
+    fn test_missing_tile_type() {
+        let s = "{ Plain: () }";
+        match from_ron(s) {
+            Err(LoadError::Invalid(Error::MissingTileType(_))) => {}
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+}
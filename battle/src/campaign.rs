@@ -1,20 +1,52 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use crate::{
-    battle::{component::ObjType, scenario::Scenario, state::BattleResult, PlayerId},
+    battle::{
+        component::ObjType,
+        heroes::{Hero, HeroId, HeroObject, Perk},
+        prototypes::Prototypes,
+        scenario::{self, Scenario},
+        state::BattleResult,
+        tiles::Tiles,
+        PlayerId, Strength, TileType,
+    },
     utils::{self},
 };
 
+/// Every this much experience the fighter gets a new perk to choose.
+pub const EXPERIENCE_PER_PERK: u32 = 3;
+
+pub const HEAL_COST_PER_WOUND: Renown = Renown(3);
+
+const NAMES: [&str; 16] = [
+    "Aldric", "Bran", "Cedric", "Dunstan", "Edric", "Folke", "Gareth", "Hakon", "Ivo", "Jorund",
+    "Kael", "Leif", "Magnus", "Njal", "Osric", "Roderick",
+];
+
+/// Names repeat with a number once the list is exhausted: "Bran", ..., "Bran 2".
+fn fighter_name(id: HeroId) -> String {
+    let i = id.0 as usize;
+    let name = NAMES[i % NAMES.len()];
+    if i < NAMES.len() {
+        name.to_owned()
+    } else {
+        format!("{} {}", name, i / NAMES.len() + 1)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub enum Mode {
     /// Recruiting/upgrading fighters or starting a new battle.
     PreparingForBattle,
 
-    /// Campaign is finished, the player have won.
+    /// Picking one of the current node's successors on the campaign map.
+    ChoosingNextNode,
+
+    /// Campaign is finished: the player has completed a terminal node.
     Won,
 
-    /// Campaign is finished, the player have lost.
+    /// Campaign is finished: the player has lost a battle.
     Failed,
 }
 
@@ -23,7 +55,7 @@ pub enum Mode {
 pub struct Renown(pub i32);
 
 /// An award that is given to the player after the successful battle.
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Award {
     #[serde(default)]
     pub recruits: Vec<ObjType>,
@@ -33,23 +65,175 @@ pub struct Award {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum Action {
-    Recruit { agent_type: ObjType },
-    Upgrade { from: ObjType, to: ObjType },
+    Recruit {
+        agent_type: ObjType,
+    },
+    Upgrade {
+        id: HeroId,
+        from: ObjType,
+        to: ObjType,
+    },
+    ChoosePerk {
+        id: HeroId,
+        perk: Perk,
+    },
+
+    /// The fighter skips the next battle and comes back fully healed.
+    Rest {
+        id: HeroId,
+    },
+
+    /// Heals all the fighter's wounds right away.
+    Heal {
+        id: HeroId,
+    },
+
+    /// Takes one of the rewards of a `Event::Choice` node.
+    ChooseReward {
+        award: Award,
+    },
+}
+
+/// A recruited hero. Keeps its id, name and record through upgrades.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Fighter {
+    pub id: HeroId,
+    pub name: String,
+    pub typename: ObjType,
+
+    /// Enemies killed in all the battles.
+    pub kills: u32,
+
+    /// Battles survived.
+    pub battles: u32,
+
+    pub experience: u32,
+    pub perks: Vec<Perk>,
+
+    /// Strength lost in the previous battles.
+    #[serde(default)]
+    pub wounds: Strength,
+
+    #[serde(default)]
+    pub is_resting: bool,
+}
+
+impl Fighter {
+    /// How many perks the fighter has earned, but not chosen yet.
+    pub fn unchosen_perks(&self) -> u32 {
+        (self.experience / EXPERIENCE_PER_PERK).saturating_sub(self.perks.len() as u32)
+    }
+
+    pub fn hero_object(&self) -> HeroObject {
+        let hero = Hero::from_name(&self.typename.0).expect("Not a hero type");
+        HeroObject {
+            wounds: self.wounds,
+            ..HeroObject::veteran(hero, self.id, self.perks.clone())
+        }
+    }
+}
+
+/// An index of a node in the campaign plan.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct NodeId(pub usize);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum Event {
+    Battle {
+        scenario: Scenario,
+        award: Award,
+    },
+
+    /// Offers the agents for recruitment at the usual price.
+    Shop {
+        agents: Vec<ObjType>,
+    },
+
+    /// The agents join the group for free.
+    Recruits {
+        agents: Vec<ObjType>,
+    },
+
+    /// The player takes one of the rewards.
+    Choice {
+        rewards: Vec<Award>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct CampaignNode {
-    pub scenario: Scenario,
-    pub award: Award,
+    /// Shown on the campaign map.
+    pub title: String,
+
+    pub event: Event,
+
+    /// Nodes that can be visited after this one. Completing a node
+    /// without successors wins the campaign.
+    #[serde(default)]
+    pub next: Vec<NodeId>,
 }
 
-#[allow(dead_code)]
-fn casualties(initial_agents: &[ObjType], survivors: &[ObjType]) -> Vec<ObjType> {
-    let mut agents = initial_agents.to_vec();
-    for typename in survivors {
-        assert!(utils::try_remove_item(&mut agents, typename));
+impl CampaignNode {
+    pub fn scenario(&self) -> Option<&Scenario> {
+        match self.event {
+            Event::Battle { ref scenario, .. } => Some(scenario),
+            _ => None,
+        }
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        self.next.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    NoNodes,
+    UnknownNextNode {
+        node: NodeId,
+        next: NodeId,
+    },
+
+    /// Non-battle nodes that lead to each other in a circle.
+    EventLoop(NodeId),
+
+    /// A saved campaign mentions a type that the prototypes no longer have.
+    UnknownObjType(ObjType),
+
+    /// Only heroes can join the player's group.
+    NotAHero(ObjType),
+
+    /// A saved campaign is at a node that its plan doesn't have.
+    UnknownCurrentNode(NodeId),
+
+    /// A saved campaign prepares for a battle at a non-battle node.
+    NotABattle(NodeId),
+
+    /// A saved campaign chooses the next node at a terminal one.
+    NoNextNode(NodeId),
+
+    /// A saved battle no longer passes the scenario checks.
+    BadScenario {
+        node: NodeId,
+        error: scenario::ValidationError,
+    },
+
+    /// A saved battle has a tile type that the tiles don't describe.
+    UnknownTileType {
+        node: NodeId,
+        tile: TileType,
+    },
+
+    /// There's no cost or upgrade info for the agent type.
+    NoAgentInfo(ObjType),
+}
+
+fn check_hero_type(typename: &ObjType) -> Result<(), Error> {
+    match Hero::from_name(&typename.0) {
+        Some(_) => Ok(()),
+        None => Err(Error::NotAHero(typename.clone())),
     }
-    agents
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -58,6 +242,60 @@ pub struct Plan {
     nodes: Vec<CampaignNode>,
 }
 
+impl Plan {
+    pub fn nodes(&self) -> &[CampaignNode] {
+        &self.nodes
+    }
+
+    /// The campaign starts at the first node.
+    pub fn check(&self) -> Result<(), Error> {
+        if self.nodes.is_empty() {
+            return Err(Error::NoNodes);
+        }
+        for (i, node) in self.nodes.iter().enumerate() {
+            for &next in &node.next {
+                if next.0 >= self.nodes.len() {
+                    let node = NodeId(i);
+                    return Err(Error::UnknownNextNode { node, next });
+                }
+            }
+        }
+        // Non-battle nodes with one successor are passed automatically,
+        // so a circle of them would never let the player go.
+        for start in 0..self.nodes.len() {
+            let mut i = start;
+            for _ in 0..=self.nodes.len() {
+                let node = &self.nodes[i];
+                if node.scenario().is_some() || node.next.len() != 1 {
+                    break;
+                }
+                i = node.next[0].0;
+                if i == start {
+                    return Err(Error::EventLoop(NodeId(start)));
+                }
+            }
+        }
+        let mut recruits: Vec<&ObjType> = self.initial_agents.iter().collect();
+        for node in &self.nodes {
+            match &node.event {
+                Event::Battle { award, .. } => recruits.extend(&award.recruits),
+                Event::Shop { agents } | Event::Recruits { agents } => recruits.extend(agents),
+                Event::Choice { rewards } => {
+                    recruits.extend(rewards.iter().flat_map(|award| &award.recruits));
+                }
+            }
+        }
+        recruits.into_iter().try_for_each(check_hero_type)
+    }
+}
+
+#[cfg(not(feature = "event"))]
+#[cfg(test)]
+pub(crate) fn campaign_01() -> Plan {
+    let s = include_str!("../../assets/campaign_01.ron");
+    ron::de::from_str(s).expect("Can't parse the campaign")
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AgentInfo {
     pub cost: Renown,
@@ -68,11 +306,13 @@ pub struct AgentInfo {
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct State {
-    scenarios: Vec<CampaignNode>,
-    current_scenario_index: i32,
+    nodes: Vec<CampaignNode>,
+    current_node: NodeId,
+    battles_won: i32,
     mode: Mode,
-    agents: Vec<ObjType>,
-    last_battle_casualties: Vec<ObjType>,
+    fighters: Vec<Fighter>,
+    next_hero_id: HeroId,
+    last_battle_casualties: Vec<Fighter>,
     agent_info: HashMap<ObjType, AgentInfo>,
     actions: Vec<Action>,
     renown: Renown,
@@ -80,43 +320,265 @@ pub struct State {
 
 impl State {
     pub fn new(plan: Plan, agent_info: HashMap<ObjType, AgentInfo>) -> Self {
-        assert!(!plan.nodes.is_empty(), "No scenarios");
-        Self {
-            current_scenario_index: 0,
-            scenarios: plan.nodes,
+        if let Err(err) = plan.check() {
+            panic!("Bad campaign plan: {:?}", err);
+        }
+        let mut this = Self {
+            nodes: plan.nodes,
+            current_node: NodeId(0),
+            battles_won: 0,
             mode: Mode::PreparingForBattle,
-            agents: plan.initial_agents,
+            fighters: Vec::new(),
+            next_hero_id: HeroId(0),
             last_battle_casualties: Vec::new(),
             actions: Vec::new(),
             agent_info,
             renown: Renown(0),
+        };
+        for typename in plan.initial_agents {
+            this.recruit(typename);
+        }
+        this.enter_node(NodeId(0));
+        this
+    }
+
+    /// Battles wait for the player, other events happen right away.
+    fn enter_node(&mut self, id: NodeId) {
+        self.current_node = id;
+        match self.nodes[id.0].event.clone() {
+            Event::Battle { .. } => {
+                self.mode = Mode::PreparingForBattle;
+                return;
+            }
+            Event::Shop { agents } => {
+                for agent_type in agents {
+                    self.actions.push(Action::Recruit { agent_type });
+                }
+            }
+            Event::Recruits { agents } => {
+                for typename in agents {
+                    self.recruit(typename);
+                }
+            }
+            Event::Choice { rewards } => {
+                for award in rewards {
+                    self.actions.push(Action::ChooseReward { award });
+                }
+            }
+        }
+        self.leave_node();
+    }
+
+    /// The only way forward is taken automatically.
+    fn leave_node(&mut self) {
+        match self.nodes[self.current_node.0].next.as_slice() {
+            [] => self.mode = Mode::Won,
+            &[next] => self.enter_node(next),
+            _ => self.mode = Mode::ChoosingNextNode,
+        }
+    }
+
+    pub fn choose_next_node(&mut self, id: NodeId) {
+        assert_eq!(self.mode(), Mode::ChoosingNextNode);
+        assert!(self.next_nodes().contains(&id));
+        self.enter_node(id);
+    }
+
+    fn give_award(&mut self, award: &Award) {
+        self.renown.0 += award.renown.0;
+        for recruit in &award.recruits {
+            let action = Action::Recruit {
+                agent_type: recruit.clone(),
+            };
+            self.actions.push(action);
         }
     }
 
+    fn recruit(&mut self, typename: ObjType) {
+        let id = self.next_hero_id;
+        self.next_hero_id.0 += 1;
+        self.fighters.push(Fighter {
+            id,
+            name: fighter_name(id),
+            typename,
+            kills: 0,
+            battles: 0,
+            experience: 0,
+            perks: Vec::new(),
+            wounds: Strength(0),
+            is_resting: false,
+        });
+    }
+
+    fn fighter_mut(&mut self, id: HeroId) -> &mut Fighter {
+        self.fighters
+            .iter_mut()
+            .find(|fighter| fighter.id == id)
+            .expect("No such fighter")
+    }
+
     pub fn mode(&self) -> Mode {
         self.mode
     }
 
-    pub fn last_battle_casualties(&self) -> &[ObjType] {
+    pub fn last_battle_casualties(&self) -> &[Fighter] {
         &self.last_battle_casualties
     }
 
     pub fn scenario(&self) -> &Scenario {
-        assert!(!self.scenarios.is_empty());
-        let i = self.current_scenario_index as usize;
-        &self.scenarios[i].scenario
+        self.current_node()
+            .scenario()
+            .expect("The current node isn't a battle")
+    }
+
+    pub fn node(&self, id: NodeId) -> &CampaignNode {
+        &self.nodes[id.0]
+    }
+
+    pub fn current_node(&self) -> &CampaignNode {
+        self.node(self.current_node)
+    }
+
+    pub fn next_nodes(&self) -> &[NodeId] {
+        &self.current_node().next
+    }
+
+    pub fn battles_won(&self) -> i32 {
+        self.battles_won
+    }
+
+    /// A loaded campaign may have been saved by another version of the game
+    /// or edited by hand.
+    pub fn check(&self, prototypes: &Prototypes, tiles: &Tiles) -> Result<(), Error> {
+        let plan = Plan {
+            initial_agents: Vec::new(),
+            nodes: self.nodes.clone(),
+        };
+        plan.check()?;
+        let node = match self.nodes.get(self.current_node.0) {
+            Some(node) => node,
+            None => return Err(Error::UnknownCurrentNode(self.current_node)),
+        };
+        if self.mode == Mode::PreparingForBattle && node.scenario().is_none() {
+            return Err(Error::NotABattle(self.current_node));
+        }
+        if self.mode == Mode::ChoosingNextNode && node.is_terminal() {
+            return Err(Error::NoNextNode(self.current_node));
+        }
+        for fighter in &self.fighters {
+            check_hero_type(&fighter.typename)?;
+        }
+        self.check_scenarios(tiles)?;
+        self.check_obj_types(prototypes)?;
+        self.check_agent_info()
+    }
+
+    /// The battles are checked when the campaign starts, not when they do.
+    fn check_scenarios(&self, tiles: &Tiles) -> Result<(), Error> {
+        for (i, node) in self.nodes.iter().enumerate() {
+            let scenario = match node.scenario() {
+                Some(scenario) => scenario,
+                None => continue,
+            };
+            let node = NodeId(i);
+            if let Err(errors) = scenario.validate_layout() {
+                let error = errors[0].clone();
+                return Err(Error::BadScenario { node, error });
+            }
+            if let Some(&tile) = scenario.tiles.values().find(|t| !tiles.0.contains_key(t)) {
+                return Err(Error::UnknownTileType { node, tile });
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces the costs and upgrades the campaign was saved with,
+    /// so a loaded campaign follows the current assets.
+    ///
+    /// Upgrades that are no longer offered are dropped.
+    pub fn set_agent_info(&mut self, agent_info: HashMap<ObjType, AgentInfo>) {
+        self.agent_info = agent_info;
+        let agent_info = &self.agent_info;
+        self.actions.retain(|action| match action {
+            Action::Upgrade { from, to, .. } => agent_info
+                .get(from)
+                .is_some_and(|info| info.upgrades.contains(to)),
+            _ => true,
+        });
+    }
+
+    /// Every agent that the player has or may recruit needs a cost and upgrades.
+    fn check_agent_info(&self) -> Result<(), Error> {
+        let mut types: Vec<&ObjType> = self.fighters.iter().map(|f| &f.typename).collect();
+        for action in &self.actions {
+            match action {
+                Action::Recruit { agent_type } => types.push(agent_type),
+                Action::Upgrade { to, .. } => types.push(to),
+                Action::ChooseReward { award } => types.extend(&award.recruits),
+                _ => {}
+            }
+        }
+        for node in &self.nodes {
+            match &node.event {
+                Event::Battle { award, .. } => types.extend(&award.recruits),
+                Event::Shop { agents } => types.extend(agents),
+                Event::Choice { rewards } => {
+                    types.extend(rewards.iter().flat_map(|award| &award.recruits));
+                }
+                Event::Recruits { .. } => {}
+            }
+        }
+        match types.into_iter().find(|t| !self.agent_info.contains_key(t)) {
+            Some(typename) => Err(Error::NoAgentInfo(typename.clone())),
+            None => Ok(()),
+        }
     }
 
-    pub fn current_scenario_index(&self) -> i32 {
-        self.current_scenario_index
+    /// A loaded campaign may have been saved with different assets.
+    fn check_obj_types(&self, prototypes: &Prototypes) -> Result<(), Error> {
+        let mut types: Vec<&ObjType> = self.fighters.iter().map(|f| &f.typename).collect();
+        for action in &self.actions {
+            match action {
+                Action::Recruit { agent_type } => types.push(agent_type),
+                Action::Upgrade { to, .. } => types.push(to),
+                Action::ChooseReward { award } => types.extend(&award.recruits),
+                _ => {}
+            }
+        }
+        for node in &self.nodes {
+            match &node.event {
+                Event::Battle { scenario, award } => {
+                    types.extend(scenario.randomized_objects.iter().map(|g| &g.typename));
+                    types.extend(scenario.objects.iter().map(|o| &o.typename));
+                    types.extend(&award.recruits);
+                }
+                Event::Shop { agents } | Event::Recruits { agents } => types.extend(agents),
+                Event::Choice { rewards } => {
+                    types.extend(rewards.iter().flat_map(|award| &award.recruits));
+                }
+            }
+        }
+        match types.into_iter().find(|t| !prototypes.0.contains_key(t)) {
+            Some(typename) => Err(Error::UnknownObjType(typename.clone())),
+            None => Ok(()),
+        }
     }
 
-    pub fn scenarios_count(&self) -> i32 {
-        self.scenarios.len() as _
+    pub fn fighters(&self) -> &[Fighter] {
+        &self.fighters
     }
 
-    pub fn agents(&self) -> &[ObjType] {
-        &self.agents
+    pub fn fighter(&self, id: HeroId) -> Option<&Fighter> {
+        self.fighters.iter().find(|fighter| fighter.id == id)
+    }
+
+    /// The heroes to create in the next battle.
+    pub fn heroes(&self) -> Vec<HeroObject> {
+        self.fighters
+            .iter()
+            .filter(|fighter| !fighter.is_resting)
+            .map(Fighter::hero_object)
+            .collect()
     }
 
     pub fn renown(&self) -> Renown {
@@ -128,18 +590,56 @@ impl State {
     }
 
     pub fn execute_action(&mut self, action: Action) {
-        assert_eq!(self.mode(), Mode::PreparingForBattle);
+        assert!(matches!(
+            self.mode(),
+            Mode::PreparingForBattle | Mode::ChoosingNextNode
+        ));
         assert!(utils::try_remove_item(&mut self.actions, &action));
         let cost = self.action_cost(&action);
         assert!(self.renown.0 >= cost.0);
         self.renown.0 -= cost.0;
         match action {
             Action::Recruit { agent_type } => {
-                self.agents.push(agent_type);
+                self.recruit(agent_type);
             }
-            Action::Upgrade { from, to } => {
-                assert!(utils::try_remove_item(&mut self.agents, &from));
-                self.agents.push(to);
+            Action::Upgrade { id, from, to } => {
+                let fighter = self.fighter_mut(id);
+                assert_eq!(fighter.typename, from);
+                fighter.typename = to;
+                // Other upgrade options for this fighter are gone now.
+                self.actions.retain(
+                    |action| !matches!(action, Action::Upgrade { id: other, .. } if *other == id),
+                );
+            }
+            Action::ChoosePerk { id, perk } => {
+                let fighter = self.fighter_mut(id);
+                assert!(fighter.unchosen_perks() > 0);
+                assert!(!fighter.perks.contains(&perk));
+                fighter.perks.push(perk);
+                if fighter.unchosen_perks() == 0 {
+                    self.actions.retain(
+                        |action| !matches!(action, Action::ChoosePerk { id: other, .. } if *other == id),
+                    );
+                }
+            }
+            Action::Rest { id } => {
+                self.fighter_mut(id).is_resting = true;
+                self.remove_recovery_actions(id);
+                // Someone has to go to the battle.
+                let active_count = self.fighters.iter().filter(|f| !f.is_resting).count();
+                if active_count <= 1 {
+                    self.actions
+                        .retain(|action| !matches!(action, Action::Rest { .. }));
+                }
+            }
+            Action::Heal { id } => {
+                self.fighter_mut(id).wounds = Strength(0);
+                self.remove_recovery_actions(id);
+            }
+            Action::ChooseReward { award } => {
+                self.actions
+                    .retain(|action| !matches!(action, Action::ChooseReward { .. }));
+                self.give_award(&award);
             }
         }
     }
@@ -147,37 +647,64 @@ impl State {
     pub fn action_cost(&self, action: &Action) -> Renown {
         match action {
             Action::Recruit { agent_type } => {
-                let squad_size_penalty = self.agents.len() as i32;
+                let squad_size_penalty = self.fighters.len() as i32;
                 let agent_cost = self.agent_info[agent_type].cost;
                 Renown(agent_cost.0 + squad_size_penalty)
             }
-            Action::Upgrade { from, to } => {
+            Action::Upgrade { from, to, .. } => {
                 let cost_from = self.agent_info[from].cost;
                 let cost_to = self.agent_info[to].cost;
                 Renown(cost_to.0 - cost_from.0)
             }
+            Action::ChoosePerk { .. } | Action::Rest { .. } | Action::ChooseReward { .. } => {
+                Renown(0)
+            }
+            Action::Heal { id } => {
+                let wounds = self.fighter(*id).map_or(0, |fighter| fighter.wounds.0);
+                Renown(wounds * HEAL_COST_PER_WOUND.0)
+            }
         }
     }
 
+    fn remove_recovery_actions(&mut self, id: HeroId) {
+        self.actions.retain(|action| {
+            !matches!(action, Action::Rest { id: other } | Action::Heal { id: other } if *other == id)
+        });
+    }
+
     pub fn report_battle_results(&mut self, result: &BattleResult) -> Result<(), ()> {
         {
             if self.mode != Mode::PreparingForBattle {
                 return Err(());
             }
 
-            if result.winner_id == PlayerId(0) && result.survivor_types.is_empty() {
+            if result.is_won_by(PlayerId(0)) && result.survivor_types.is_empty() {
                 // You can't win with no survivors.
                 return Err(());
             }
 
-            for survivor in &result.survivor_types {
-                if !self.agents.contains(survivor) {
-                    // This agent isn't a survivor.
+            // Survivors are matched by their hero ids, but their types must agree too.
+            let mut survivor_types = Vec::new();
+            for (i, hero) in result.survivor_heroes.iter().enumerate() {
+                if result.survivor_heroes[..i].iter().any(|h| h.id == hero.id) {
                     return Err(());
                 }
+                match self.fighter(hero.id) {
+                    Some(fighter) if !fighter.is_resting => {
+                        survivor_types.push(fighter.typename.clone())
+                    }
+                    // This agent isn't a survivor.
+                    _ => return Err(()),
+                }
+            }
+            let mut reported_types = result.survivor_types.clone();
+            survivor_types.sort_by(|a, b| a.0.cmp(&b.0));
+            reported_types.sort_by(|a, b| a.0.cmp(&b.0));
+            if survivor_types != reported_types {
+                return Err(());
             }
 
-            if result.winner_id != PlayerId(0) {
+            if !result.is_won_by(PlayerId(0)) {
                 self.mode = Mode::Failed;
                 return Ok(());
             }
@@ -185,41 +712,413 @@ impl State {
 
         self.actions.clear();
 
-        self.last_battle_casualties = casualties(&self.agents, &result.survivor_types);
-        self.agents = result.survivor_types.clone();
+        let mut fighters = Vec::new();
+        let mut casualties = Vec::new();
+        for mut fighter in self.fighters.drain(..) {
+            let survivor = result
+                .survivor_heroes
+                .iter()
+                .find(|hero| hero.id == fighter.id);
+            if fighter.is_resting {
+                fighter.is_resting = false;
+                fighter.wounds = Strength(0);
+                fighters.push(fighter);
+            } else if let Some(hero) = survivor {
+                fighter.battles += 1;
+                fighter.kills += hero.kills;
+                fighter.experience += 1 + hero.kills;
+                fighter.wounds = hero.wounds();
+                fighters.push(fighter);
+            } else {
+                casualties.push(fighter);
+            }
+        }
+        self.fighters = fighters;
+        self.last_battle_casualties = casualties;
 
-        if self.current_scenario_index + 1 >= self.scenarios.len() as _ {
+        if self.current_node().is_terminal() {
             self.mode = Mode::Won;
         } else {
-            let i = self.current_scenario_index as usize;
-            let award = &self.scenarios[i].award;
-            self.renown.0 += award.renown.0;
-            for recruit in &award.recruits {
-                let action = Action::Recruit {
-                    agent_type: recruit.clone(),
-                };
-                self.actions.push(action);
-            }
-
-            if self.current_scenario_index() != 0 {
-                let mut set = HashSet::new();
-                for agent in &self.agents {
-                    if !set.contains(agent) {
-                        let agent_info = self.agent_info.get(agent).unwrap();
-                        for upgrade in &agent_info.upgrades {
-                            let from = agent.clone();
-                            let to = upgrade.clone();
-                            self.actions.push(Action::Upgrade { from, to });
-                        }
-                        set.insert(agent.clone());
+            self.battles_won += 1;
+            if let Event::Battle { award, .. } = self.current_node().event.clone() {
+                self.give_award(&award);
+            }
+
+            if self.battles_won > 1 {
+                for fighter in &self.fighters {
+                    let agent_info = self.agent_info.get(&fighter.typename).unwrap();
+                    for upgrade in &agent_info.upgrades {
+                        let id = fighter.id;
+                        let from = fighter.typename.clone();
+                        let to = upgrade.clone();
+                        self.actions.push(Action::Upgrade { id, from, to });
                     }
                 }
             }
 
-            self.current_scenario_index += 1;
-            self.mode = Mode::PreparingForBattle;
+            for fighter in &self.fighters {
+                if fighter.wounds.0 == 0 {
+                    continue;
+                }
+                let id = fighter.id;
+                self.actions.push(Action::Heal { id });
+                if self.fighters.len() > 1 {
+                    self.actions.push(Action::Rest { id });
+                }
+            }
+
+            for fighter in &self.fighters {
+                if fighter.unchosen_perks() == 0 {
+                    continue;
+                }
+                for perk in Perk::all() {
+                    if !fighter.perks.contains(&perk) {
+                        let id = fighter.id;
+                        self.actions.push(Action::ChoosePerk { id, perk });
+                    }
+                }
+            }
+
+            self.leave_node();
         }
 
         Ok(())
     }
 }
+
+#[cfg(not(feature = "event"))]
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        battle::{
+            component::ObjType,
+            heroes::{HeroId, Perk},
+            prototypes, scenario,
+            state::{BattleResult, SurvivorHero},
+            tiles, PlayerId, Strength, TileType,
+        },
+        campaign::{
+            campaign_01, Action, AgentInfo, CampaignNode, Error, Event, Mode, NodeId, Plan, Renown,
+            State,
+        },
+        map::PosHex,
+    };
+
+    fn agent_info() -> HashMap<ObjType, AgentInfo> {
+        let s = include_str!("../../assets/agent_campaign_info.ron");
+        ron::de::from_str(s).expect("Can't parse the agent info")
+    }
+
+    fn survivor(id: HeroId, kills: u32, strength: i32) -> SurvivorHero {
+        SurvivorHero {
+            id,
+            kills,
+            strength: Strength(strength),
+            base_strength: Strength(3),
+        }
+    }
+
+    fn victory(state: &State) -> BattleResult {
+        let fighters = state.fighters().iter().filter(|f| !f.is_resting);
+        let (types, heroes) = fighters
+            .map(|f| (f.typename.clone(), survivor(f.id, 0, 3)))
+            .unzip();
+        BattleResult {
+            winner_ids: vec![PlayerId(0)],
+            survivor_types: types,
+            survivor_heroes: heroes,
+        }
+    }
+
+    fn event_node(event: Event, next: Vec<usize>) -> CampaignNode {
+        CampaignNode {
+            title: String::new(),
+            event,
+            next: next.into_iter().map(NodeId).collect(),
+        }
+    }
+
+    #[test]
+    fn test_veterans() {
+        let mut plan = campaign_01();
+        plan.initial_agents.push("swordsman".into());
+        let mut state = State::new(plan, HashMap::new());
+        let fighters = state.fighters().to_vec();
+        assert_eq!(fighters.len(), 2);
+        assert_ne!(fighters[0].id, fighters[1].id);
+        assert_ne!(fighters[0].name, fighters[1].name);
+        let veteran = &fighters[0];
+        let mut result = BattleResult {
+            winner_ids: vec![PlayerId(0)],
+            survivor_types: vec![veteran.typename.clone()],
+            survivor_heroes: vec![survivor(veteran.id, 2, 3)],
+        };
+        // The reported types must match the surviving heroes.
+        result.survivor_types.push(veteran.typename.clone());
+        assert_eq!(state.report_battle_results(&result), Err(()));
+        result.survivor_types.pop();
+        assert_eq!(state.report_battle_results(&result), Ok(()));
+        assert_eq!(state.fighters().len(), 1);
+        assert_eq!(state.last_battle_casualties().len(), fighters.len() - 1);
+        let fighter = &state.fighters()[0];
+        assert_eq!(
+            (fighter.kills, fighter.battles, fighter.experience),
+            (2, 1, 3)
+        );
+        assert_eq!(fighter.unchosen_perks(), 1);
+        let action = Action::ChoosePerk {
+            id: veteran.id,
+            perk: Perk::Tough,
+        };
+        assert!(state.available_actions().contains(&action));
+        state.execute_action(action);
+        let is_perk_choice = |action: &Action| matches!(action, Action::ChoosePerk { .. });
+        assert!(!state.available_actions().iter().any(is_perk_choice));
+        assert_eq!(state.heroes()[0].perks, vec![Perk::Tough]);
+        assert_eq!(state.heroes()[0].id, Some(veteran.id));
+    }
+
+    #[test]
+    fn test_injuries() {
+        let mut plan = campaign_01();
+        plan.initial_agents.push("swordsman".into());
+        plan.initial_agents.push("swordsman".into());
+        let mut state = State::new(plan, agent_info());
+        let fighters = state.fighters().to_vec();
+        let ids: Vec<_> = fighters.iter().map(|fighter| fighter.id).collect();
+        let types = fighters.iter().map(|fighter| fighter.typename.clone());
+        let result = BattleResult {
+            winner_ids: vec![PlayerId(0)],
+            survivor_types: types.collect(),
+            survivor_heroes: vec![
+                survivor(ids[0], 0, 1),
+                survivor(ids[1], 0, 2),
+                survivor(ids[2], 0, 3),
+            ],
+        };
+        state.report_battle_results(&result).unwrap();
+        let wounds: Vec<_> = state.fighters().iter().map(|f| f.wounds).collect();
+        assert_eq!(wounds, vec![Strength(2), Strength(1), Strength(0)]);
+        let heal = Action::Heal { id: ids[0] };
+        assert_eq!(state.action_cost(&heal), Renown(6));
+        let rest = Action::Rest { id: ids[1] };
+        assert!(state.available_actions().contains(&rest));
+        state.execute_action(rest);
+        state.execute_action(heal);
+        let heroes = state.heroes();
+        assert_eq!(heroes.len(), 2);
+        assert_eq!(heroes[0].wounds, Strength(0));
+        state.choose_next_node(NodeId(1));
+        // Only the heroes that took part in the battle can survive it.
+        let mut result = BattleResult {
+            winner_ids: vec![PlayerId(0)],
+            survivor_types: vec![fighters[1].typename.clone()],
+            survivor_heroes: vec![survivor(ids[1], 0, 3)],
+        };
+        assert_eq!(state.report_battle_results(&result), Err(()));
+        result.survivor_types = vec![fighters[2].typename.clone()];
+        result.survivor_heroes = vec![survivor(ids[2], 0, 3)];
+        state.report_battle_results(&result).unwrap();
+        let casualties = state.last_battle_casualties();
+        assert_eq!(casualties.len(), 1);
+        assert_eq!(casualties[0].id, ids[0]);
+        // The rested fighter is back and healed.
+        let rested = state.fighter(ids[1]).unwrap();
+        assert!(!rested.is_resting);
+        assert_eq!((rested.wounds, rested.battles), (Strength(0), 1));
+    }
+
+    #[test]
+    fn test_campaign_graph() {
+        let mut state = State::new(campaign_01(), agent_info());
+        assert_eq!(state.mode(), Mode::PreparingForBattle);
+        state.report_battle_results(&victory(&state)).unwrap();
+        assert_eq!(state.mode(), Mode::ChoosingNextNode);
+        assert_eq!(state.next_nodes(), &[NodeId(1), NodeId(6)]);
+        // The shop is passed on the way to the next battle.
+        state.choose_next_node(NodeId(6));
+        assert_eq!(state.mode(), Mode::PreparingForBattle);
+        assert_eq!(state.current_node().title, "Imp pack");
+        let hammerman = Action::Recruit {
+            agent_type: "hammerman".into(),
+        };
+        assert!(state.available_actions().contains(&hammerman));
+        state.report_battle_results(&victory(&state)).unwrap();
+        assert_eq!(state.current_node().title, "Bombers");
+        state.report_battle_results(&victory(&state)).unwrap();
+        state.choose_next_node(NodeId(7));
+        let is_reward = |action: &&Action| matches!(action, Action::ChooseReward { .. });
+        let rewards: Vec<_> = state.available_actions().iter().filter(is_reward).collect();
+        assert_eq!(rewards.len(), 2);
+        let action = rewards[0].clone();
+        let renown = state.renown();
+        state.execute_action(action);
+        assert_eq!(state.renown(), Renown(renown.0 + 15));
+        assert!(!state.available_actions().iter().any(|a| is_reward(&a)));
+        state.report_battle_results(&victory(&state)).unwrap();
+        state.report_battle_results(&victory(&state)).unwrap();
+        assert_eq!(state.mode(), Mode::PreparingForBattle);
+        assert!(state.current_node().is_terminal());
+        state.report_battle_results(&victory(&state)).unwrap();
+        assert_eq!(state.mode(), Mode::Won);
+        assert_eq!(state.battles_won(), 5);
+    }
+
+    #[test]
+    fn test_campaign_failed() {
+        let mut state = State::new(campaign_01(), agent_info());
+        let result = BattleResult {
+            winner_ids: vec![PlayerId(1)],
+            survivor_types: Vec::new(),
+            survivor_heroes: Vec::new(),
+        };
+        state.report_battle_results(&result).unwrap();
+        assert_eq!(state.mode(), Mode::Failed);
+    }
+
+    #[test]
+    fn test_plan_check() {
+        let recruits = || Event::Recruits {
+            agents: vec!["swordsman".into()],
+        };
+        let mut plan = Plan {
+            initial_agents: Vec::new(),
+            nodes: Vec::new(),
+        };
+        assert_eq!(plan.check(), Err(Error::NoNodes));
+        plan.nodes = vec![event_node(recruits(), vec![2])];
+        let node = NodeId(0);
+        let next = NodeId(2);
+        assert_eq!(plan.check(), Err(Error::UnknownNextNode { node, next }));
+        plan.nodes = vec![
+            event_node(recruits(), vec![1]),
+            event_node(recruits(), vec![0]),
+        ];
+        assert_eq!(plan.check(), Err(Error::EventLoop(NodeId(0))));
+        plan.nodes = vec![event_node(
+            Event::Shop {
+                agents: vec!["imp".into()],
+            },
+            vec![],
+        )];
+        assert_eq!(plan.check(), Err(Error::NotAHero("imp".into())));
+        // A terminal event node wins the campaign right away.
+        plan.nodes = vec![
+            event_node(recruits(), vec![1]),
+            event_node(
+                Event::Choice {
+                    rewards: Vec::new(),
+                },
+                vec![],
+            ),
+        ];
+        assert_eq!(plan.check(), Ok(()));
+        let state = State::new(plan, agent_info());
+        assert_eq!(state.mode(), Mode::Won);
+        assert_eq!(state.fighters().len(), 1);
+    }
+
+    #[test]
+    fn test_check() {
+        let prototypes = prototypes::shipped();
+        let tiles = tiles::shipped();
+        let state = State::new(campaign_01(), agent_info());
+        assert_eq!(state.check(&prototypes, &tiles), Ok(()));
+        let mut bad = state.clone();
+        bad.nodes.clear();
+        assert_eq!(bad.check(&prototypes, &tiles), Err(Error::NoNodes));
+        let mut bad = state.clone();
+        bad.current_node = NodeId(state.nodes.len());
+        let error = Error::UnknownCurrentNode(bad.current_node);
+        assert_eq!(bad.check(&prototypes, &tiles), Err(error));
+        let mut bad = state.clone();
+        let event = Event::Recruits { agents: Vec::new() };
+        bad.nodes[0] = event_node(event, vec![1]);
+        let error = Error::NotABattle(NodeId(0));
+        assert_eq!(bad.check(&prototypes, &tiles), Err(error));
+        let mut bad = state.clone();
+        bad.current_node = NodeId(bad.nodes.iter().position(|n| n.is_terminal()).unwrap());
+        bad.mode = Mode::ChoosingNextNode;
+        let error = Error::NoNextNode(bad.current_node);
+        assert_eq!(bad.check(&prototypes, &tiles), Err(error));
+        let mut bad = state.clone();
+        bad.fighters[0].typename = "imp".into();
+        let error = Error::NotAHero("imp".into());
+        assert_eq!(bad.check(&prototypes, &tiles), Err(error));
+    }
+
+    #[test]
+    fn test_check_scenarios() {
+        let prototypes = prototypes::shipped();
+        let mut tiles = tiles::shipped();
+        let mut state = State::new(campaign_01(), agent_info());
+        let scenario = match &mut state.nodes[0].event {
+            Event::Battle { scenario, .. } => scenario,
+            _ => panic!("The campaign starts with a battle"),
+        };
+        let pos = PosHex { q: 0, r: 0 };
+        scenario.tiles.insert(pos, TileType::Lava);
+        assert_eq!(state.check(&prototypes, &tiles), Ok(()));
+        tiles.0.remove(&TileType::Lava);
+        let error = Error::UnknownTileType {
+            node: NodeId(0),
+            tile: TileType::Lava,
+        };
+        assert_eq!(state.check(&prototypes, &tiles), Err(error));
+        let mut bad = state;
+        match &mut bad.nodes[0].event {
+            Event::Battle { scenario, .. } => scenario.map_radius = 1,
+            _ => unreachable!(),
+        }
+        let error = Error::BadScenario {
+            node: NodeId(0),
+            error: scenario::ValidationError {
+                path: "map_radius".into(),
+                error: scenario::Error::MapIsTooSmall,
+            },
+        };
+        assert_eq!(bad.check(&prototypes, &tiles), Err(error));
+    }
+
+    #[test]
+    fn test_set_agent_info() {
+        let prototypes = prototypes::shipped();
+        let mut state = State::new(campaign_01(), agent_info());
+        let id = state.fighters()[0].id;
+        let from = state.fighters()[0].typename.clone();
+        let to = ObjType("elite_spearman".into());
+        let upgrade = Action::Upgrade {
+            id,
+            from: from.clone(),
+            to: to.clone(),
+        };
+        state.actions.push(upgrade.clone());
+        let mut info = agent_info();
+        state.set_agent_info(info.clone());
+        assert!(state.available_actions().contains(&upgrade));
+        info.get_mut(&from).unwrap().upgrades.retain(|t| t != &to);
+        state.set_agent_info(info.clone());
+        assert!(!state.available_actions().contains(&upgrade));
+        assert_eq!(state.check(&prototypes, &tiles::shipped()), Ok(()));
+        info.remove(&from);
+        state.set_agent_info(info);
+        let error = Error::NoAgentInfo(from);
+        assert_eq!(state.check(&prototypes, &tiles::shipped()), Err(error));
+    }
+
+    #[test]
+    fn test_check_obj_types() {
+        let mut prototypes = prototypes::shipped();
+        let mut state = State::new(campaign_01(), agent_info());
+        assert_eq!(state.check_obj_types(&prototypes), Ok(()));
+        state.recruit("ghost".into());
+        let error = Error::UnknownObjType("ghost".into());
+        assert_eq!(state.check_obj_types(&prototypes), Err(error));
+        let state = State::new(campaign_01(), agent_info());
+        prototypes.0.remove(&"imp_summoner".into());
+        let error = Error::UnknownObjType("imp_summoner".into());
+        assert_eq!(state.check_obj_types(&prototypes), Err(error));
+    }
+}
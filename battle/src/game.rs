@@ -1,923 +1,587 @@
+#[cfg(not(feature = "event"))]
+use std::collections::HashSet;
+
+use risc0_zkvm::sha::{Digest, Impl, Sha256};
 use serde::{Deserialize, Serialize};
 
+use crate::battle::{
+    ability::definitions::{self, Definitions},
+    check,
+    command::Command,
+    heroes::{HeroId, HeroObject, Perk},
+    prototypes::{self, Prototypes},
+    scenario::{self, Scenario},
+    state::BattleResult,
+    tiles::{self, Tiles},
+    PlayerId, State,
+};
+#[cfg(not(feature = "event"))]
 use crate::{
     battle::{
-        command::Command,
-        component::{BelongsTo, Component, Meta, ObjType, Parts, Pos},
-        heroes::{imp, imp_bomber, imp_summoner, toxic_imp, Hero, HeroObject},
-        scenario::Scenario,
-        Id, PlayerId, State, TileType,
+        ai::Ais,
+        command,
+        component::{Component, ObjType},
+        execute::execute_strict,
+        heroes::Hero,
     },
-    map::{HexMap, PosHex},
+    utils::SimpleRng,
 };
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-pub enum Level {
-    Level0,
-    Level1,
-    Level2,
-    Level3,
-    Level4,
-    Level5,
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Input {
+    pub scenario: Scenario,
+
+    /// Passed with the input, so balance changes don't require a new guest.
+    pub prototypes: Prototypes,
+    pub ability_definitions: Definitions,
+    pub tiles: Tiles,
+
+    pub commands: Vec<Vec<Command>>,
+    pub heroes: Vec<HeroObject>,
+    pub seed: [u8; 32],
 }
 
-impl Level {
-    pub fn from_index(i: i32) -> Self {
-        match i {
-            0 => Level::Level0,
-            1 => Level::Level1,
-            2 => Level::Level2,
-            3 => Level::Level3,
-            4 => Level::Level4,
-            5 => Level::Level5,
-            _ => unimplemented!(),
-        }
+impl Input {
+    pub fn commands_digest(&self) -> Digest {
+        let bytes = serde_json::to_vec(&self.commands).expect("Can't serialize the commands");
+        *Impl::hash_bytes(&bytes)
     }
+}
 
-    pub fn state(&self) -> State {
-        match self {
-            Level::Level0 => state_level0(),
-            Level::Level1 => state_level1(),
-            Level::Level2 => state_level2(),
-            Level::Level3 => state_level3(),
-            Level::Level4 => state_level4(),
-            Level::Level5 => state_level5(),
-        }
-    }
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Error {
+    BadScenario(scenario::Error),
+    BadPrototypes(prototypes::Error),
+    BadAbilities(definitions::Error),
+    BadTiles(tiles::Error),
 
-    pub fn scenario(&self) -> Scenario {
-        let scenario = match self {
-            Level::Level0 => {
-                r###"
-        {"map_radius":3,"players_count":2,"rocky_tiles_count":0,"tiles":{},"randomized_objects":[{"owner":1,"typename":"imp","line":"Front","count":1}],"objects":[]}
-        "###
-            }
-            Level::Level1 => {
-                r###"
-        {"map_radius":3,"players_count":2,"rocky_tiles_count":0,"tiles":{},"randomized_objects":[{"owner":1,"typename":"imp","line":"Front","count":3}],"objects":[]}
-        "###
-            }
-            Level::Level2 => {
-                r###"
-        {"map_radius":3,"players_count":2,"rocky_tiles_count":0,"tiles":{},"randomized_objects":[{"owner":1,"typename":"imp","line":"Front","count":3},{"owner":1,"typename":"imp_bomber","line":"Back","count":1}],"objects":[]}
-        "###
-            }
-            Level::Level3 => {
-                r###"
-        {"map_radius":3,"players_count":2,"rocky_tiles_count":0,"tiles":{},"randomized_objects":[{"owner":1,"typename":"imp","line":"Front","count":3},{"owner":1,"typename":"imp_bomber","line":"Back","count":1},{"owner":1,"typename":"toxic_imp","line":"Middle","count":1}],"objects":[]}
-        "###
-            }
-            Level::Level4 => {
-                r###"
-        {"map_radius":3,"players_count":2,"rocky_tiles_count":0,"tiles":{},"randomized_objects":[{"owner":1,"typename":"imp","line":"Front","count":3},{"owner":1,"typename":"imp_bomber","line":"Back","count":1},{"owner":1,"typename":"toxic_imp","line":"Middle","count":2}],"objects":[]}
-        "###
-            }
+    /// `HeroObject::index` that doesn't name any hero.
+    UnknownHero(u8),
 
-            Level::Level5 => {
-                r###"
-        {"map_radius":3,"players_count":2,"rocky_tiles_count":0,"tiles":{},"randomized_objects":[{"owner":1,"typename":"imp","line":"Front","count":3},{"owner":1,"typename":"imp_bomber","line":"Back","count":1},{"owner":1,"typename":"toxic_imp","line":"Middle","count":2},{"owner":1,"typename":"imp_summoner","line":"Back","count":1}],"objects":[]}
-        "###
-            }
-        };
+    /// Two heroes with the same id, or a group of more than one with an id.
+    DuplicateHeroId(HeroId),
 
-        serde_json::from_str(scenario).unwrap()
-    }
+    DuplicatePerk(Perk),
 
-    pub fn fake_heros(&self) -> Vec<HeroObject> {
-        match self {
-            Level::Level0 => vec![HeroObject::new(Hero::Spearman, 1)],
-            Level::Level1 => vec![
-                HeroObject::new(Hero::Spearman, 1),
-                HeroObject::new(Hero::Swordsman, 1),
-            ],
-            Level::Level2 => vec![
-                HeroObject::new(Hero::HeavySpearman, 1),
-                HeroObject::new(Hero::Swordsman, 1),
-            ],
-            Level::Level3 => vec![
-                HeroObject::new(Hero::HeavySpearman, 1),
-                HeroObject::new(Hero::EliteSwordsman, 1),
-                HeroObject::new(Hero::Hammerman, 1),
-            ],
-            Level::Level4 => vec![
-                HeroObject::new(Hero::Alchemist, 1),
-                HeroObject::new(Hero::Swordsman, 1),
-                HeroObject::new(Hero::EliteSpearman, 1),
-            ],
-            Level::Level5 => vec![
-                HeroObject::new(Hero::HeavySpearman, 1),
-                HeroObject::new(Hero::EliteSwordsman, 1),
-                HeroObject::new(Hero::HeavyHammerman, 1),
-            ],
-        }
-    }
+    /// Wounds can't make a hero stronger.
+    NegativeWounds(HeroId),
+
+    BadCommand {
+        round: usize,
+        player_id: PlayerId,
+        error: check::Error,
+    },
+    RoundAfterBattleEnd {
+        round: usize,
+    },
 }
 
-pub fn state_level0() -> State {
-    let mut parts = Parts::new();
+/// Replays the transcript from scratch, validating every command of every player.
+///
+/// The transcript may stop early, but it must not go on after the battle has ended.
+#[cfg(not(feature = "event"))]
+pub fn replay(input: &Input) -> Result<State, Error> {
+    input.scenario.check_layout().map_err(Error::BadScenario)?;
+    check_heroes(input)?;
+    check_prototypes(input).map_err(Error::BadPrototypes)?;
+    check_abilities(input).map_err(Error::BadAbilities)?;
+    input.tiles.check().map_err(Error::BadTiles)?;
+    let mut rng = SimpleRng::from_seed(input.seed);
+    let prototypes = input.prototypes.clone();
+    let ability_definitions = input.ability_definitions.clone();
+    let mut state = State::new(
+        input.scenario.clone(),
+        prototypes,
+        ability_definitions,
+        input.tiles.clone(),
+        &mut rng,
+    );
+    state.create_heroes(&input.heroes, &mut rng);
+
+    let mut ais = Ais::new(state.players_count());
+
+    for (round, commands) in input.commands.iter().enumerate() {
+        if state.battle_result().is_some() {
+            return Err(Error::RoundAfterBattleEnd { round });
+        }
 
-    let id_imp = Id(0);
-    parts.next_obj_id = Id(1);
+        let run = |state: &mut State, command: &Command, rng: &mut SimpleRng| {
+            let player_id = state.player_id();
+            execute_strict(state, command, rng).map_err(|error| Error::BadCommand {
+                round,
+                player_id,
+                error,
+            })
+        };
 
-    {
-        for c in imp() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp, x);
-                }
-                _ => unreachable!(),
-            }
+        for command in commands {
+            run(&mut state, command, &mut rng)?;
         }
 
-        parts.pos.insert(id_imp, Pos(PosHex { q: 1, r: -2 }));
-        parts.meta.insert(
-            id_imp,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-        parts.belongs_to.insert(id_imp, BelongsTo(PlayerId(1)));
-    }
-
-    let map = HexMap {
-        tiles: vec![TileType::Plain; 49],
-        size: 7,
-        radius: 3,
-    };
+        if state.battle_result().is_some() {
+            continue;
+        }
 
-    State {
-        parts,
-        map,
-        players_count: 2,
-        player_id: PlayerId(0),
-        battle_result: None,
-        level: Level::Level0,
-
-        #[cfg(feature = "debug")]
-        turn_id: 0,
-        #[cfg(feature = "debug")]
-        commands: vec![],
-        #[cfg(feature = "debug")]
-        heroes: vec![],
-    }
-}
+        run(&mut state, &command::EndTurn.into(), &mut rng)?;
 
-pub fn state_level1() -> State {
-    let mut parts = Parts::new();
-
-    let id_imp_0 = Id(0);
-    let id_imp_1 = Id(1);
-    let id_imp_2 = Id(2);
-    parts.next_obj_id = Id(3);
-
-    {
-        for c in imp() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_0, x.clone());
-                    parts.strength.insert(id_imp_1, x.clone());
-                    parts.strength.insert(id_imp_2, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_0, x.clone());
-                    parts.agent.insert(id_imp_1, x.clone());
-                    parts.agent.insert(id_imp_2, x);
+        while state.battle_result().is_none() && state.player_id() != PlayerId(0) {
+            let ai = ais.get_mut(state.player_id());
+            ai.update_obj_ids(&state);
+            while let Some(command) = ai.command(&state) {
+                run(&mut state, &command, &mut rng)?;
+                if let Command::EndTurn(_) = command {
+                    break;
                 }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_0, x.clone());
-                    parts.blocker.insert(id_imp_1, x.clone());
-                    parts.blocker.insert(id_imp_2, x);
-                }
-                _ => unreachable!(),
             }
         }
-
-        parts.pos.insert(id_imp_0, Pos(PosHex { q: 1, r: -2 }));
-        parts.pos.insert(id_imp_1, Pos(PosHex { q: 0, r: -1 }));
-        parts.pos.insert(id_imp_2, Pos(PosHex { q: 2, r: -3 }));
-
-        parts.meta.insert(
-            id_imp_0,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-        parts.meta.insert(
-            id_imp_1,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-        parts.meta.insert(
-            id_imp_2,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-
-        parts.belongs_to.insert(id_imp_0, BelongsTo(PlayerId(1)));
-        parts.belongs_to.insert(id_imp_1, BelongsTo(PlayerId(1)));
-        parts.belongs_to.insert(id_imp_2, BelongsTo(PlayerId(1)));
     }
 
-    let map = HexMap {
-        tiles: vec![TileType::Plain; 49],
-        size: 7,
-        radius: 3,
-    };
+    Ok(state)
+}
 
-    State {
-        parts,
-        map,
-        players_count: 2,
-        player_id: PlayerId(0),
-        battle_result: None,
-        level: Level::Level1,
-
-        #[cfg(feature = "debug")]
-        turn_id: 0,
-        #[cfg(feature = "debug")]
-        commands: vec![],
-        #[cfg(feature = "debug")]
-        heroes: vec![],
+/// Checks the prototypes and that every object of the input has one.
+#[cfg(not(feature = "event"))]
+fn check_prototypes(input: &Input) -> Result<(), prototypes::Error> {
+    let prototypes = &input.prototypes;
+    prototypes.check()?;
+    let scenario = &input.scenario;
+    let randomized = scenario.randomized_objects.iter().map(|g| &g.typename);
+    for name in randomized.chain(scenario.objects.iter().map(|o| &o.typename)) {
+        prototypes.check_known(name)?;
+    }
+    for hero in &input.heroes {
+        let hero = Hero::from_index(hero.index).expect("The heroes are checked first");
+        prototypes.check_known(&ObjType(hero.name()))?;
     }
+    Ok(())
 }
 
-pub fn state_level2() -> State {
-    let mut parts = Parts::new();
-
-    let id_imp_0 = Id(0);
-    let id_imp_1 = Id(1);
-    let id_imp_2 = Id(2);
-    let id_imp_3 = Id(3);
-    parts.next_obj_id = Id(4);
-
-    {
-        for c in imp() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_0, x.clone());
-                    parts.strength.insert(id_imp_1, x.clone());
-                    parts.strength.insert(id_imp_2, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_0, x.clone());
-                    parts.agent.insert(id_imp_1, x.clone());
-                    parts.agent.insert(id_imp_2, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_0, x.clone());
-                    parts.blocker.insert(id_imp_1, x.clone());
-                    parts.blocker.insert(id_imp_2, x);
-                }
-                _ => unreachable!(),
+/// Checks that every hero of the input is known and that the campaign heroes are unique.
+#[cfg(not(feature = "event"))]
+fn check_heroes(input: &Input) -> Result<(), Error> {
+    let mut ids = HashSet::new();
+    for hero in &input.heroes {
+        if Hero::from_index(hero.index).is_none() {
+            return Err(Error::UnknownHero(hero.index));
+        }
+        if let Some(id) = hero.id {
+            if hero.count != 1 || !ids.insert(id) {
+                return Err(Error::DuplicateHeroId(id));
+            }
+            if hero.wounds.0 < 0 {
+                return Err(Error::NegativeWounds(id));
+            }
+        }
+        for (i, perk) in hero.perks.iter().enumerate() {
+            if hero.perks[..i].contains(perk) {
+                return Err(Error::DuplicatePerk(*perk));
             }
         }
-        parts.pos.insert(id_imp_0, Pos(PosHex { q: 1, r: -2 }));
-        parts.pos.insert(id_imp_1, Pos(PosHex { q: 0, r: -1 }));
-        parts.pos.insert(id_imp_2, Pos(PosHex { q: 2, r: -3 }));
-
-        parts.meta.insert(
-            id_imp_0,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-        parts.meta.insert(
-            id_imp_1,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-        parts.meta.insert(
-            id_imp_2,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-
-        parts.belongs_to.insert(id_imp_0, BelongsTo(PlayerId(1)));
-        parts.belongs_to.insert(id_imp_1, BelongsTo(PlayerId(1)));
-        parts.belongs_to.insert(id_imp_2, BelongsTo(PlayerId(1)));
     }
+    Ok(())
+}
 
-    {
-        for c in imp_bomber() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_3, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_3, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_3, x);
-                }
-                Component::Abilities(x) => {
-                    parts.abilities.insert(id_imp_3, x);
+/// Checks the ability definitions and that every ability of the prototypes has one.
+#[cfg(not(feature = "event"))]
+fn check_abilities(input: &Input) -> Result<(), definitions::Error> {
+    let definitions = &input.ability_definitions;
+    definitions.check()?;
+    for components in input.prototypes.0.values() {
+        for component in components {
+            if let Component::Abilities(abilities) = component {
+                for r_ability in &abilities.0 {
+                    definitions.check_known(&r_ability.ability)?;
                 }
-                _ => unreachable!(),
             }
         }
-
-        parts.pos.insert(id_imp_3, Pos(PosHex { q: 3, r: 0 }));
-        parts.meta.insert(
-            id_imp_3,
-            Meta {
-                name: ObjType("imp_bomber".to_string()),
-            },
-        );
-        parts.belongs_to.insert(id_imp_3, BelongsTo(PlayerId(1)));
     }
+    Ok(())
+}
 
-    let map = HexMap {
-        tiles: vec![TileType::Plain; 49],
-        size: 7,
-        radius: 3,
-    };
+/// The record committed by the guest.
+///
+/// Binds the outcome to the exact scenario, roster and transcript that produced it,
+/// a rejected transcript included.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Journal {
+    pub input: InputDigests,
+    pub outcome: Result<Outcome, Error>,
+}
 
-    State {
-        parts,
-        map,
-        players_count: 2,
-        player_id: PlayerId(0),
-        battle_result: None,
-        level: Level::Level2,
-
-        #[cfg(feature = "debug")]
-        turn_id: 0,
-        #[cfg(feature = "debug")]
-        commands: vec![],
-        #[cfg(feature = "debug")]
-        heroes: vec![],
+impl Journal {
+    pub fn new(input: &Input, replayed: Result<State, Error>) -> Self {
+        Self {
+            input: InputDigests::new(input),
+            outcome: replayed.map(|state| Outcome::new(&state)),
+        }
     }
 }
 
-pub fn state_level3() -> State {
-    let mut parts = Parts::new();
-
-    let id_imp_0 = Id(0);
-    let id_imp_1 = Id(1);
-    let id_imp_2 = Id(2);
-    let id_imp_3 = Id(3);
-    let id_imp_4 = Id(4);
-
-    parts.next_obj_id = Id(5);
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputDigests {
+    pub scenario_digest: Digest,
+    pub prototypes_digest: Digest,
+    pub ability_definitions_digest: Digest,
+    pub tiles_digest: Digest,
+    pub heroes: Vec<HeroObject>,
+    pub seed: [u8; 32],
+    pub commands_digest: Digest,
+}
 
-    {
-        for c in imp() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_0, x.clone());
-                    parts.strength.insert(id_imp_1, x.clone());
-                    parts.strength.insert(id_imp_2, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_0, x.clone());
-                    parts.agent.insert(id_imp_1, x.clone());
-                    parts.agent.insert(id_imp_2, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_0, x.clone());
-                    parts.blocker.insert(id_imp_1, x.clone());
-                    parts.blocker.insert(id_imp_2, x);
-                }
-                _ => unreachable!(),
-            }
+impl InputDigests {
+    pub fn new(input: &Input) -> Self {
+        Self {
+            scenario_digest: input.scenario.digest(),
+            prototypes_digest: input.prototypes.digest(),
+            ability_definitions_digest: input.ability_definitions.digest(),
+            tiles_digest: input.tiles.digest(),
+            heroes: input.heroes.clone(),
+            seed: input.seed,
+            commands_digest: input.commands_digest(),
         }
-        parts.pos.insert(id_imp_0, Pos(PosHex { q: 1, r: -2 }));
-        parts.pos.insert(id_imp_1, Pos(PosHex { q: 0, r: -1 }));
-        parts.pos.insert(id_imp_2, Pos(PosHex { q: 2, r: -3 }));
-
-        parts.meta.insert(
-            id_imp_0,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-        parts.meta.insert(
-            id_imp_1,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-        parts.meta.insert(
-            id_imp_2,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-
-        parts.belongs_to.insert(id_imp_0, BelongsTo(PlayerId(1)));
-        parts.belongs_to.insert(id_imp_1, BelongsTo(PlayerId(1)));
-        parts.belongs_to.insert(id_imp_2, BelongsTo(PlayerId(1)));
     }
+}
 
-    {
-        for c in imp_bomber() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_3, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_3, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_3, x);
-                }
-                Component::Abilities(x) => {
-                    parts.abilities.insert(id_imp_3, x);
-                }
-                _ => unreachable!(),
-            }
-        }
-
-        parts.pos.insert(id_imp_3, Pos(PosHex { q: 3, r: 0 }));
-        parts.meta.insert(
-            id_imp_3,
-            Meta {
-                name: ObjType("imp_bomber".to_string()),
-            },
-        );
-        parts.belongs_to.insert(id_imp_3, BelongsTo(PlayerId(1)));
-    }
+/// The state of an accepted transcript.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Outcome {
+    pub state_digest: Digest,
+    pub battle_result: Option<BattleResult>,
+}
 
-    {
-        for c in toxic_imp() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_4, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_4, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_4, x);
-                }
-                Component::PassiveAbilities(x) => {
-                    parts.passive_abilities.insert(id_imp_4, x);
-                }
-                _ => unreachable!(),
-            }
+impl Outcome {
+    pub fn new(state: &State) -> Self {
+        Self {
+            state_digest: state.digest(),
+            battle_result: state.battle_result().clone(),
         }
-
-        parts.pos.insert(id_imp_4, Pos(PosHex { q: 2, r: 1 }));
-        parts.meta.insert(
-            id_imp_4,
-            Meta {
-                name: ObjType("toxic_imp".to_string()),
-            },
-        );
-        parts.belongs_to.insert(id_imp_4, BelongsTo(PlayerId(1)));
     }
+}
 
-    let map = HexMap {
-        tiles: vec![TileType::Plain; 49],
-        size: 7,
-        radius: 3,
+#[cfg(not(feature = "event"))]
+#[cfg(test)]
+mod test {
+    use crate::{
+        battle::{
+            ability::{definitions, Ability},
+            check, command,
+            component::{self, Abilities, Facing, ObjType},
+            execute,
+            heroes::{Hero, HeroId, HeroObject, Perk},
+            prototypes,
+            scenario::{self, Line, Object, ObjectsGroup, Scenario, Team},
+            state, tiles, Id, PlayerId, PosHex, Rounds, Strength, TileType,
+        },
+        campaign,
+        game::{replay, Error, Input},
+        map::Dir,
+        utils::SimpleRng,
     };
 
-    State {
-        parts,
-        map,
-        players_count: 2,
-        player_id: PlayerId(0),
-        battle_result: None,
-        level: Level::Level3,
-
-        #[cfg(feature = "debug")]
-        turn_id: 0,
-        #[cfg(feature = "debug")]
-        commands: vec![],
-        #[cfg(feature = "debug")]
-        heroes: vec![],
+    fn scenario(i: usize) -> Scenario {
+        campaign::campaign_01().nodes()[i]
+            .scenario()
+            .unwrap()
+            .clone()
     }
-}
 
-pub fn state_level4() -> State {
-    let mut parts = Parts::new();
-
-    let id_imp_0 = Id(0);
-    let id_imp_1 = Id(1);
-    let id_imp_2 = Id(2);
-    let id_imp_3 = Id(3);
-    let id_imp_4 = Id(4);
-    let id_imp_5 = Id(5);
-
-    parts.next_obj_id = Id(6);
-
-    {
-        for c in imp() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_0, x.clone());
-                    parts.strength.insert(id_imp_1, x.clone());
-                    parts.strength.insert(id_imp_2, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_0, x.clone());
-                    parts.agent.insert(id_imp_1, x.clone());
-                    parts.agent.insert(id_imp_2, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_0, x.clone());
-                    parts.blocker.insert(id_imp_1, x.clone());
-                    parts.blocker.insert(id_imp_2, x);
-                }
-                _ => unreachable!(),
-            }
+    fn input(commands: Vec<Vec<command::Command>>) -> Input {
+        Input {
+            scenario: scenario(0),
+            prototypes: prototypes::shipped(),
+            ability_definitions: definitions::shipped(),
+            tiles: tiles::shipped(),
+            commands,
+            heroes: vec![HeroObject::new(Hero::Swordsman, 2)],
+            seed: [0; 32],
         }
-        parts.pos.insert(id_imp_0, Pos(PosHex { q: 1, r: -2 }));
-        parts.pos.insert(id_imp_1, Pos(PosHex { q: 0, r: -1 }));
-        parts.pos.insert(id_imp_2, Pos(PosHex { q: 2, r: -3 }));
-
-        parts.meta.insert(
-            id_imp_0,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-        parts.meta.insert(
-            id_imp_1,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-        parts.meta.insert(
-            id_imp_2,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-
-        parts.belongs_to.insert(id_imp_0, BelongsTo(PlayerId(1)));
-        parts.belongs_to.insert(id_imp_1, BelongsTo(PlayerId(1)));
-        parts.belongs_to.insert(id_imp_2, BelongsTo(PlayerId(1)));
     }
 
-    {
-        for c in imp_bomber() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_3, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_3, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_3, x);
-                }
-                Component::Abilities(x) => {
-                    parts.abilities.insert(id_imp_3, x);
-                }
-                _ => unreachable!(),
-            }
+    #[test]
+    fn test_campaign_scenarios() {
+        let plan = campaign::campaign_01();
+        let scenarios = plan.nodes().iter().filter_map(|node| node.scenario());
+        for (i, scenario) in scenarios.enumerate() {
+            assert_eq!(scenario.validate_layout(), Ok(()));
+            let input = Input {
+                scenario: scenario.clone(),
+                ..input(vec![vec![]])
+            };
+            assert!(replay(&input).is_ok(), "Can't replay scenario #{}", i);
         }
-
-        parts.pos.insert(id_imp_3, Pos(PosHex { q: 3, r: 0 }));
-        parts.meta.insert(
-            id_imp_3,
-            Meta {
-                name: ObjType("imp_bomber".to_string()),
-            },
-        );
-        parts.belongs_to.insert(id_imp_3, BelongsTo(PlayerId(1)));
     }
 
-    {
-        for c in toxic_imp() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_4, x.clone());
-                    parts.strength.insert(id_imp_5, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_4, x.clone());
-                    parts.agent.insert(id_imp_5, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_4, x.clone());
-                    parts.blocker.insert(id_imp_5, x);
-                }
-                Component::PassiveAbilities(x) => {
-                    parts.passive_abilities.insert(id_imp_4, x.clone());
-                    parts.passive_abilities.insert(id_imp_5, x);
-                }
-                _ => unreachable!(),
-            }
-        }
-
-        parts.pos.insert(id_imp_4, Pos(PosHex { q: 2, r: 1 }));
-        parts.pos.insert(id_imp_5, Pos(PosHex { q: 2, r: -1 }));
+    #[test]
+    fn test_exact_tiles_and_objects() {
+        let rocks = PosHex { q: 0, r: 1 };
+        let boulder = PosHex { q: 1, r: 0 };
+        let imp = PosHex { q: 0, r: -2 };
+        let mut scenario = scenario(1);
+        scenario.rocky_tiles_count = 5;
+        scenario.tiles.insert(rocks, TileType::Rocks);
+        let object = |owner, typename: &str, pos| Object {
+            owner,
+            typename: ObjType(typename.into()),
+            pos,
+        };
+        scenario.objects = vec![
+            object(None, "boulder", boulder),
+            object(Some(PlayerId(1)), "imp", imp),
+        ];
+        let state = state::state_with_scenario(scenario, &mut SimpleRng::seed_from_u32(0));
+        assert_eq!(state.map().tile(rocks), TileType::Rocks);
+        let name_at = |pos| {
+            let id = state::get_id_by_pos(&state, pos);
+            state.meta(&id).name.0.clone()
+        };
+        assert_eq!(name_at(boulder), "boulder");
+        assert_eq!(name_at(imp), "imp");
+        let imp_id = state::agent_id_at_opt(&state, imp).unwrap();
+        assert_eq!(state.belongs_to(&imp_id).0, PlayerId(1));
+        // The exact imp plus three random ones.
+        assert_eq!(state::players_agent_ids(&state, PlayerId(1)).len(), 4);
+    }
 
-        parts.meta.insert(
-            id_imp_4,
-            Meta {
-                name: ObjType("toxic_imp".to_string()),
-            },
-        );
-        parts.meta.insert(
-            id_imp_5,
-            Meta {
-                name: ObjType("toxic_imp".to_string()),
-            },
+    #[test]
+    fn test_replay_rejects_bad_scenario() {
+        let pos = PosHex { q: 0, r: 0 };
+        let mut input = input(vec![]);
+        input.scenario.objects = vec![
+            Object {
+                owner: None,
+                typename: ObjType("boulder".into()),
+                pos,
+            };
+            2
+        ];
+        assert_eq!(
+            replay(&input).err(),
+            Some(Error::BadScenario(scenario::Error::ObjectsOverlap(pos)))
         );
-
-        parts.belongs_to.insert(id_imp_4, BelongsTo(PlayerId(1)));
-        parts.belongs_to.insert(id_imp_5, BelongsTo(PlayerId(1)));
     }
 
-    let map = HexMap {
-        tiles: vec![TileType::Plain; 49],
-        size: 7,
-        radius: 3,
-    };
-
-    State {
-        parts,
-        map,
-        players_count: 2,
-        player_id: PlayerId(0),
-        battle_result: None,
-        level: Level::Level4,
-
-        #[cfg(feature = "debug")]
-        turn_id: 0,
-        #[cfg(feature = "debug")]
-        commands: vec![],
-        #[cfg(feature = "debug")]
-        heroes: vec![],
+    #[test]
+    fn test_state_digest() {
+        let state = |i| state::state_with_scenario(scenario(i), &mut SimpleRng::seed_from_u32(0));
+        assert_eq!(state(3).digest(), state(3).digest());
+        assert_ne!(state(3).digest(), state(4).digest());
+        let id = *state(3).parts().agent.keys().min().unwrap();
+        let mut armored = state(3);
+        armored.parts.armor.entry(id).or_default().slash.0 += 1;
+        assert_ne!(armored.digest(), state(3).digest());
+        let facing = |dir| {
+            let mut state = state(3);
+            state.parts.facing.insert(id, Facing(dir));
+            state.digest()
+        };
+        assert_ne!(facing(Dir::East), facing(Dir::West));
+        let kills = |kills| {
+            let mut state = state(3);
+            let hero = component::Hero {
+                id: HeroId(1),
+                kills,
+            };
+            state.parts.hero.insert(id, hero);
+            state.digest()
+        };
+        assert_ne!(kills(0), kills(1));
     }
-}
-
-pub fn state_level5() -> State {
-    let mut parts = Parts::new();
-
-    let id_imp_0 = Id(0);
-    let id_imp_1 = Id(1);
-    let id_imp_2 = Id(2);
-    let id_imp_3 = Id(3);
-    let id_imp_4 = Id(4);
-    let id_imp_5 = Id(5);
-    let id_imp_6 = Id(6);
-
-    parts.next_obj_id = Id(7);
-
-    {
-        for c in imp() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_0, x.clone());
-                    parts.strength.insert(id_imp_1, x.clone());
-                    parts.strength.insert(id_imp_2, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_0, x.clone());
-                    parts.agent.insert(id_imp_1, x.clone());
-                    parts.agent.insert(id_imp_2, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_0, x.clone());
-                    parts.blocker.insert(id_imp_1, x.clone());
-                    parts.blocker.insert(id_imp_2, x);
-                }
-                _ => unreachable!(),
-            }
-        }
-        parts.pos.insert(id_imp_0, Pos(PosHex { q: 1, r: -2 }));
-        parts.pos.insert(id_imp_1, Pos(PosHex { q: 0, r: -1 }));
-        parts.pos.insert(id_imp_2, Pos(PosHex { q: 2, r: -3 }));
-
-        parts.meta.insert(
-            id_imp_0,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-        parts.meta.insert(
-            id_imp_1,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
-        parts.meta.insert(
-            id_imp_2,
-            Meta {
-                name: ObjType("imp".to_string()),
-            },
-        );
 
-        parts.belongs_to.insert(id_imp_0, BelongsTo(PlayerId(1)));
-        parts.belongs_to.insert(id_imp_1, BelongsTo(PlayerId(1)));
-        parts.belongs_to.insert(id_imp_2, BelongsTo(PlayerId(1)));
+    #[test]
+    fn test_replay_end_turns() {
+        assert!(replay(&input(vec![vec![]; 5])).is_ok());
     }
 
-    {
-        for c in imp_bomber() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_3, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_3, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_3, x);
-                }
-                Component::Abilities(x) => {
-                    parts.abilities.insert(id_imp_3, x);
-                }
-                _ => unreachable!(),
-            }
-        }
+    #[test]
+    fn test_replay_rejects_rounds_after_battle_end() {
+        let round = match replay(&input(vec![vec![]; 12])) {
+            Err(Error::RoundAfterBattleEnd { round }) => round,
+            result => panic!("Unexpected result: {:?}", result.map(|_| ())),
+        };
+        let state = replay(&input(vec![vec![]; round])).unwrap();
+        assert!(state.battle_result().is_some());
+    }
 
-        parts.pos.insert(id_imp_3, Pos(PosHex { q: 3, r: 0 }));
-        parts.meta.insert(
-            id_imp_3,
-            Meta {
-                name: ObjType("imp_bomber".to_string()),
-            },
-        );
-        parts.belongs_to.insert(id_imp_3, BelongsTo(PlayerId(1)));
+    #[test]
+    fn test_out_of_rounds() {
+        let mut rng = SimpleRng::seed_from_u32(0);
+        let mut state = state::state_with_scenario(scenario(1), &mut rng);
+        state.create_heroes(&[HeroObject::new(Hero::Swordsman, 1)], &mut rng);
+        state.round_limit.max_rounds = Rounds(1);
+        let end_turn = command::EndTurn.into();
+        execute(&mut state, &end_turn, &mut rng).unwrap();
+        assert!(state.battle_result().is_none());
+        execute(&mut state, &end_turn, &mut rng).unwrap();
+        assert_eq!(state.round(), Rounds(1));
+        assert!(state
+            .battle_result()
+            .as_ref()
+            .unwrap()
+            .is_won_by(PlayerId(1)));
     }
 
-    {
-        for c in toxic_imp() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_4, x.clone());
-                    parts.strength.insert(id_imp_5, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_4, x.clone());
-                    parts.agent.insert(id_imp_5, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_4, x.clone());
-                    parts.blocker.insert(id_imp_5, x);
-                }
-                Component::PassiveAbilities(x) => {
-                    parts.passive_abilities.insert(id_imp_4, x.clone());
-                    parts.passive_abilities.insert(id_imp_5, x);
-                }
-                _ => unreachable!(),
-            }
+    #[test]
+    fn test_replay_allies() {
+        let mut input = input(vec![vec![]]);
+        input.scenario.players_count = 3;
+        input.scenario.teams = vec![Team::Allied(0), Team::Alone, Team::Allied(0)];
+        input.scenario.objects.push(Object {
+            owner: Some(PlayerId(2)),
+            typename: ObjType("imp".into()),
+            pos: PosHex { q: 0, r: 0 },
+        });
+        assert_eq!(input.scenario.validate_layout(), Ok(()));
+        let state = replay(&input).unwrap();
+        assert_eq!(state.player_id(), PlayerId(0));
+        let enemies = state::enemy_agent_ids(&state, PlayerId(2));
+        assert!(!enemies.is_empty());
+        for id in enemies {
+            assert_eq!(state.belongs_to(&id).0, PlayerId(1));
         }
-
-        parts.pos.insert(id_imp_4, Pos(PosHex { q: 2, r: 1 }));
-        parts.pos.insert(id_imp_5, Pos(PosHex { q: 2, r: -1 }));
-
-        parts.meta.insert(
-            id_imp_4,
-            Meta {
-                name: ObjType("toxic_imp".to_string()),
-            },
-        );
-        parts.meta.insert(
-            id_imp_5,
-            Meta {
-                name: ObjType("toxic_imp".to_string()),
-            },
-        );
-
-        parts.belongs_to.insert(id_imp_4, BelongsTo(PlayerId(1)));
-        parts.belongs_to.insert(id_imp_5, BelongsTo(PlayerId(1)));
     }
 
-    {
-        for c in imp_summoner() {
-            match c {
-                Component::Strength(x) => {
-                    parts.strength.insert(id_imp_6, x);
-                }
-                Component::Agent(x) => {
-                    parts.agent.insert(id_imp_6, x);
-                }
-                Component::Blocker(x) => {
-                    parts.blocker.insert(id_imp_6, x);
-                }
-                Component::Abilities(x) => {
-                    parts.abilities.insert(id_imp_6, x);
-                }
-                Component::PassiveAbilities(x) => {
-                    parts.passive_abilities.insert(id_imp_6, x);
-                }
-                Component::Summoner(x) => {
-                    parts.summoner.insert(id_imp_6, x);
-                }
-                _ => unreachable!(),
-            }
+    #[test]
+    fn test_replay_four_sectors() {
+        let mut input = input(vec![vec![]]);
+        input.scenario.map_radius = 5;
+        input.scenario.players_count = 4;
+        for owner in 2..4 {
+            input.scenario.randomized_objects.push(ObjectsGroup {
+                owner: Some(PlayerId(owner)),
+                typename: ObjType("imp".into()),
+                line: Some(Line::Back),
+                count: 1,
+            });
+        }
+        assert_eq!(input.scenario.validate_layout(), Ok(()));
+        let state = replay(&input).unwrap();
+        for owner in 2..4 {
+            assert!(!state::players_agent_ids(&state, PlayerId(owner)).is_empty());
         }
+    }
 
-        parts.pos.insert(id_imp_6, Pos(PosHex { q: 3, r: -1 }));
-        parts.meta.insert(
-            id_imp_6,
-            Meta {
-                name: ObjType("imp_summoner".to_string()),
-            },
+    #[test]
+    fn test_replay_rejects_create() {
+        let create = command::Create {
+            prototype: ObjType("swordsman".into()),
+            pos: PosHex { q: 0, r: 0 },
+            owner: Some(PlayerId(0)),
+        };
+        assert_eq!(
+            replay(&input(vec![vec![], vec![create.into()]])).err(),
+            Some(Error::BadCommand {
+                round: 1,
+                player_id: PlayerId(0),
+                error: check::Error::CanNotCreateObjects,
+            })
         );
-        parts.belongs_to.insert(id_imp_6, BelongsTo(PlayerId(1)));
     }
 
-    let map = HexMap {
-        tiles: vec![TileType::Plain; 49],
-        size: 7,
-        radius: 3,
-    };
-
-    State {
-        parts,
-        map,
-        players_count: 2,
-        player_id: PlayerId(0),
-        battle_result: None,
-        level: Level::Level5,
-
-        #[cfg(feature = "debug")]
-        turn_id: 0,
-        #[cfg(feature = "debug")]
-        commands: vec![],
-        #[cfg(feature = "debug")]
-        heroes: vec![],
+    #[test]
+    fn test_replay_unknown_prototype() {
+        let mut input = input(vec![vec![]]);
+        let imp = ObjType("imp".into());
+        input.prototypes.0.remove(&imp);
+        let error = prototypes::Error::UnknownPrototype(imp);
+        assert_eq!(replay(&input).err(), Some(Error::BadPrototypes(error)));
     }
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Input {
-    pub level: Level,
-    pub commands: Vec<Vec<Command>>,
-    pub heroes: Vec<HeroObject>,
-}
+    #[test]
+    fn test_replay_unknown_hero() {
+        let mut input = input(vec![vec![]]);
+        input.heroes.push(HeroObject::new_index(200, 1));
+        assert_eq!(replay(&input).err(), Some(Error::UnknownHero(200)));
+    }
 
-#[cfg(not(feature = "event"))]
-#[cfg(test)]
-mod test {
-    use crate::{
-        battle::State,
-        game::{
-            state_level0, state_level1, state_level2, state_level3, state_level4, state_level5,
-            Level,
-        },
-        utils::SimpleRng,
-    };
+    #[test]
+    fn test_replay_duplicate_hero_id() {
+        let mut input = input(vec![vec![]]);
+        let hero = HeroObject::veteran(Hero::Swordsman, HeroId(1), Vec::new());
+        input.heroes = vec![hero.clone(), hero];
+        let error = Error::DuplicateHeroId(HeroId(1));
+        assert_eq!(replay(&input).err(), Some(error.clone()));
+        input.heroes = vec![HeroObject {
+            count: 2,
+            ..input.heroes[0].clone()
+        }];
+        assert_eq!(replay(&input).err(), Some(error));
+    }
 
     #[test]
-    fn test_level_0() {
-        let level = Level::Level0;
-        let mut rng = SimpleRng::seed_from_u32(0);
-        let state = State::new(level.scenario(), level, &mut rng);
-        println!("{:?}", state.parts().pos);
-        assert_eq!(state_level0(), state);
+    fn test_replay_duplicate_perk() {
+        let mut input = input(vec![vec![]]);
+        let perks = vec![Perk::Tough, Perk::Swift, Perk::Tough];
+        input.heroes = vec![HeroObject::veteran(Hero::Swordsman, HeroId(1), perks)];
+        let error = Error::DuplicatePerk(Perk::Tough);
+        assert_eq!(replay(&input).err(), Some(error));
     }
 
     #[test]
-    fn test_level_1() {
-        let level = Level::Level1;
-        let mut rng = SimpleRng::seed_from_u32(0);
-        let state = State::new(level.scenario(), level, &mut rng);
-        println!("{:?}", state.parts().pos);
-        assert_eq!(state_level1(), state);
+    fn test_replay_negative_wounds() {
+        let mut input = input(vec![vec![]]);
+        input.heroes = vec![HeroObject {
+            wounds: Strength(-1),
+            ..HeroObject::veteran(Hero::Swordsman, HeroId(1), Vec::new())
+        }];
+        let error = Error::NegativeWounds(HeroId(1));
+        assert_eq!(replay(&input).err(), Some(error));
     }
 
     #[test]
-    fn test_level_2() {
-        let level = Level::Level2;
-        let mut rng = SimpleRng::seed_from_u32(0);
-        let state = State::new(level.scenario(), level, &mut rng);
-        println!("{:?}", state.parts().pos);
-        assert_eq!(state_level2(), state);
+    fn test_replay_unknown_hero_prototype() {
+        let mut input = input(vec![vec![]]);
+        let swordsman = ObjType("swordsman".into());
+        input.prototypes.0.remove(&swordsman);
+        let error = prototypes::Error::UnknownPrototype(swordsman);
+        assert_eq!(replay(&input).err(), Some(Error::BadPrototypes(error)));
     }
 
     #[test]
-    fn test_level_3() {
-        let level = Level::Level3;
-        let mut rng = SimpleRng::seed_from_u32(0);
-        let state = State::new(level.scenario(), level, &mut rng);
-        println!("{:?}", state.parts().pos);
-        assert_eq!(state_level3(), state);
+    fn test_replay_missing_tile_type() {
+        let mut input = input(vec![vec![]]);
+        input.tiles.0.remove(&TileType::Lava);
+        let error = tiles::Error::MissingTileType(TileType::Lava);
+        assert_eq!(replay(&input).err(), Some(Error::BadTiles(error)));
     }
 
     #[test]
-    fn test_level_4() {
-        let level = Level::Level4;
-        let mut rng = SimpleRng::seed_from_u32(0);
-        let state = State::new(level.scenario(), level, &mut rng);
-        println!("{:?}", state.parts().pos);
-        assert_eq!(state_level4(), state);
+    fn test_replay_unknown_ability() {
+        let mut input = input(vec![vec![]]);
+        let ability = Ability::Custom("stun_shot".into());
+        let abilities = Abilities(vec![ability.clone().into()]);
+        let swordsman = input.prototypes.0.get_mut(&"swordsman".into()).unwrap();
+        swordsman.push(abilities.into());
+        let error = definitions::Error::UnknownAbility(ability);
+        assert_eq!(replay(&input).err(), Some(Error::BadAbilities(error)));
     }
 
     #[test]
-    fn test_level_5() {
-        let level = Level::Level5;
-        let mut rng = SimpleRng::seed_from_u32(0);
-        let state = State::new(level.scenario(), level, &mut rng);
-        println!("{:?}", state.parts().pos);
-        assert_eq!(state_level5(), state);
+    fn test_replay_rejects_bogus_actor_ids() {
+        let state = replay(&input(vec![vec![]])).unwrap();
+        let enemy_id = state::enemy_agent_ids(&state, PlayerId(0))[0];
+        let bogus_id = Id(9999);
+        let commands: Vec<command::Command> = vec![
+            command::Attack {
+                attacker_id: bogus_id,
+                target_id: enemy_id,
+            }
+            .into(),
+            command::UseAbility {
+                id: bogus_id,
+                pos: PosHex { q: 0, r: 0 },
+                ability: Ability::Jump,
+            }
+            .into(),
+        ];
+        for command in commands {
+            assert_eq!(
+                replay(&input(vec![vec![command]])).err(),
+                Some(Error::BadCommand {
+                    round: 0,
+                    player_id: PlayerId(0),
+                    error: check::Error::BadActorId,
+                })
+            );
+        }
     }
 }
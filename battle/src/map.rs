@@ -78,6 +78,59 @@ pub fn distance_hex(a: PosHex, b: PosHex) -> i32 {
     distance_cube(hex_to_cube(a), hex_to_cube(b))
 }
 
+/// All hexes on the straight line between two positions, including both ends.
+/// <https://www.redblobgames.com/grids/hexagons/#line-drawing>
+///
+/// Only integers are used, so the result is the same on every platform.
+pub fn line(from: PosHex, to: PosHex) -> Vec<PosHex> {
+    let n = distance_hex(from, to);
+    if n == 0 {
+        return vec![from];
+    }
+    // The coordinates are kept multiplied by `n * LINE_SCALE`.
+    // The ends are nudged by less than any step's fraction,
+    // so lines going along hex edges always pick the same side.
+    let denominator = n * LINE_SCALE;
+    let a = hex_to_cube(from);
+    let b = hex_to_cube(to);
+    (0..=n)
+        .map(|i| {
+            let lerp = |a: i32, b: i32, nudge: i32| (a * (n - i) + b * i) * LINE_SCALE + nudge;
+            let cube = PosCube {
+                x: lerp(a.x, b.x, 1),
+                y: lerp(a.y, b.y, 1),
+                z: lerp(a.z, b.z, -2),
+            };
+            cube_to_hex(cube_round_scaled(cube, denominator))
+        })
+        .collect()
+}
+
+const LINE_SCALE: i32 = 8;
+
+/// `cube_round` for the coordinates multiplied by the `denominator`.
+fn cube_round_scaled(cube: PosCube, denominator: i32) -> PosCube {
+    let round = |n: i32| (2 * n + denominator).div_euclid(2 * denominator);
+    let mut rx = round(cube.x);
+    let mut ry = round(cube.y);
+    let mut rz = round(cube.z);
+    let x_diff = (rx * denominator - cube.x).abs();
+    let y_diff = (ry * denominator - cube.y).abs();
+    let z_diff = (rz * denominator - cube.z).abs();
+    if x_diff > y_diff && x_diff > z_diff {
+        rx = -ry - rz;
+    } else if y_diff > z_diff {
+        ry = -rx - rz;
+    } else {
+        rz = -rx - ry;
+    }
+    PosCube {
+        x: rx,
+        y: ry,
+        z: rz,
+    }
+}
+
 fn is_inboard(radius: i32, pos: PosHex) -> bool {
     let origin = PosCube { x: 0, y: 0, z: 0 };
     distance_cube(origin, hex_to_cube(pos)) <= radius
@@ -193,7 +246,7 @@ impl<T: Copy + Default + Debug> HexMap<T> {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Dir {
     SouthEast,
     East,
@@ -242,6 +295,32 @@ impl Dir {
         panic!("impossible positions: {:?}, {:?}", from, to);
     }
 
+    /// The direction that points closest to `to`, which doesn't have to be adjacent.
+    /// Ties are resolved in favor of the direction that comes first in `dirs()`.
+    pub fn towards(from: PosHex, to: PosHex) -> Option<Self> {
+        if from == to {
+            return None;
+        }
+        let diff = hex_to_cube(PosHex::new(to.q - from.q, to.r - from.r));
+        let mut best = None;
+        let mut best_dot = i32::MIN;
+        for dir in dirs() {
+            let dir_diff = hex_to_cube(Self::get_neighbor_pos(PosHex::new(0, 0), dir));
+            let dot = diff.x * dir_diff.x + diff.y * dir_diff.y + diff.z * dir_diff.z;
+            if dot > best_dot {
+                best = Some(dir);
+                best_dot = dot;
+            }
+        }
+        best
+    }
+
+    /// How many sixth-turns it takes to rotate from one direction to another (0..=3).
+    pub fn turns_between(self, other: Self) -> i32 {
+        let diff = (self.to_int() - other.to_int()).rem_euclid(6);
+        diff.min(6 - diff)
+    }
+
     pub fn get_neighbor_pos(pos: PosHex, dir: Self) -> PosHex {
         let diff = DIR_TO_POS_DIFF[dir.to_int() as usize];
         PosHex {
@@ -291,7 +370,7 @@ impl Iterator for DirIter {
 
 #[cfg(test)]
 mod tests {
-    use crate::map::HexMap;
+    use crate::map::{dirs, distance_hex, line, Dir, HexMap, PosHex};
 
     #[test]
     fn test_map_height() {
@@ -299,4 +378,51 @@ mod tests {
         let height = map.height();
         assert_eq!(height, 7);
     }
+
+    #[test]
+    fn test_line() {
+        let from = PosHex::new(0, 0);
+        assert_eq!(line(from, from), vec![from]);
+        let to = PosHex::new(3, 0);
+        let expected: Vec<_> = (0..=3).map(|q| PosHex::new(q, 0)).collect();
+        assert_eq!(line(from, to), expected);
+        let to = PosHex::new(2, -4);
+        let tiles = line(from, to);
+        assert_eq!(tiles.len(), 5);
+        assert_eq!(tiles[0], from);
+        assert_eq!(tiles[4], to);
+        for pair in tiles.windows(2) {
+            assert_eq!(distance_hex(pair[0], pair[1]), 1);
+        }
+        // Lines along hex edges pick the same side both ways.
+        let to = PosHex::new(1, 1);
+        let mut back = line(to, from);
+        back.reverse();
+        assert_eq!(line(from, to), back);
+        // Exact ties are broken the same way wherever the line starts.
+        let to = PosHex::new(4, -8);
+        let tiles = line(from, to);
+        assert_eq!(tiles[1], PosHex::new(1, -1));
+        assert_eq!(tiles[7], PosHex::new(4, -7));
+        let shift = |pos: PosHex| PosHex::new(pos.q - 7, pos.r);
+        let shifted: Vec<_> = tiles.iter().map(|&pos| shift(pos)).collect();
+        assert_eq!(line(shift(from), shift(to)), shifted);
+    }
+
+    #[test]
+    fn test_dir_towards() {
+        let from = PosHex::new(0, 0);
+        assert_eq!(Dir::towards(from, from), None);
+        for dir in dirs() {
+            let neighbor = Dir::get_neighbor_pos(from, dir);
+            assert_eq!(Dir::towards(from, neighbor), Some(dir));
+            let far = Dir::get_neighbor_pos(neighbor, dir);
+            assert_eq!(Dir::towards(from, far), Some(dir));
+        }
+        assert_eq!(Dir::towards(from, PosHex::new(3, -1)), Some(Dir::SouthEast));
+        assert_eq!(Dir::SouthEast.turns_between(Dir::SouthEast), 0);
+        assert_eq!(Dir::SouthEast.turns_between(Dir::SouthWest), 1);
+        assert_eq!(Dir::SouthEast.turns_between(Dir::NorthEast), 2);
+        assert_eq!(Dir::SouthEast.turns_between(Dir::NorthWest), 3);
+    }
 }
@@ -35,6 +35,13 @@ pub fn clamp<T: PartialOrd>(value: T, min: T, max: T) -> T {
     }
 }
 
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct SimpleRng {
     seed: u32,
@@ -51,6 +58,19 @@ impl SimpleRng {
         }
     }
 
+    /// Mixes a 32-byte seed (e.g. a commit-reveal hash) into the generator's state.
+    ///
+    /// Every word goes through splitmix64, so the seeds that only differ
+    /// in a few bits still give unrelated streams.
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        let state = seed.chunks(8).fold(0, |acc, word| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(word);
+            splitmix64(acc ^ u64::from_le_bytes(bytes))
+        });
+        Self::seed_from_u32((state >> 32) as u32 ^ state as u32)
+    }
+
     fn next(&mut self) -> u32 {
         self.seed = self.a.wrapping_mul(self.seed).wrapping_add(self.c);
         self.seed
@@ -96,4 +116,42 @@ mod tests {
         assert_eq!(&a, &[2, 3]);
         assert!(!super::try_remove_item(&mut a, &666));
     }
+
+    #[test]
+    fn test_rng_from_seed() {
+        use super::SimpleRng;
+        let roll =
+            |mut rng: SimpleRng| -> Vec<i32> { (0..8).map(|_| rng.gen_range(0, 100)).collect() };
+        assert_eq!(
+            roll(SimpleRng::from_seed([7; 32])),
+            roll(SimpleRng::from_seed([7; 32]))
+        );
+        let mut seed = [0; 32];
+        seed[31] = 1;
+        assert_ne!(
+            roll(SimpleRng::from_seed(seed)),
+            roll(SimpleRng::from_seed([0; 32]))
+        );
+    }
+
+    #[test]
+    fn test_rng_from_seed_high_bits() {
+        use super::SimpleRng;
+        let roll =
+            |mut rng: SimpleRng| -> Vec<i32> { (0..8).map(|_| rng.gen_range(0, 100)).collect() };
+        // These two would cancel each other out if the words were just XORed.
+        let mut seed = [0; 32];
+        seed[3] = 0x80;
+        seed[7] = 0x80;
+        assert_ne!(
+            roll(SimpleRng::from_seed(seed)),
+            roll(SimpleRng::from_seed([0; 32]))
+        );
+        let mut other = [0; 32];
+        other[7] = 0x80;
+        assert_ne!(
+            roll(SimpleRng::from_seed(seed)),
+            roll(SimpleRng::from_seed(other))
+        );
+    }
 }
@@ -0,0 +1,117 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use battle::game::{Input, InputDigests, Journal};
+use methods::{METHOD_ELF, METHOD_ID};
+use risc0_zkvm::{default_prover, sha::Digest, ExecutorEnv, ExecutorImpl, Receipt};
+use serde::{Deserialize, Serialize};
+
+/// A proven battle as it is stored on disk.
+///
+/// The file is plain JSON, so it can be read without the game.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofFile {
+    pub image_id: Digest,
+
+    /// Decoded copy of the journal committed in `receipt`.
+    pub journal: Journal,
+
+    /// Holds the seal and the raw journal bytes.
+    pub receipt: Receipt,
+
+    pub input: Input,
+}
+
+impl ProofFile {
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Can't write '{}'", path.display()))?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let bytes = fs::read(path).with_context(|| format!("Can't read '{}'", path.display()))?;
+        serde_json::from_slice(&bytes).with_context(|| format!("Bad receipt '{}'", path.display()))
+    }
+
+    /// Checks the seal against the guest's image id and
+    /// makes sure `journal` is what the receipt actually commits to
+    /// and that `input` is what the journal was committed for.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        let image_id = Digest::from(METHOD_ID);
+        if self.image_id != image_id {
+            anyhow::bail!("Image id mismatch: {} != {}", self.image_id, image_id);
+        }
+        self.receipt.verify(image_id)?;
+        let journal: Journal = self.receipt.journal.decode()?;
+        if journal != self.journal {
+            anyhow::bail!("The decoded journal doesn't match the receipt");
+        }
+        // Even a rejection must be for this very input.
+        check_input(&journal.input, &self.input)
+    }
+}
+
+fn check_input(journal: &InputDigests, input: &Input) -> anyhow::Result<()> {
+    let digests = [
+        ("scenario", journal.scenario_digest, input.scenario.digest()),
+        (
+            "prototypes",
+            journal.prototypes_digest,
+            input.prototypes.digest(),
+        ),
+        (
+            "ability definitions",
+            journal.ability_definitions_digest,
+            input.ability_definitions.digest(),
+        ),
+        ("tiles", journal.tiles_digest, input.tiles.digest()),
+        ("commands", journal.commands_digest, input.commands_digest()),
+    ];
+    for (name, committed, actual) in digests {
+        if committed != actual {
+            anyhow::bail!(
+                "The input's {} digest {} doesn't match the journal's {}",
+                name,
+                actual,
+                committed
+            );
+        }
+    }
+    if journal.heroes != input.heroes {
+        anyhow::bail!("The input's heroes don't match the journal");
+    }
+    if journal.seed != input.seed {
+        anyhow::bail!("The input's seed doesn't match the journal");
+    }
+    Ok(())
+}
+
+/// Runs the guest without proving. Returns the user cycle count and the journal.
+pub fn execute(input: &Input) -> anyhow::Result<(u64, Journal)> {
+    let env = ExecutorEnv::builder().write(input)?.build()?;
+    let mut exec = ExecutorImpl::from_elf(env, METHOD_ELF)?;
+    let session = exec.run()?;
+    let journal = match session.journal {
+        Some(journal) => journal.decode()?,
+        None => anyhow::bail!("The guest didn't commit a journal"),
+    };
+    Ok((session.user_cycles, journal))
+}
+
+pub fn prove(input: &Input) -> anyhow::Result<ProofFile> {
+    let env = ExecutorEnv::builder().write(input)?.build()?;
+    let receipt = default_prover().prove(env, METHOD_ELF)?.receipt;
+    let journal = receipt.journal.decode()?;
+    let proof = ProofFile {
+        image_id: Digest::from(METHOD_ID),
+        journal,
+        receipt,
+        input: input.clone(),
+    };
+    proof.verify()?;
+    Ok(proof)
+}
@@ -1,62 +1,124 @@
-use battle::{
-    battle::heroes::{Hero, HeroObject},
-    game::{Input, Level},
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
 };
-use methods::{METHOD_ELF, METHOD_ID};
-use risc0_zkvm::{default_prover, ExecutorEnv, ExecutorImpl};
 
-pub fn prove_game(input: Input) {
-    let env = ExecutorEnv::builder()
-        .write(&input)
-        .unwrap()
-        .build()
-        .unwrap();
+use anyhow::Context;
+use battle::game::{Input, Journal};
+use host::ProofFile;
 
-    let prover = default_prover();
+const USAGE: &str = "\
+Usage:
+    host execute <input.json>
+    host prove <input.json> -o <receipt>
+    host verify <receipt>
+    host inspect <receipt>";
 
-    let start = std::time::Instant::now();
-    let receipt = prover.prove(env, METHOD_ELF).unwrap().receipt;
-    println!("prover time: {:.2?}", start.elapsed());
+enum Command {
+    Execute { input: PathBuf },
+    Prove { input: PathBuf, output: PathBuf },
+    Verify { receipt: PathBuf },
+    Inspect { receipt: PathBuf },
+}
 
-    assert!(receipt.verify(METHOD_ID).is_ok());
+fn parse_args(args: &[String]) -> Option<Command> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let command = match args.as_slice() {
+        ["execute", input] => Command::Execute {
+            input: input.into(),
+        },
+        ["prove", input, "-o", output] | ["prove", "-o", output, input] => Command::Prove {
+            input: input.into(),
+            output: output.into(),
+        },
+        ["verify", receipt] => Command::Verify {
+            receipt: receipt.into(),
+        },
+        ["inspect", receipt] => Command::Inspect {
+            receipt: receipt.into(),
+        },
+        _ => return None,
+    };
+    Some(command)
 }
 
-pub fn run(input: Input) {
-    let env = ExecutorEnv::builder()
-        .write(&input)
-        .unwrap()
-        .build()
-        .unwrap();
+fn load_input(path: &Path) -> anyhow::Result<Input> {
+    let s = fs::read_to_string(path).with_context(|| format!("Can't read '{}'", path.display()))?;
+    let input = if path.extension().and_then(|ext| ext.to_str()) == Some("ron") {
+        ron::de::from_str(&s).map_err(anyhow::Error::from)
+    } else {
+        serde_json::from_str(&s).map_err(anyhow::Error::from)
+    };
+    input.with_context(|| format!("Bad input '{}'", path.display()))
+}
 
-    let mut exec = ExecutorImpl::from_elf(env, &METHOD_ELF).unwrap();
-    let session = exec.run().unwrap();
-    println!("cycle:{}", session.user_cycles);
+fn print_journal(journal: &Journal) {
+    let input = &journal.input;
+    println!("scenario digest: {}", input.scenario_digest);
+    println!("prototypes digest: {}", input.prototypes_digest);
+    println!(
+        "ability definitions digest: {}",
+        input.ability_definitions_digest
+    );
+    println!("tiles digest: {}", input.tiles_digest);
+    println!("heroes: {:?}", input.heroes);
+    println!("seed: {}", hex::encode(input.seed));
+    println!("commands digest: {}", input.commands_digest);
+    match &journal.outcome {
+        Ok(outcome) => {
+            println!("state digest: {}", outcome.state_digest);
+            println!("battle result: {:?}", outcome.battle_result);
+        }
+        Err(error) => println!("rejected transcript: {:?}", error),
+    }
+}
+
+fn run(command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::Execute { input } => {
+            let input = load_input(&input)?;
+            let (cycles, journal) = host::execute(&input)?;
+            println!("cycles: {}", cycles);
+            print_journal(&journal);
+        }
+        Command::Prove { input, output } => {
+            let input = load_input(&input)?;
+            let start = std::time::Instant::now();
+            let proof = host::prove(&input)?;
+            println!("prover time: {:.2?}", start.elapsed());
+            proof.save(&output)?;
+            println!("saved to {}", output.display());
+            print_journal(&proof.journal);
+        }
+        Command::Verify { receipt } => {
+            let proof = ProofFile::load(&receipt)?;
+            proof.verify()?;
+            println!("receipt is valid, image id: {}", proof.image_id);
+            print_journal(&proof.journal);
+        }
+        Command::Inspect { receipt } => {
+            let proof = ProofFile::load(&receipt)?;
+            println!("image id: {}", proof.image_id);
+            println!("rounds: {}", proof.input.commands.len());
+            println!("(not verified, the journal is shown as stored in the file)");
+            print_journal(&proof.journal);
+        }
+    }
+    Ok(())
 }
 
 fn main() {
-    // The player's command is simply `End Turn`.
-    let commands = vec![
-        vec![],
-        vec![],
-        vec![],
-        vec![],
-        vec![],
-        vec![],
-        vec![],
-        vec![],
-        vec![],
-    ];
-
-    let heroes = vec![
-        HeroObject::new(Hero::Swordsman, 1),
-        HeroObject::new(Hero::EliteSpearman, 1),
-    ];
-
-    let input = Input {
-        commands,
-        level: Level::Level0,
-        heroes,
+    let args: Vec<String> = env::args().skip(1).collect();
+    let command = match parse_args(&args) {
+        Some(command) => command,
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
     };
-
-    run(input);
+    if let Err(error) = run(command) {
+        eprintln!("error: {:#}", error);
+        process::exit(1);
+    }
 }
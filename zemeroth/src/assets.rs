@@ -14,10 +14,15 @@ use crate::{error::ZError, ZResult};
 
 use battle::{
     battle::{
-        ability::Ability,
-        component::{ObjType, Prototypes, WeaponType},
+        ability::{
+            definitions::{self, Definitions},
+            Ability,
+        },
+        component::{ObjType, WeaponType},
         effect,
-        scenario::Scenario,
+        prototypes::{self, Prototypes},
+        scenario::{self, Scenario},
+        tiles::{self, Tiles},
     },
     campaign,
 };
@@ -46,13 +51,45 @@ async fn deserialize_from_file<D: DeserializeOwned>(path: &str) -> ZResult<D> {
     ron::de::from_str(&s).map_err(|e| ZError::from_ron_de_error(e, path.into()))
 }
 
-async fn load_map<Key: Hash + Eq + Copy>(
+async fn load_scenario(path: &str) -> ZResult<Scenario> {
+    let s = read_file(path).await?;
+    scenario::format::from_ron(&s).map_err(|error| ZError::ScenarioFormat {
+        error,
+        path: path.into(),
+    })
+}
+
+async fn load_prototypes(path: &str) -> ZResult<Prototypes> {
+    let s = read_file(path).await?;
+    prototypes::from_ron(&s).map_err(|error| ZError::PrototypesFormat {
+        error,
+        path: path.into(),
+    })
+}
+
+async fn load_ability_definitions(path: &str) -> ZResult<Definitions> {
+    let s = read_file(path).await?;
+    definitions::from_ron(&s).map_err(|error| ZError::AbilitiesFormat {
+        error,
+        path: path.into(),
+    })
+}
+
+async fn load_tiles(path: &str) -> ZResult<Tiles> {
+    let s = read_file(path).await?;
+    tiles::from_ron(&s).map_err(|error| ZError::TilesFormat {
+        error,
+        path: path.into(),
+    })
+}
+
+async fn load_map<Key: Hash + Eq + Clone>(
     table: &[(Key, &str)],
     expand_path: fn(&str) -> String,
 ) -> ZResult<HashMap<Key, Texture2D>> {
     let mut map = HashMap::new();
-    for &(key, path) in table {
-        map.insert(key, load_texture(&expand_path(path)).await?);
+    for (key, path) in table {
+        map.insert(key.clone(), load_texture(&expand_path(path)).await?);
     }
     Ok(map)
 }
@@ -81,6 +118,8 @@ pub struct Assets {
     pub sprites_info: SpritesInfo,
     pub sprite_frames: HashMap<ObjType, HashMap<String, Texture2D>>,
     pub prototypes: Prototypes,
+    pub ability_definitions: Definitions,
+    pub tiles: Tiles,
     pub demo_scenario: Scenario,
     pub campaign_plan: campaign::Plan,
     pub agent_campaign_info: HashMap<ObjType, campaign::AgentInfo>,
@@ -105,8 +144,10 @@ impl Assets {
             font: text::load_ttf_font("OpenSans-Regular.ttf").await?,
             sprites_info,
             sprite_frames,
-            prototypes: Prototypes::from_str(&read_file("objects.ron").await?),
-            demo_scenario: deserialize_from_file("scenario_01.ron").await?,
+            prototypes: load_prototypes("objects.ron").await?,
+            ability_definitions: load_ability_definitions("abilities.ron").await?,
+            tiles: load_tiles("tiles.ron").await?,
+            demo_scenario: load_scenario("scenario_01.ron").await?,
             campaign_plan: deserialize_from_file("campaign_01.ron").await?,
             agent_campaign_info: deserialize_from_file("agent_campaign_info.ron").await?,
         })
@@ -216,6 +257,12 @@ async fn load_lasting_effects() -> ZResult<HashMap<effect::Lasting, Texture2D>>
         (effect::Lasting::Stun, "stun"),
         (effect::Lasting::Poison, "poison"),
         (effect::Lasting::Bloodlust, "bloodlust"),
+        (effect::Lasting::Burning, "burning"),
+        (effect::Lasting::Slowed, "slowed"),
+        (effect::Lasting::Rooted, "rooted"),
+        (effect::Lasting::Shielded, "shielded"),
+        (effect::Lasting::Blinded, "blinded"),
+        (effect::Lasting::Taunted, "taunted"),
     ];
     load_map(map, |s| format!("img/effect_{}.png", s)).await
 }
@@ -1,5 +1,15 @@
 use std::{error, fmt, io, path::PathBuf};
 
+use battle::{
+    battle::{
+        ability::definitions,
+        prototypes,
+        scenario::{self, format::LoadError},
+        tiles,
+    },
+    campaign,
+};
+
 #[derive(Debug, derive_more::From)]
 pub struct GenerateProofError(pub String);
 
@@ -19,9 +29,42 @@ pub enum ZError {
         error: ron::de::SpannedError,
         path: PathBuf,
     },
+    RonSerialize {
+        error: ron::Error,
+        path: PathBuf,
+    },
+    Json {
+        error: serde_json::Error,
+        path: PathBuf,
+    },
     IO(io::Error),
     Mq(mq::Error),
     GenerateProof(GenerateProofError),
+    ScenarioFormat {
+        error: LoadError,
+        path: PathBuf,
+    },
+    BadScenario(Vec<scenario::ValidationError>),
+    PrototypesFormat {
+        error: prototypes::LoadError,
+        path: PathBuf,
+    },
+    AbilitiesFormat {
+        error: definitions::LoadError,
+        path: PathBuf,
+    },
+    TilesFormat {
+        error: tiles::LoadError,
+        path: PathBuf,
+    },
+    SaveVersion {
+        version: u32,
+        path: PathBuf,
+    },
+    BadSave {
+        error: campaign::Error,
+        path: PathBuf,
+    },
 }
 
 impl ZError {
@@ -39,9 +82,45 @@ impl fmt::Display for ZError {
                 let s = path.to_str().unwrap_or("<no path>");
                 write!(f, "Can't deserialize '{}': {}", s, error)
             }
+            ZError::RonSerialize { error, path } => {
+                let s = path.to_str().unwrap_or("<no path>");
+                write!(f, "Can't serialize '{}': {}", s, error)
+            }
+            ZError::Json { error, path } => {
+                let s = path.to_str().unwrap_or("<no path>");
+                write!(f, "JSON error in '{}': {}", s, error)
+            }
             ZError::IO(ref e) => write!(f, "IO Error: {}", e),
             ZError::Mq(ref e) => write!(f, "Macroquad error: {}", e),
             ZError::GenerateProof(e) => write!(f, "{}", e),
+            ZError::ScenarioFormat { error, path } => {
+                let s = path.to_str().unwrap_or("<no path>");
+                write!(f, "Can't load scenario '{}': {}", s, error)
+            }
+            ZError::BadScenario(errors) => {
+                let errors: Vec<_> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "Bad scenario: {}", errors.join("; "))
+            }
+            ZError::PrototypesFormat { error, path } => {
+                let s = path.to_str().unwrap_or("<no path>");
+                write!(f, "Can't load prototypes '{}': {}", s, error)
+            }
+            ZError::AbilitiesFormat { error, path } => {
+                let s = path.to_str().unwrap_or("<no path>");
+                write!(f, "Can't load abilities '{}': {}", s, error)
+            }
+            ZError::TilesFormat { error, path } => {
+                let s = path.to_str().unwrap_or("<no path>");
+                write!(f, "Can't load tiles '{}': {}", s, error)
+            }
+            ZError::SaveVersion { version, path } => {
+                let s = path.to_str().unwrap_or("<no path>");
+                write!(f, "Save '{}' has an unsupported version {}", s, version)
+            }
+            ZError::BadSave { error, path } => {
+                let s = path.to_str().unwrap_or("<no path>");
+                write!(f, "Save '{}' doesn't match the assets: {:?}", s, error)
+            }
         }
     }
 }
@@ -52,9 +131,18 @@ impl error::Error for ZError {
             ZError::Ui(ref e) => Some(e),
             ZError::Scene(ref e) => Some(e),
             ZError::RonDeserialize { error, .. } => Some(error),
+            ZError::RonSerialize { error, .. } => Some(error),
+            ZError::Json { error, .. } => Some(error),
             ZError::IO(ref e) => Some(e),
             ZError::Mq(ref e) => Some(e),
             ZError::GenerateProof(ref e) => Some(e),
+            ZError::ScenarioFormat { error, .. } => Some(error),
+            ZError::BadScenario(_) => None,
+            ZError::PrototypesFormat { error, .. } => Some(error),
+            ZError::AbilitiesFormat { error, .. } => Some(error),
+            ZError::TilesFormat { error, .. } => Some(error),
+            ZError::SaveVersion { .. } => None,
+            ZError::BadSave { .. } => None,
         }
     }
 }
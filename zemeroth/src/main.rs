@@ -7,7 +7,9 @@ use mq::{input, window};
 mod assets;
 mod error;
 mod geom;
+mod save;
 mod screen;
+mod transcript;
 mod utils;
 
 type ZResult<T = ()> = Result<T, error::ZError>;
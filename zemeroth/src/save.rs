@@ -0,0 +1,230 @@
+//! Campaign saves (`battle::campaign::State`), one RON file per named slot.
+//!
+//! The campaign plan is saved together with the state, so the runs in progress
+//! aren't affected by the changes in `campaign_01.ron`.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use battle::campaign::{Mode, Renown, State};
+use serde::{Deserialize, Serialize};
+
+use crate::{assets, error::ZError, ZResult};
+
+/// Bump this when a change of `campaign::State` breaks the older saves.
+pub const FORMAT_VERSION: u32 = 1;
+
+pub const SLOTS: [&str; 3] = ["first", "second", "third"];
+
+/// Lets the slots be listed without loading and checking the whole campaign.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Summary {
+    pub mode: Mode,
+    pub battles_won: i32,
+    pub renown: Renown,
+}
+
+impl Summary {
+    fn new(state: &State) -> Self {
+        Self {
+            mode: state.mode(),
+            battles_won: state.battles_won(),
+            renown: state.renown(),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(self.mode, Mode::Won | Mode::Failed)
+    }
+}
+
+#[derive(Serialize)]
+struct SaveFileRef<'a> {
+    version: u32,
+    summary: Summary,
+    state: &'a State,
+}
+
+/// Read before the rest of the file, so an old save is reported as such
+/// and not as a parse error.
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct SummaryFile {
+    summary: Summary,
+}
+
+#[derive(Deserialize)]
+struct SaveFile {
+    state: State,
+}
+
+/// `$XDG_DATA_HOME/zemeroth/saves` (`~/.local/share/zemeroth/saves` by default)
+/// on Linux, a directory next to the game elsewhere.
+fn dir() -> PathBuf {
+    #[cfg(target_os = "linux")]
+    {
+        let data_home = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+        if let Some(data_home) = data_home {
+            return data_home.join("zemeroth").join("saves");
+        }
+    }
+    PathBuf::from("saves")
+}
+
+fn path(slot: &str) -> PathBuf {
+    dir().join(format!("{}.ron", slot))
+}
+
+pub fn save(slot: &str, state: &State) -> ZResult {
+    save_to(&path(slot), state)
+}
+
+fn save_to(path: &Path, state: &State) -> ZResult {
+    let file = SaveFileRef {
+        version: FORMAT_VERSION,
+        summary: Summary::new(state),
+        state,
+    };
+    let s =
+        ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default()).map_err(|error| {
+            ZError::RonSerialize {
+                error,
+                path: path.to_owned(),
+            }
+        })?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // A crash in the middle of writing shouldn't take the old save with it.
+    let tmp_path = path.with_extension("ron.tmp");
+    fs::write(&tmp_path, s)?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// Reads the file and checks its version.
+fn read(path: &Path) -> ZResult<String> {
+    let s = fs::read_to_string(path)?;
+    let header: Header =
+        ron::de::from_str(&s).map_err(|e| ZError::from_ron_de_error(e, path.to_owned()))?;
+    if header.version != FORMAT_VERSION {
+        let version = header.version;
+        let path = path.to_owned();
+        return Err(ZError::SaveVersion { version, path });
+    }
+    Ok(s)
+}
+
+/// Reads only the summary, the campaign itself is skipped.
+pub fn summary(slot: &str) -> ZResult<Summary> {
+    read_summary(&path(slot))
+}
+
+fn read_summary(path: &Path) -> ZResult<Summary> {
+    let s = read(path)?;
+    let file: SummaryFile =
+        ron::de::from_str(&s).map_err(|e| ZError::from_ron_de_error(e, path.to_owned()))?;
+    Ok(file.summary)
+}
+
+/// Reads the campaign without checking it against the assets.
+fn read_state(path: &Path) -> ZResult<State> {
+    let s = read(path)?;
+    let file: SaveFile =
+        ron::de::from_str(&s).map_err(|e| ZError::from_ron_de_error(e, path.to_owned()))?;
+    Ok(file.state)
+}
+
+pub fn load(slot: &str) -> ZResult<State> {
+    let path = path(slot);
+    let mut state = read_state(&path)?;
+    let assets = assets::get();
+    state.set_agent_info(assets.agent_campaign_info.clone());
+    if let Err(error) = state.check(&assets.prototypes, &assets.tiles) {
+        return Err(ZError::BadSave { error, path });
+    }
+    Ok(state)
+}
+
+pub fn exists(slot: &str) -> bool {
+    path(slot).exists()
+}
+
+fn modified(slot: &str) -> Option<SystemTime> {
+    fs::metadata(path(slot)).and_then(|m| m.modified()).ok()
+}
+
+/// The most recently saved campaign that isn't finished yet.
+pub fn latest() -> Option<&'static str> {
+    SLOTS
+        .iter()
+        .copied()
+        .filter(|slot| match summary(slot) {
+            Ok(summary) => !summary.is_finished(),
+            Err(_) => false,
+        })
+        .max_by_key(|slot| modified(slot))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use battle::campaign::{Plan, State};
+
+    use crate::error::ZError;
+
+    use super::{read_state, read_summary, save_to, FORMAT_VERSION};
+
+    fn state() -> State {
+        let plan: Plan = ron::de::from_str(include_str!("../../assets/campaign_01.ron"))
+            .expect("Can't parse the campaign");
+        let agent_info = ron::de::from_str(include_str!("../../assets/agent_campaign_info.ron"))
+            .expect("Can't parse the agent info");
+        State::new(plan, agent_info)
+    }
+
+    /// The maps are sorted, so the result doesn't depend on their iteration order.
+    fn to_value(state: &State) -> serde_json::Value {
+        serde_json::to_value(state).expect("Can't serialize the campaign")
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = env::temp_dir().join(format!("zemeroth_test_save_{}.ron", process::id()));
+        let state = state();
+        save_to(&path, &state).unwrap();
+        assert_eq!(to_value(&read_state(&path).unwrap()), to_value(&state));
+        let summary = read_summary(&path).unwrap();
+        assert_eq!(summary.mode, state.mode());
+        assert_eq!(summary.battles_won, state.battles_won());
+        assert_eq!(summary.renown, state.renown());
+
+        // The summary doesn't depend on the rest of the campaign.
+        let s = fs::read_to_string(&path).unwrap();
+        fs::write(&path, s.replacen("current_node:", "unknown_field:", 1)).unwrap();
+        assert!(read_state(&path).is_err());
+        assert_eq!(read_summary(&path).unwrap().mode, state.mode());
+
+        let s = fs::read_to_string(&path).unwrap();
+        let version = format!("version: {}", FORMAT_VERSION);
+        let other_version = format!("version: {}", FORMAT_VERSION + 1);
+        fs::write(&path, s.replacen(&version, &other_version, 1)).unwrap();
+        let result = read_summary(&path);
+        assert!(
+            matches!(result, Err(ZError::SaveVersion { version, .. }) if version == FORMAT_VERSION + 1)
+        );
+        assert!(matches!(read_state(&path), Err(ZError::SaveVersion { .. })));
+        fs::remove_file(&path).unwrap();
+    }
+}
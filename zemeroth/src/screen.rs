@@ -14,10 +14,20 @@ mod campaign;
 mod confirm;
 mod general_info;
 mod main_menu;
+mod picker;
+mod replay;
+mod slots;
 
 pub use self::{
-    agent_info::AgentInfo, battle::Battle, campaign::Campaign, confirm::Confirm,
-    general_info::GeneralInfo, main_menu::MainMenu,
+    agent_info::AgentInfo,
+    battle::Battle,
+    campaign::Campaign,
+    confirm::Confirm,
+    general_info::GeneralInfo,
+    main_menu::MainMenu,
+    picker::Picker,
+    replay::Replay,
+    slots::{SlotPurpose, Slots},
 };
 
 pub const COLOR_SCREEN_BG: Color = Color::new(0.9, 0.9, 0.8, 1.0);
@@ -12,7 +12,8 @@ use crate::{
 
 use battle::battle::{
     ability::{Ability, PassiveAbility},
-    component::{self, Component, ObjType, Prototypes},
+    component::{self, Component, ObjType},
+    prototypes::Prototypes,
 };
 
 /// This struct is similar to [component::Parts],
@@ -23,6 +24,7 @@ struct StaticObjectInfo {
     strength: Option<component::Strength>,
     agent: Option<component::Agent>,
     blocker: Option<component::Blocker>,
+    armor: Option<component::Armor>,
     abilities: Option<component::Abilities>,
     passive_abilities: Option<component::PassiveAbilities>,
     summoner: Option<component::Summoner>,
@@ -42,10 +44,13 @@ impl StaticObjectInfo {
                 Component::PassiveAbilities(c) => this.passive_abilities = Some(c),
                 Component::Summoner(c) => this.summoner = Some(c),
                 Component::Blocker(c) => this.blocker = Some(c),
+                Component::Armor(c) => this.armor = Some(c),
                 Component::BelongsTo(_)
                 | Component::Pos(_)
+                | Component::Facing(_)
                 | Component::Effects(_)
-                | Component::Schedule(_) => (),
+                | Component::Schedule(_)
+                | Component::Hero(_) => (),
             }
         }
         this
@@ -120,23 +125,30 @@ fn info_panel(
             }
             add(line_i("attack strength:", a.attack_strength.0)?);
             add(line_i("attack accuracy:", a.attack_accuracy.0)?);
+            if a.armor_piercing.0 != 0 {
+                add(line_i("armor piercing:", a.armor_piercing.0)?);
+            }
             add(line_i("move points:", a.move_points.0)?);
         }
         if let Some(blocker) = info.blocker {
             add(line("weight:", &format!("{}", blocker.weight))?);
         }
+        if let Some(armor) = info.armor {
+            if !armor.is_empty() {
+                add(line("armor:", &armor.to_string())?);
+            }
+        }
         if let Some(abilities) = info.abilities {
             if !abilities.0.is_empty() {
                 add(label_s("~ abilities ~")?);
                 for r_ability in &abilities.0 {
-                    let s = r_ability.ability.title();
-                    let cooldown = r_ability.ability.base_cooldown();
-                    let text = format!("{} (cooldown: {}t)", s, cooldown);
+                    let definition = assets::get().ability_definitions.get(&r_ability.ability);
+                    let text = format!("{} (cooldown: {}t)", definition.title, definition.cooldown);
                     let mut line_layout = ui::HLayout::new().stretchable(true);
                     line_layout.add(label(&text)?);
                     line_layout.add(spacer_s());
                     let icon = Drawable::Texture(assets::get().textures.icons.info.clone());
-                    let message = Message::AbilityInfo(r_ability.ability);
+                    let message = Message::AbilityInfo(r_ability.ability.clone());
                     let button = ui::Button::new(icon, h, gui.sender(), message)?;
                     line_layout.add(Box::new(button));
                     add(Box::new(line_layout));
@@ -247,9 +259,10 @@ impl Screen for AgentInfo {
         match message {
             Some(Message::Back) => Ok(StackCommand::Pop),
             Some(Message::AbilityInfo(info)) => {
-                let mut description = info.description();
-                description.push(format!("Cooldown: {}t", info.base_cooldown()));
-                let screen = screen::GeneralInfo::new(&info.title(), &description)?;
+                let definition = assets::get().ability_definitions.get(&info);
+                let mut description = definition.description.clone();
+                description.push(format!("Cooldown: {}t", definition.cooldown));
+                let screen = screen::GeneralInfo::new(&definition.title, &description)?;
                 Ok(StackCommand::PushPopup(Box::new(screen)))
             }
             Some(Message::PassiveAbilityInfo(info)) => {
@@ -1,6 +1,7 @@
 use std::{
-    collections::HashMap,
-    sync::mpsc::{channel, Receiver, Sender},
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver, Sender, TryRecvError},
+    thread,
     time::Duration,
 };
 
@@ -22,14 +23,17 @@ use crate::{
         },
         Screen, StackCommand,
     },
+    transcript,
     utils::{self, line_heights, time_s},
     ZResult,
 };
 
+use host::ProofFile;
+
 use battle::{
     battle::{
         ability::{self, Ability, PassiveAbility},
-        ai::Ai,
+        ai::Ais,
         check,
         command::{self},
         effect, execute,
@@ -39,13 +43,13 @@ use battle::{
         state::{self, BattleResult},
         Id, PlayerId, State,
     },
-    game::Level,
+    game::Input,
     map::PosHex,
     utils::SimpleRng,
 };
 
-mod view;
-mod visualize;
+pub(super) mod view;
+pub(super) mod visualize;
 
 #[derive(Clone, Debug)]
 enum Message {
@@ -55,6 +59,7 @@ enum Message {
     PassiveAbilityInfo(PassiveAbility),
     LastingEffectInfo(effect::Lasting),
     GenerateProof,
+    SaveTranscript(&'static str),
     NextLevel,
 }
 
@@ -154,11 +159,20 @@ fn build_panel_agent_info(gui: &mut Gui<Message>, state: &State, id: Id) -> ZRes
             add(line_i("attack distance:", a.attack_distance)?);
         }
         add(line_i("attack strength:", a.attack_strength.0)?);
-        add(line_i("attack accuracy:", a.attack_accuracy.0)?);
-        add(line_i("move points:", a.move_points.0)?);
+        let accuracy = state::attack_accuracy(state, &id);
+        add(line_i("attack accuracy:", accuracy.0)?);
+        if a.armor_piercing.0 != 0 {
+            add(line_i("armor piercing:", a.armor_piercing.0)?);
+        }
+        add(line_i("move points:", state::move_points(state, &id).0)?);
         if let Some(blocker) = parts.blocker.get(&id) {
             add(line("weight:", &blocker.weight.to_string())?);
         }
+        if let Some(armor) = parts.armor.get(&id) {
+            if !armor.is_empty() {
+                add(line("armor:", &armor.to_string())?);
+            }
+        }
         if let Some(abilities) = parts.passive_abilities.get(&id) {
             if !abilities.0.is_empty() {
                 add(label_s("~ passive abilities ~")?);
@@ -227,9 +241,12 @@ fn build_panel_agent_abilities(
     let h = line_heights().large;
     for ability in abilities {
         let icons = &assets::get().textures.icons.abilities;
-        let texture = icons.get(&ability.ability).expect("No such icon found");
+        // Abilities that only exist in the data don't have their own icons.
+        let texture = icons
+            .get(&ability.ability)
+            .unwrap_or(&textures().icons.info);
         let drawable = ui::Drawable::Texture(texture.clone());
-        let msg = Message::Ability(ability.ability);
+        let msg = Message::Ability(ability.ability.clone());
         let mut button = ui::Button::new(drawable, h, gui.sender(), msg)?;
         if !state::can_agent_use_ability(state, &id, &ability.ability) {
             button.set_active(false);
@@ -278,18 +295,19 @@ fn build_panel_ability_description(
     let text = |s: &str| ui::Drawable::text(s, font.clone());
     let h = line_heights().normal;
     let mut layout = Box::new(ui::VLayout::new().stretchable(true));
-    let text_title = text(&format!("~~~ {} ~~~", ability.title()));
+    let definition = state.ability_definition(ability);
+    let text_title = text(&format!("~~~ {} ~~~", definition.title));
     let label_title = ui::Label::new(text_title, h)?.stretchable(true);
     layout.add(Box::new(label_title));
     layout.add(Box::new(ui::Spacer::new_vertical(h / 2.0)));
-    for line in ability.description() {
-        layout.add(Box::new(ui::Label::new(text(&line), h)?));
+    for line in &definition.description {
+        layout.add(Box::new(ui::Label::new(text(line), h)?));
     }
     let agent_player_id = state.parts().belongs_to.get(&id).unwrap().0;
     let abilities = &state.parts().abilities.get(&id).unwrap().0;
     let r_ability = abilities.iter().find(|r| &r.ability == ability).unwrap();
     let is_enemy_agent = agent_player_id != state.player_id();
-    let cooldown = r_ability.ability.base_cooldown();
+    let cooldown = definition.cooldown;
     let text_cooldown = text(&format!("Cooldown: {}t", cooldown));
     layout.add(Box::new(ui::Label::new(text_cooldown, h)?));
     if !state::can_agent_use_ability(state, &id, ability) {
@@ -321,19 +339,38 @@ fn build_panel_ability_description(
 fn build_panel_generate_proof(gui: &mut Gui<Message>) -> ZResult<ui::RcWidget> {
     let h = line_heights().big;
     let font = assets::get().font.clone();
-    let drawable = Drawable::Text {
-        label: "gen proof".to_owned(),
-        font,
-        font_size: 2,
+    let button = |label: &str, message| -> ZResult<Box<dyn ui::Widget>> {
+        let drawable = Drawable::Text {
+            label: label.to_owned(),
+            font: font.clone(),
+            font_size: 2,
+        };
+        let button = ui::Button::new(drawable, h, gui.sender(), message)?.stretchable(true);
+        Ok(Box::new(button))
     };
-    let button = ui::Button::new(drawable, h, gui.sender(), Message::GenerateProof)?;
-    let layout = ui::VLayout::from_widget(Box::new(button));
+    let mut layout = ui::VLayout::new().stretchable(true);
+    layout.add(button("gen proof", Message::GenerateProof)?);
+    layout.add(button("save json", Message::SaveTranscript("json"))?);
+    layout.add(button("save ron", Message::SaveTranscript("ron"))?);
+    layout.stretch_to_self();
     let anchor = ui::Anchor(ui::HAnchor::Right, ui::VAnchor::Top);
     let packed_layout = ui::pack(layout);
     gui.add(&packed_layout, anchor);
     Ok(packed_layout)
 }
 
+fn build_panel_proof_progress(gui: &mut Gui<Message>, elapsed: Duration) -> ZResult<ui::RcWidget> {
+    let h = line_heights().normal;
+    let font = assets::get().font.clone();
+    let text = format!("Generating the proof... {}s", elapsed.as_secs());
+    let label = ui::Label::new(ui::Drawable::text(text, font), h)?;
+    let layout = utils::add_offsets_and_bg(Box::new(label), utils::OFFSET_SMALL)?;
+    let anchor = ui::Anchor(ui::HAnchor::Middle, ui::VAnchor::Top);
+    let packed_layout = ui::pack(layout);
+    gui.add(&packed_layout, anchor);
+    Ok(packed_layout)
+}
+
 fn build_panel_next_level(gui: &mut Gui<Message>) -> ZResult<ui::RcWidget> {
     let h = line_heights().big;
     let font = assets::get().font.clone();
@@ -371,13 +408,14 @@ enum CommandOrigin {
 pub struct Battle {
     gui: Gui<Message>,
     state: State,
+    scenario: scenario::Scenario,
     battle_type: scenario::BattleType,
     mode: SelectionMode,
     view: BattleView,
     selected_agent_id: Option<Id>,
     pathfinder: Pathfinder,
     block_timer: Option<Duration>,
-    ai: Ai,
+    ais: Ais,
     panel_info: Option<ui::RcWidget>,
     panel_abilities: Option<ui::RcWidget>,
     panel_ability_description: Option<ui::RcWidget>,
@@ -385,33 +423,44 @@ pub struct Battle {
     sender: Sender<Option<BattleResult>>,
     confirmation_receiver_exit: Option<Receiver<screen::confirm::Message>>,
     rand: SimpleRng,
-    _proof: HashMap<String, String>,
+    seed: [u8; 32],
+    proof_receiver: Option<Receiver<Result<(PathBuf, ProofFile), String>>>,
+    proof_elapsed: Duration,
+    panel_proof: Option<ui::RcWidget>,
 }
 
 impl Battle {
     pub fn new(
         heroes: &[HeroObject],
         battle_type: scenario::BattleType,
-        level: Level,
+        scenario: scenario::Scenario,
         sender: Sender<Option<BattleResult>>,
-        _seed: [u8; 32],
+        seed: [u8; 32],
     ) -> ZResult<Self> {
-        let scenario: scenario::Scenario = level.scenario();
-        let mut rng = SimpleRng::seed_from_u32(0);
-        // let heros = level.fake_heros();
+        scenario.validate_layout()?;
+        let mut rng = SimpleRng::from_seed(seed);
 
         let radius = scenario.map_radius;
         let mut view = BattleView::new(radius)?;
         let mut gui = make_gui()?;
         let mut actions = Vec::new();
 
-        let mut state = State::new(scenario, level, &mut rng, &mut |state, event, phase| {
-            let action =
-                visualize(state, &mut view, event, phase).expect("Can't visualize the event");
-            actions.push(fork(action));
-        });
+        let prototypes = assets::get().prototypes.clone();
+        let ability_definitions = assets::get().ability_definitions.clone();
+        let tiles = assets::get().tiles.clone();
+        let mut state = State::new(
+            scenario.clone(),
+            prototypes,
+            ability_definitions,
+            tiles,
+            &mut rng,
+            &mut |state, event, phase| {
+                let action =
+                    visualize(state, &mut view, event, phase).expect("Can't visualize the event");
+                actions.push(fork(action));
+            },
+        );
 
-        let mut rng = SimpleRng::seed_from_u32(0);
         state.create_heroes(heroes, &mut rng, &mut |state, event, phase| {
             let action =
                 visualize(state, &mut view, event, phase).expect("Can't visualize the event");
@@ -422,17 +471,19 @@ impl Battle {
         let panel_end_turn = Some(build_panel_end_turn(&mut gui)?);
 
         build_panel_generate_proof(&mut gui)?;
+        let ais = Ais::new(scenario.players_count);
 
         Ok(Self {
             gui,
             view,
             mode: SelectionMode::Normal,
             state,
+            scenario,
             battle_type,
             selected_agent_id: None,
             pathfinder: Pathfinder::new(radius),
             block_timer: None,
-            ai: Ai::new(PlayerId(1)),
+            ais,
             panel_info: None,
             panel_abilities: None,
             panel_end_turn,
@@ -440,7 +491,10 @@ impl Battle {
             sender,
             confirmation_receiver_exit: None,
             rand: rng,
-            _proof: HashMap::new(),
+            seed,
+            proof_receiver: None,
+            proof_elapsed: Duration::from_secs(0),
+            panel_proof: None,
         })
     }
 
@@ -458,12 +512,15 @@ impl Battle {
 
     fn do_ai(&mut self) -> Box<dyn Action> {
         let mut actions = Vec::new();
-        self.ai.update_obj_ids(&self.state);
-        while let Some(command) = self.ai.command(&self.state) {
-            actions.push(self.do_command_inner(&command, CommandOrigin::Internal));
-            actions.push(action::Sleep::new(time_s(0.2)).boxed());
-            if let command::Command::EndTurn(_) = command {
-                break;
+        while self.state.battle_result().is_none() && self.state.player_id() != PlayerId(0) {
+            let player_id = self.state.player_id();
+            self.ais.get_mut(player_id).update_obj_ids(&self.state);
+            while let Some(command) = self.ais.get_mut(player_id).command(&self.state) {
+                actions.push(self.do_command_inner(&command, CommandOrigin::Internal));
+                actions.push(action::Sleep::new(time_s(0.2)).boxed());
+                if let command::Command::EndTurn(_) = command {
+                    break;
+                }
             }
         }
         action::Sequence::new(actions).boxed()
@@ -480,65 +537,97 @@ impl Battle {
         self.set_mode(id, SelectionMode::Ability(ability))
     }
 
+    fn input(&self) -> Input {
+        Input {
+            commands: self.state.commands.clone(),
+            scenario: self.scenario.clone(),
+            heroes: self.state.heroes.clone(),
+            prototypes: self.state.prototypes().clone(),
+            ability_definitions: self.state.ability_definitions().clone(),
+            tiles: self.state.tiles().clone(),
+            seed: self.seed,
+        }
+    }
+
+    fn save_transcript(&self, extension: &str) -> ZResult<Box<dyn Screen>> {
+        let path = transcript::save(&self.input(), extension)?;
+        let lines = [format!("Saved to: {}", path.display())];
+        let popup = screen::GeneralInfo::new("transcript", &lines)?;
+        Ok(Box::new(popup))
+    }
+
     fn generate_proof(&mut self) -> ZResult {
         if self.state.battle_result().is_none() {
             return Err(ZError::GenerateProof(GenerateProofError(
                 "The game is not over yet!".to_owned(),
             )));
         }
-
-        use battle::game::Input;
-        use methods::METHOD_ELF;
-        use risc0_zkvm::ExecutorEnv;
-        use risc0_zkvm::ExecutorImpl;
-
-        pub fn execute_local(input: Input) {
-            let env = ExecutorEnv::builder()
-                .write(&input)
-                .unwrap()
-                .build()
-                .unwrap();
-
-            let mut exec = ExecutorImpl::from_elf(env, &METHOD_ELF).unwrap();
-            let session = exec.run().unwrap();
-            println!("cycle:{}", session.user_cycles);
+        if self.proof_receiver.is_some() {
+            return Ok(());
         }
 
-        let input = Input {
-            commands: self.state.commands.clone(),
-            level: self.state.level.clone() ,
-            heroes: self.state.heroes.clone(),
-        };
-
-        execute_local(input);
-
-        // let start = Instant::now();
-        // let receipt = prove_bonsai(&self.state.commands)
-        //     .map_err(|x| ZError::GenerateProof(GenerateProofError(x.to_string())))?;
-        // println!("Prover time: {:.2?}", start.elapsed());
-
-        // let image_id: Digest = METHOD_ID.into();
-
-        // self.proof.insert(
-        //     "seal".to_string(),
-        //     format!("0x{}", hex::encode(receipt.snark.to_vec())),
-        // );
-        // self.proof
-        //     .insert("image_id".to_string(), format!("0x{}", image_id));
-        // self.proof.insert(
-        //     "post_digest".to_string(),
-        //     format!("0x{}", receipt.post_state_digest.digest()),
-        // );
-        // self.proof.insert(
-        //     "jounral".to_string(),
-        //     format!("0x{}", receipt.journal.digest()),
-        // );
-
-        // println!("proof:{:?}", self.proof);
-
+        let input = self.input();
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let result = host::prove(&input).and_then(|proof| {
+                let path = utils::timestamped_path("receipts", "battle", "json");
+                proof.save(&path)?;
+                Ok((path, proof))
+            });
+            // The screen may already be gone, nobody to report to.
+            let _ = sender.send(result.map_err(|e| e.to_string()));
+        });
+        self.proof_receiver = Some(receiver);
+        self.proof_elapsed = Duration::from_secs(0);
+        self.panel_proof = Some(build_panel_proof_progress(
+            &mut self.gui,
+            self.proof_elapsed,
+        )?);
         Ok(())
     }
 
+    /// Polls the prover thread. Returns a popup with the outcome once it's done.
+    fn update_proof(&mut self, dtime: Duration) -> ZResult<Option<Box<dyn Screen>>> {
+        let receiver = match &self.proof_receiver {
+            Some(receiver) => receiver,
+            None => return Ok(None),
+        };
+        let result = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Disconnected) => Err("The prover thread has crashed".to_owned()),
+            Err(TryRecvError::Empty) => {
+                let secs = self.proof_elapsed.as_secs();
+                self.proof_elapsed += dtime;
+                if self.proof_elapsed.as_secs() != secs {
+                    utils::remove_widget(&mut self.gui, &mut self.panel_proof)?;
+                    let panel = build_panel_proof_progress(&mut self.gui, self.proof_elapsed)?;
+                    self.panel_proof = Some(panel);
+                }
+                return Ok(None);
+            }
+        };
+        self.proof_receiver = None;
+        utils::remove_widget(&mut self.gui, &mut self.panel_proof)?;
+        let popup = match result {
+            Ok((path, proof)) => {
+                let mut lines = vec![
+                    format!("Done in {}s.", self.proof_elapsed.as_secs()),
+                    format!("Saved to: {}", path.display()),
+                    format!("Image id: {}", proof.image_id),
+                ];
+                if let Ok(outcome) = &proof.journal.outcome {
+                    lines.push(format!("State digest: {}", outcome.state_digest));
+                }
+                screen::GeneralInfo::new("proof", &lines)?
+            }
+            Err(message) => {
+                let error = ZError::GenerateProof(GenerateProofError(message));
+                screen::GeneralInfo::new("proof failed", &[error.to_string()])?
+            }
+        };
+        Ok(Some(Box::new(popup)))
+    }
+
     fn popup_confirm_exit(&mut self) -> ZResult<Box<dyn Screen>> {
         let (sender, receiver) = channel();
         self.confirmation_receiver_exit = Some(receiver);
@@ -642,7 +731,7 @@ impl Battle {
         self.panel_abilities = build_panel_agent_abilities(gui, state, id, &mode)?;
         self.panel_info = Some(build_panel_agent_info(gui, state, id)?);
         let map = self.pathfinder.map();
-        self.view.set_mode(state, map, id, mode)?;
+        self.view.set_mode(state, map, id, &mode)?;
         self.mode = mode;
         Ok(())
     }
@@ -717,7 +806,7 @@ impl Battle {
         if self.block_timer.is_some() {
             return Ok(());
         }
-        if let SelectionMode::Ability(ability) = self.mode {
+        if let SelectionMode::Ability(ability) = self.mode.clone() {
             let id = self.selected_agent_id.unwrap();
             let command = command::UseAbility { id, pos, ability }.into();
             if check(&self.state, &command).is_ok() {
@@ -767,6 +856,9 @@ impl Screen for Battle {
         }
         self.view.tick(dtime);
         self.update_block_timer(dtime)?;
+        if let Some(popup) = self.update_proof(dtime)? {
+            return Ok(StackCommand::PushPopup(popup));
+        }
 
         if self.state.battle_result().is_some() {
             build_panel_next_level(&mut self.gui)?;
@@ -829,29 +921,10 @@ impl Screen for Battle {
                     return Ok(StackCommand::PushPopup(Box::new(popup)));
                 }
 
-                println!("--------GenerateProof---------");
-
                 self.generate_proof()?;
-
-                // let seal = format!("seal:{}", self.proof.get("seal").unwrap());
-                // let seal = (0..seal.len())
-                //     .step_by(76)
-                //     .map(|i| (&seal[i..std::cmp::min(i + 76, seal.len())]).to_string())
-                //     .collect::<Vec<_>>();
-                // let title = "proof";
-                // let mut description = vec![
-                //     format!("image_id:{}", self.proof.get("image_id").unwrap()),
-                //     format!("post_digest:{}", self.proof.get("post_digest").unwrap()),
-                //     format!("jounral:{}", self.proof.get("jounral").unwrap()),
-                // ];
-                // description.extend(seal);
-
-                let title = "proof";
-                let description = vec!["proof".to_owned()];
-
-                //  let description = vec!["hello zemeroth"];
-                let popup = screen::GeneralInfo::new(title, &description)?;
-                return Ok(StackCommand::PushPopup(Box::new(popup)));
+            }
+            Some(Message::SaveTranscript(extension)) => {
+                return Ok(StackCommand::PushPopup(self.save_transcript(extension)?));
             }
             None => self.handle_click(pos)?,
         }
@@ -1,6 +1,9 @@
 use std::{collections::HashMap, default::Default, time::Duration};
 
-use mq::{color::Color, math::Vec2};
+use mq::{
+    color::{Color, WHITE},
+    math::Vec2,
+};
 
 use zscene::{action, Action, Boxed, Layer, Scene, Sprite};
 
@@ -15,12 +18,12 @@ use crate::{
 use battle::{
     battle::{
         ability::Ability, check as command_check, command, component::ObjType, execute::hit_chance,
-        movement, Id, Jokers, Moves, State, TileType, Turns,
+        movement, state, Id, Jokers, Moves, State, TileType, Turns,
     },
     map::{self, Dir, HexMap, PosHex},
 };
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum SelectionMode {
     Normal,
     Ability(Ability),
@@ -29,6 +32,10 @@ pub enum SelectionMode {
 const TILE_COLOR_WALKABLE: Color = Color::new(0.1, 0.6, 0.1, 0.3);
 const TILE_COLOR_ATTACKABLE: Color = Color::new(0.8, 0.0, 0.0, 0.3);
 const TILE_COLOR_ABILITY: Color = Color::new(0.0, 0.0, 0.9, 0.3);
+const TILE_COLOR_WATER: Color = Color::new(0.5, 0.7, 1.0, 1.0);
+const TILE_COLOR_FOREST: Color = Color::new(0.6, 0.9, 0.6, 1.0);
+const TILE_COLOR_SWAMP: Color = Color::new(0.6, 0.65, 0.45, 1.0);
+const TILE_COLOR_LAVA: Color = Color::new(1.0, 0.45, 0.3, 1.0);
 
 #[derive(Debug, Clone, Default)]
 pub struct Layers {
@@ -327,7 +334,7 @@ impl BattleView {
         state: &State,
         map: &HexMap<movement::Tile>,
         selected_id: Id,
-        mode: SelectionMode,
+        mode: &SelectionMode,
     ) -> ZResult {
         match mode {
             SelectionMode::Normal => self.select_normal(state, map, selected_id),
@@ -394,11 +401,12 @@ impl BattleView {
         self.show_attackable_tiles(state, id)
     }
 
-    fn select_ability(&mut self, state: &State, selected_id: Id, ability: Ability) -> ZResult {
+    fn select_ability(&mut self, state: &State, selected_id: Id, ability: &Ability) -> ZResult {
         self.remove_highlights();
         let positions = state.map().iter();
         for pos in positions {
             let id = selected_id;
+            let ability = ability.clone();
             let command = command::UseAbility { id, pos, ability }.into();
             if command_check(state, &command).is_ok() {
                 self.highlight_tile(pos, TILE_COLOR_ABILITY)?;
@@ -459,8 +467,9 @@ impl BattleView {
         if agent.moves == Moves(0) && agent.jokers == Jokers(0) {
             return Ok(());
         }
+        let move_points = state::move_points(state, &id);
         for pos in map.iter() {
-            if map.tile(pos).cost() > agent.move_points {
+            if map.tile(pos).cost() > move_points {
                 continue;
             }
             self.highlight_tile(pos, TILE_COLOR_WALKABLE)?
@@ -490,7 +499,11 @@ impl BattleView {
         let target_pos = state.parts().pos.get(&target_id).unwrap().0;
         let chances = hit_chance(state, attacker_id, target_id);
         let pos = hex_to_point(self.tile_size(), target_pos);
-        let text = format!("{}%", chances.1 * 10);
+        let text = match state::attack_side(state, &attacker_id, &target_id) {
+            state::AttackSide::Front => format!("{}%", chances.1 * 10),
+            state::AttackSide::Flank => format!("{}% (flank)", chances.1 * 10),
+            state::AttackSide::Rear => format!("{}% (rear)", chances.1 * 10),
+        };
         let font = &assets::get().font;
         let mut sprite = Sprite::from_text((text.as_str(), font), 0.1);
         sprite.set_pos(pos);
@@ -505,18 +518,28 @@ impl BattleView {
 
 fn make_action_show_tile(state: &State, view: &BattleView, at: PosHex) -> ZResult<Box<dyn Action>> {
     let screen_pos = hex_to_point(view.tile_size(), at);
-    let texture = match state.map().tile(at) {
-        TileType::Plain => &textures().map.tile,
-        TileType::Rocks => &textures().map.tile_rocks,
+    let tile = state.map().tile(at);
+    // There're no special textures for most of the tile types yet, so they're tinted plain tiles.
+    let (texture, color) = match tile {
+        TileType::Plain => (&textures().map.tile, WHITE),
+        TileType::Rocks => (&textures().map.tile_rocks, WHITE),
+        TileType::Water => (&textures().map.tile, TILE_COLOR_WATER),
+        TileType::Forest => (&textures().map.tile, TILE_COLOR_FOREST),
+        TileType::Swamp => (&textures().map.tile, TILE_COLOR_SWAMP),
+        TileType::Lava => (&textures().map.tile, TILE_COLOR_LAVA),
     };
     let size = view.tile_size() * 2.0 * geom::FLATNESS_COEFFICIENT;
     let mut sprite = Sprite::from_texture(texture, size);
     sprite.set_centered(true);
     sprite.set_pos(screen_pos);
-    Ok(action::Show::new(&view.layers().bg, &sprite).boxed())
+    sprite.set_color(color);
+    let action = action::Show::new(&view.layers().bg, &sprite).boxed();
+    if tile == TileType::Forest {
+        return Ok(visualize::seq(vec![action, make_action_grass(view, at)?]));
+    }
+    Ok(action)
 }
 
-#[allow(dead_code)]
 fn make_action_grass(view: &BattleView, at: PosHex) -> ZResult<Box<dyn Action>> {
     let screen_pos = hex_to_point(view.tile_size(), at);
     let mut sprite = Sprite::from_texture(&textures().map.grass, view.tile_size() * 2.0);
@@ -10,12 +10,12 @@ use crate::{assets, geom, screen::battle::view::BattleView, utils::time_s, ZResu
 
 use battle::{
     battle::{
-        ability::Ability,
+        ability::{Ability, PassiveAbility},
         component::{Component, WeaponType},
         effect::{self, Effect},
         event::{self, ActiveEvent, Event},
         execute::{hit_chance, ApplyPhase},
-        Id, PlayerId, State, Turns,
+        state, Id, PlayerId, State, Turns,
     },
     map::PosHex,
 };
@@ -556,7 +556,7 @@ fn visualize_event(
     event: &ActiveEvent,
 ) -> ZResult<Box<dyn Action>> {
     let action = match *event {
-        ActiveEvent::UsePassiveAbility(_) | ActiveEvent::Create => action::Empty::new().boxed(),
+        ActiveEvent::EnterTile(_) | ActiveEvent::Create => action::Empty::new().boxed(),
         ActiveEvent::MoveTo(ref ev) => visualize_event_move_to(state, view, ev)?,
         ActiveEvent::Attack(ref ev) => visualize_event_attack(state, view, ev)?,
         ActiveEvent::EndBattle(ref ev) => visualize_event_end_battle(state, view, ev)?,
@@ -565,6 +565,9 @@ fn visualize_event(
         ActiveEvent::EffectTick(ref ev) => visualize_event_effect_tick(state, view, ev)?,
         ActiveEvent::EffectEnd(ref ev) => visualize_event_effect_end(state, view, ev)?,
         ActiveEvent::UseAbility(ref ev) => visualize_event_use_ability(state, view, ev)?,
+        ActiveEvent::UsePassiveAbility(ref ev) => {
+            visualize_event_use_passive_ability(state, view, ev)?
+        }
     };
     Ok(action)
 }
@@ -634,6 +637,11 @@ fn visualize_event_attack(
     if event.mode == event::AttackMode::Reactive {
         actions.push(message(view, map_from, "reaction")?);
     }
+    match state::attack_side(state, &id, &event.target_id) {
+        state::AttackSide::Front => {}
+        state::AttackSide::Flank => actions.push(message(view, map_to, "flank")?),
+        state::AttackSide::Rear => actions.push(message(view, map_to, "rear")?),
+    }
     let time_to = time_s(TIME_LUNGE_TO);
     let time_from = time_s(TIME_LUNGE_FROM);
     let facing_opt = geom::Facing::from_positions(view.tile_size(), map_from, map_to);
@@ -658,14 +666,16 @@ fn visualize_event_attack(
 }
 
 fn visualize_event_end_battle(
-    _command_state: &State,
+    state: &State,
     view: &mut BattleView,
     event: &event::EndBattle,
 ) -> ZResult<Box<dyn Action>> {
-    let text = match event.result.winner_id {
-        PlayerId(0) => "YOU WON!",
-        PlayerId(1) => "YOU LOSE!",
-        _ => unreachable!(),
+    let text = if event.result.is_won_by(PlayerId(0)) {
+        "YOU WON!"
+    } else if !state::players_agent_ids(state, PlayerId(0)).is_empty() {
+        "OUT OF TIME!"
+    } else {
+        "YOU LOSE!"
     };
 
     Ok(seq([
@@ -687,14 +697,14 @@ fn visualize_event_end_turn(
 }
 
 fn visualize_event_begin_turn(
-    _: &State,
+    state: &State,
     view: &mut BattleView,
     event: &event::BeginTurn,
 ) -> ZResult<Box<dyn Action>> {
     let text = match event.player_id {
         PlayerId(0) => "YOUR TURN",
-        PlayerId(1) => "ENEMY TURN",
-        _ => unreachable!(),
+        id if state::are_allies(state, PlayerId(0), id) => "ALLIES' TURN",
+        _ => "ENEMY TURN",
     };
     announce(view, text, time_s(1.5), false)
 }
@@ -866,7 +876,7 @@ fn visualize_event_use_ability(
         _ => action::Empty::new().boxed(),
     };
     let pos = state.parts().pos.get(&event.id).unwrap().0;
-    let text = event.ability.title();
+    let text = state.ability_definition(&event.ability).title.clone();
     let mut actions = Vec::new();
     if let Some(facing) = geom::Facing::from_positions(view.tile_size(), pos, event.pos) {
         let sprite = view.id_to_sprite(event.id).clone();
@@ -881,16 +891,46 @@ fn visualize_event_use_ability(
     Ok(seq(actions))
 }
 
+fn visualize_event_use_passive_ability(
+    _: &State,
+    view: &mut BattleView,
+    event: &event::UsePassiveAbility,
+) -> ZResult<Box<dyn Action>> {
+    match event.ability {
+        PassiveAbility::SpawnPoisonCloudOnDeath => {
+            let color = [0.0, 0.8, 0.0, 0.7].into();
+            let scale = 1.5;
+            let time = time_s(TIME_DEFAULT_FLARE);
+            let action_flare = show_flare_scale_time(view, event.pos, color, scale, time)?;
+            Ok(seq([
+                fork(action_flare),
+                message(view, event.pos, "poison cloud")?,
+            ]))
+        }
+        _ => Ok(action::Empty::new().boxed()),
+    }
+}
+
 fn visualize_event_effect_tick(
     state: &State,
     view: &mut BattleView,
     event: &event::EffectTick,
 ) -> ZResult<Box<dyn Action>> {
     let pos = state.parts().pos.get(&event.id).unwrap().0;
-    match event.effect {
-        effect::Lasting::Poison => show_flare(view, pos, [0.0, 0.8, 0.0, 0.7].into()),
-        effect::Lasting::Stun => show_flare(view, pos, [1.0, 1.0, 1.0, 0.7].into()),
-        effect::Lasting::Bloodlust => show_flare(view, pos, [1.0, 0.0, 0.0, 0.5].into()),
+    show_flare(view, pos, lasting_effect_color(event.effect))
+}
+
+fn lasting_effect_color(effect: effect::Lasting) -> Color {
+    match effect {
+        effect::Lasting::Poison => [0.0, 0.8, 0.0, 0.7].into(),
+        effect::Lasting::Stun => [1.0, 1.0, 1.0, 0.7].into(),
+        effect::Lasting::Bloodlust => [1.0, 0.0, 0.0, 0.5].into(),
+        effect::Lasting::Burning => [1.0, 0.6, 0.1, 0.7].into(),
+        effect::Lasting::Slowed => [0.5, 0.7, 1.0, 0.7].into(),
+        effect::Lasting::Rooted => [0.7, 0.4, 0.2, 0.7].into(),
+        effect::Lasting::Shielded => [0.7, 0.8, 0.9, 0.7].into(),
+        effect::Lasting::Blinded => [0.7, 0.5, 1.0, 0.7].into(),
+        effect::Lasting::Taunted => [1.0, 0.3, 0.3, 0.7].into(),
     }
 }
 
@@ -911,11 +951,7 @@ fn visualize_lasting_effect(
     timed_effect: &effect::Timed,
 ) -> ZResult<Box<dyn Action>> {
     let pos = state.parts().pos.get(&target_id).unwrap().0;
-    let action_flare = match timed_effect.effect {
-        effect::Lasting::Poison => show_flare(view, pos, [0.0, 0.8, 0.0, 0.7].into())?,
-        effect::Lasting::Stun => show_flare(view, pos, [1.0, 1.0, 1.0, 0.7].into())?,
-        effect::Lasting::Bloodlust => show_flare(view, pos, [1.0, 0.0, 0.0, 0.5].into())?,
-    };
+    let action_flare = show_flare(view, pos, lasting_effect_color(timed_effect.effect))?;
     let s = timed_effect.effect.title();
     Ok(seq([
         action_flare,
@@ -940,13 +976,14 @@ fn visualize_instant_effect(
         Effect::FlyOff(ref e) => visualize_effect_fly_off(state, view, target_id, e)?,
         Effect::Throw(ref e) => visualize_effect_throw(state, view, target_id, e)?,
         Effect::Dodge(ref e) => visualize_effect_dodge(state, view, target_id, e)?,
+        Effect::Collision(ref e) => visualize_effect_collision(state, view, target_id, e)?,
         Effect::Bloodlust => action::Empty.boxed(),
     };
     Ok(action)
 }
 
 fn visualize_effect_create(
-    _: &State,
+    state: &State,
     view: &mut BattleView,
     target_id: Id,
     effect: &effect::Create,
@@ -963,7 +1000,7 @@ fn visualize_effect_create(
         // Turn enemies left.
         for component in &effect.components {
             if let Component::BelongsTo(belongs_to) = component {
-                if belongs_to.0 == PlayerId(1) {
+                if !state::are_allies(state, PlayerId(0), belongs_to.0) {
                     sprite.set_facing(Facing::Left);
                 }
             }
@@ -1044,10 +1081,12 @@ fn visualize_effect_heal(
 
 fn wound_msg(effect: &effect::Wound) -> String {
     let damage = effect.damage.0;
-    if damage > 0 {
-        format!("-{} strength ", damage)
-    } else {
-        "no damage".into()
+    let absorbed = effect.absorbed.0;
+    match (damage > 0, absorbed > 0) {
+        (true, true) => format!("-{} strength ({} absorbed)", damage, absorbed),
+        (true, false) => format!("-{} strength ", damage),
+        (false, true) => format!("{} absorbed", absorbed),
+        (false, false) => "no damage".into(),
     }
 }
 
@@ -1127,6 +1166,19 @@ fn visualize_effect_fly_off(
     ])))
 }
 
+fn visualize_effect_collision(
+    state: &State,
+    view: &mut BattleView,
+    target_id: Id,
+    _: &effect::Collision,
+) -> ZResult<Box<dyn Action>> {
+    let pos = state.parts().pos.get(&target_id).unwrap().0;
+    Ok(seq([
+        message(view, pos, "crash")?,
+        show_dust_at_pos(view, pos)?,
+    ]))
+}
+
 fn visualize_effect_throw(
     _: &State,
     view: &mut BattleView,
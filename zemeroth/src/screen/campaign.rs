@@ -1,29 +1,22 @@
 use std::{
-    collections::HashMap,
     sync::mpsc::{channel, Receiver},
     time::Duration,
 };
 
 use heck::ToTitleCase;
-use log::info;
+use log::{error, info};
 use mq::{math::Vec2, text::Font};
 use ui::{self, Drawable, Gui, Widget};
 
 use crate::{
-    assets,
+    assets, save,
     screen::{self, Screen, StackCommand},
     utils, ZResult,
 };
 
 use battle::{
-    battle::{
-        component::ObjType,
-        heroes::{Hero, HeroObject},
-        scenario::BattleType,
-        state::BattleResult,
-    },
-    campaign::{Action, Mode, State},
-    game::Level,
+    battle::{component::ObjType, heroes::Perk, scenario::BattleType, state::BattleResult},
+    campaign::{Action, Award, CampaignNode, Event, Fighter, Mode, NodeId, State},
 };
 
 #[derive(Clone, Debug)]
@@ -32,7 +25,9 @@ enum Message {
     StartBattle,
     AgentInfo(ObjType),
     UpgradeInfo { from: ObjType, to: ObjType },
+    PerkInfo(Perk),
     Action(Action),
+    GoTo(NodeId),
 }
 
 // The main line height of this screen.
@@ -58,18 +53,63 @@ fn basic_gui() -> ZResult<Gui<Message>> {
     Ok(gui)
 }
 
+/// "Bran the Swordsman", so the veterans are told apart from their types.
+fn fighter_title(fighter: &Fighter) -> String {
+    format!(
+        "{} the {}",
+        fighter.name,
+        fighter.typename.0.to_title_case()
+    )
+}
+
+fn node_title(node: &CampaignNode) -> String {
+    let kind = match node.event {
+        Event::Battle { .. } => "battle",
+        Event::Shop { .. } => "shop",
+        Event::Recruits { .. } => "recruits",
+        Event::Choice { .. } => "choice",
+    };
+    format!("{} ({})", node.title, kind)
+}
+
+fn award_text(award: &Award) -> String {
+    let mut parts = Vec::new();
+    if award.renown.0 > 0 {
+        parts.push(format!("{}r", award.renown.0));
+    }
+    for recruit in &award.recruits {
+        parts.push(format!("a free {}", recruit.0.to_title_case()));
+    }
+    format!("Take {}", parts.join(" and "))
+}
+
 fn build_panel_agents(
     gui: &mut ui::Gui<Message>,
-    agents: &[ObjType],
+    fighters: &[Fighter],
 ) -> ZResult<Box<dyn ui::Widget>> {
     let font = &assets::get().font;
     let mut layout = Box::new(ui::VLayout::new().stretchable(true));
     layout.add(label(font, "Your group consists of:")?);
     layout.add(Box::new(ui::Spacer::new_vertical(line_height_small())));
-    for agent_type in agents {
+    for fighter in fighters {
+        let agent_type = &fighter.typename;
         let mut line = ui::HLayout::new().stretchable(true);
-        let title = agent_type.0.to_title_case();
-        line.add(label(font, &format!("- {}", title))?);
+        let mut text = format!(
+            "- {} (kills: {}, battles: {})",
+            fighter_title(fighter),
+            fighter.kills,
+            fighter.battles
+        );
+        if !fighter.perks.is_empty() {
+            let perks: Vec<_> = fighter.perks.iter().map(|perk| perk.title()).collect();
+            text += &format!(" [{}]", perks.join(", "));
+        }
+        if fighter.is_resting {
+            text += " - resting";
+        } else if fighter.wounds.0 > 0 {
+            text += &format!(" - wounded: -{} strength", fighter.wounds.0);
+        }
+        line.add(label(font, &text)?);
         let spacer = ui::Spacer::new_horizontal(line_height_small()).stretchable(true);
         line.add(Box::new(spacer));
         {
@@ -89,16 +129,32 @@ fn build_panel_agents(
 fn build_panel_casualties(state: &State) -> ZResult<Option<Box<dyn ui::Widget>>> {
     let font = &assets::get().font;
     let casualties = state.last_battle_casualties();
-    if casualties.is_empty() {
+    let injured: Vec<_> = state
+        .fighters()
+        .iter()
+        .filter(|fighter| fighter.wounds.0 > 0)
+        .collect();
+    if casualties.is_empty() && injured.is_empty() {
         return Ok(None);
     }
     let mut layout = Box::new(ui::VLayout::new());
-    let section_title = "In the last battle you have lost:";
-    layout.add(label(font, section_title)?);
-    for agent_type in casualties {
-        let text = &format!("- {}", agent_type.0.to_title_case());
-        layout.add(label(font, text)?);
-        layout.add(Box::new(ui::Spacer::new_vertical(line_height_small())));
+    if !casualties.is_empty() {
+        let section_title = "In the last battle you have lost:";
+        layout.add(label(font, section_title)?);
+        for fighter in casualties {
+            let text = &format!("- {}", fighter_title(fighter));
+            layout.add(label(font, text)?);
+            layout.add(Box::new(ui::Spacer::new_vertical(line_height_small())));
+        }
+    }
+    if !injured.is_empty() {
+        layout.add(label(font, "Injured:")?);
+        for fighter in injured {
+            let title = fighter_title(fighter);
+            let text = &format!("- {} (-{} strength)", title, fighter.wounds.0);
+            layout.add(label(font, text)?);
+            layout.add(Box::new(ui::Spacer::new_vertical(line_height_small())));
+        }
     }
     let layout = utils::add_offsets_and_bg_big(layout)?.stretchable(true);
     Ok(Some(Box::new(layout)))
@@ -127,15 +183,27 @@ fn build_panel_actions(gui: &mut ui::Gui<Message>, state: &State) -> ZResult<Box
                 let title = agent_type.0.to_title_case();
                 format!("Recruit {} for {}r", title, action_cost.0)
             }
-            Action::Upgrade { from, to } => {
-                if state.agents().contains(from) {
-                    let from = from.0.to_title_case();
+            Action::Upgrade { id, from, to } => match state.fighter(*id) {
+                Some(fighter) if fighter.typename == *from => {
                     let to = to.0.to_title_case();
-                    format!("Upgrade {} to {} for {}r", from, to, action_cost.0)
-                } else {
-                    continue;
+                    let title = fighter_title(fighter);
+                    format!("Upgrade {} to {} for {}r", title, to, action_cost.0)
                 }
-            }
+                _ => continue,
+            },
+            Action::ChoosePerk { id, perk } => match state.fighter(*id) {
+                Some(fighter) => format!("Make {} {}", fighter.name, perk.title()),
+                None => continue,
+            },
+            Action::Rest { id } => match state.fighter(*id) {
+                Some(fighter) => format!("Let {} rest for a battle", fighter.name),
+                None => continue,
+            },
+            Action::Heal { id } => match state.fighter(*id) {
+                Some(fighter) => format!("Heal {} for {}r", fighter.name, action_cost.0),
+                None => continue,
+            },
+            Action::ChooseReward { award } => award_text(award),
         };
         {
             let text = ui::Drawable::text(text, font.clone());
@@ -147,13 +215,20 @@ fn build_panel_actions(gui: &mut ui::Gui<Message>, state: &State) -> ZResult<Box
             }
             line.add(Box::new(button));
         }
-        line.add(Box::new(ui::Spacer::new_horizontal(line_height_small())));
-        {
+        let message = match action.clone() {
+            Action::Recruit { agent_type, .. } => Some(Message::AgentInfo(agent_type)),
+            Action::Upgrade { from, to, .. } => Some(Message::UpgradeInfo { from, to }),
+            Action::ChoosePerk { perk, .. } => Some(Message::PerkInfo(perk)),
+            Action::Rest { id } | Action::Heal { id } => state
+                .fighter(id)
+                .map(|fighter| Message::AgentInfo(fighter.typename.clone())),
+            Action::ChooseReward { award } => {
+                award.recruits.first().cloned().map(Message::AgentInfo)
+            }
+        };
+        if let Some(message) = message {
+            line.add(Box::new(ui::Spacer::new_horizontal(line_height_small())));
             let icon = Drawable::Texture(assets::get().textures.icons.info.clone());
-            let message = match action.clone() {
-                Action::Recruit { agent_type, .. } => Message::AgentInfo(agent_type),
-                Action::Upgrade { from, to } => Message::UpgradeInfo { from, to },
-            };
             let sender = gui.sender();
             let button = ui::Button::new(icon, h, sender, message)?;
             line.add(Box::new(button));
@@ -161,12 +236,8 @@ fn build_panel_actions(gui: &mut ui::Gui<Message>, state: &State) -> ZResult<Box
         layout.add(Box::new(line));
         layout.add(Box::new(ui::Spacer::new_vertical(line_height_small())));
     }
-    {
-        let text = &format!(
-            "Start battle - {}/{}",
-            state.current_scenario_index() + 1,
-            state.scenarios_count()
-        );
+    if state.mode() == Mode::PreparingForBattle {
+        let text = &format!("Start battle: {}", state.current_node().title);
         let text = ui::Drawable::text(text, font.clone());
         let command = Message::StartBattle;
         let button = ui::Button::new(text, h, gui.sender(), command)?.stretchable(true);
@@ -177,6 +248,25 @@ fn build_panel_actions(gui: &mut ui::Gui<Message>, state: &State) -> ZResult<Box
     Ok(Box::new(layout))
 }
 
+/// The campaign map: the nodes the player can go to next.
+fn build_panel_map(gui: &mut ui::Gui<Message>, state: &State) -> ZResult<Box<dyn ui::Widget>> {
+    let font = &assets::get().font;
+    let h = line_height();
+    let mut layout = Box::new(ui::VLayout::new().stretchable(true));
+    layout.add(label(font, "Where to go next:")?);
+    layout.add(Box::new(ui::Spacer::new_vertical(line_height_small())));
+    for &id in state.next_nodes() {
+        let text = ui::Drawable::text(node_title(state.node(id)), font.clone());
+        let message = Message::GoTo(id);
+        let button = ui::Button::new(text, h, gui.sender(), message)?.stretchable(true);
+        layout.add(Box::new(button));
+        layout.add(Box::new(ui::Spacer::new_vertical(line_height_small())));
+    }
+    layout.stretch_to_self();
+    let layout = utils::add_offsets_and_bg_big(layout)?.stretchable(true);
+    Ok(Box::new(layout))
+}
+
 fn label(font: &Font, text: &str) -> ZResult<Box<dyn ui::Widget>> {
     let text = ui::Drawable::text(text, font.clone());
     Ok(Box::new(ui::Label::new(text, line_height())?))
@@ -184,6 +274,7 @@ fn label(font: &Font, text: &str) -> ZResult<Box<dyn ui::Widget>> {
 
 #[derive(Debug)]
 pub struct Campaign {
+    slot: &'static str,
     state: State,
     receiver_battle_result: Option<Receiver<Option<BattleResult>>>,
     receiver_exit_confirmation: Option<Receiver<screen::confirm::Message>>,
@@ -193,12 +284,25 @@ pub struct Campaign {
 }
 
 impl Campaign {
-    pub fn new() -> ZResult<Self> {
+    /// Starts a new campaign, overwriting the slot's old save.
+    pub fn new(slot: &'static str) -> ZResult<Self> {
         let campaign_plan = assets::get().campaign_plan.clone();
         let agent_campaign_info = assets::get().agent_campaign_info.clone();
         let state = State::new(campaign_plan, agent_campaign_info);
+        let this = Self::from_state(slot, state)?;
+        this.autosave();
+        Ok(this)
+    }
+
+    pub fn load(slot: &'static str) -> ZResult<Self> {
+        Self::from_state(slot, save::load(slot)?)
+    }
+
+    fn from_state(slot: &'static str, state: State) -> ZResult<Self> {
         let gui = basic_gui()?;
+        let mode = state.mode();
         let mut this = Self {
+            slot,
             gui,
             state,
             receiver_battle_result: None,
@@ -206,14 +310,21 @@ impl Campaign {
             layout: None,
             label_central_message: None,
         };
-        this.set_mode(Mode::PreparingForBattle)?;
+        this.set_mode(mode)?;
         Ok(this)
     }
 
+    /// A failed save shouldn't stop the game, so it's only logged.
+    fn autosave(&self) {
+        if let Err(err) = save::save(self.slot, &self.state) {
+            error!("Campaign: Can't save to the '{}' slot: {}", self.slot, err);
+        }
+    }
+
     fn set_mode(&mut self, mode: Mode) -> ZResult {
         self.clean_ui()?;
         match mode {
-            Mode::PreparingForBattle => self.set_mode_preparing()?,
+            Mode::PreparingForBattle | Mode::ChoosingNextNode => self.set_mode_preparing()?,
             Mode::Won => self.set_mode_won()?,
             Mode::Failed => self.set_mode_failed()?,
         }
@@ -230,12 +341,16 @@ impl Campaign {
             layout.add(Box::new(ui::Spacer::new_vertical(line_height())));
         }
         let mut line = ui::HLayout::new().stretchable(true);
-        line.add(build_panel_agents(gui, state.agents())?);
+        line.add(build_panel_agents(gui, state.fighters())?);
         line.add(Box::new(ui::Spacer::new_horizontal(line_height())));
         line.add(build_panel_renown(state)?);
         layout.add(Box::new(line));
         layout.add(Box::new(ui::Spacer::new_vertical(line_height())));
         layout.add(build_panel_actions(gui, state)?);
+        if state.mode() == Mode::ChoosingNextNode {
+            layout.add(Box::new(ui::Spacer::new_vertical(line_height())));
+            layout.add(build_panel_map(gui, state)?);
+        }
         layout.stretch_to_self();
         let anchor = ui::Anchor(ui::HAnchor::Middle, ui::VAnchor::Middle);
         let layout = ui::pack(layout);
@@ -270,26 +385,22 @@ impl Campaign {
     }
 
     fn start_battle(&mut self) -> ZResult<Box<dyn Screen>> {
-        let mut heroes = HashMap::new();
-        for typename in self.state.agents() {
-            let h = heroes.entry(typename.0.clone()).or_insert(0);
-            *h += 1;
-        }
-
-        let mut heroes_create = vec![];
-        for x in heroes {
-            let hero = Hero::from_name(&x.0);
-            heroes_create.push(HeroObject::new(hero, x.1))
-        }
+        let heroes_create = self.state.heroes();
 
         let (sender, receiver) = channel();
         self.receiver_battle_result = Some(receiver);
         //let prototypes = assets::get().prototypes.clone();
         let battle_type = BattleType::CampaignNode;
 
-        let level = Level::from_index(self.state.current_scenario_index());
+        let scenario = self.state.scenario().clone();
 
-        let screen = screen::Battle::new(&heroes_create, battle_type, level, sender, [0; 32])?;
+        let screen = screen::Battle::new(
+            &heroes_create,
+            battle_type,
+            scenario,
+            sender,
+            rand::random(),
+        )?;
         Ok(Box::new(screen))
     }
 }
@@ -301,6 +412,7 @@ impl Screen for Campaign {
                 self.state
                     .report_battle_results(&result)
                     .expect("Campaign: Can't report battle results");
+                self.autosave();
                 let new_mode = self.state.mode();
                 self.set_mode(new_mode)?;
             } else {
@@ -336,17 +448,27 @@ impl Screen for Campaign {
                 let cost = self.state.action_cost(&action);
                 if cost.0 <= self.state.renown().0 {
                     self.state.execute_action(action);
+                    self.autosave();
                     let new_mode = self.state.mode();
                     self.set_mode(new_mode)?;
                 }
                 Ok(StackCommand::None)
             }
+            Some(Message::GoTo(id)) => {
+                self.state.choose_next_node(id);
+                self.autosave();
+                let new_mode = self.state.mode();
+                self.set_mode(new_mode)?;
+                Ok(StackCommand::None)
+            }
             Some(Message::Menu) => {
                 // Ask only if the player hasn't won or failed, otherwise just pop the screen.
-                if self.state.mode() == Mode::PreparingForBattle {
+                let mode = self.state.mode();
+                if mode == Mode::PreparingForBattle || mode == Mode::ChoosingNextNode {
                     let (sender, receiver) = channel();
                     self.receiver_exit_confirmation = Some(receiver);
-                    let screen = screen::Confirm::from_line("Abandon the campaign?", sender)?;
+                    let screen =
+                        screen::Confirm::from_line("Leave the campaign? It's saved.", sender)?;
                     Ok(StackCommand::PushPopup(Box::new(screen)))
                 } else {
                     Ok(StackCommand::Pop)
@@ -362,6 +484,11 @@ impl Screen for Campaign {
                 let popup = screen::AgentInfo::new_upgrade_info(prototypes, &from, &to)?;
                 Ok(StackCommand::PushPopup(Box::new(popup)))
             }
+            Some(Message::PerkInfo(perk)) => {
+                let description = [perk.description().to_owned()];
+                let popup = screen::GeneralInfo::new(perk.title(), &description)?;
+                Ok(StackCommand::PushPopup(Box::new(popup)))
+            }
             None => Ok(StackCommand::None),
         }
     }
@@ -1,19 +1,32 @@
 use mq::math::Vec2;
-use std::time::Duration;
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
 use ui::{self, Widget};
 
 use crate::{
-    assets,
-    screen::{self, Screen, StackCommand},
-    utils, ZResult,
+    assets, save,
+    screen::{self, Screen, SlotPurpose, StackCommand},
+    transcript, utils, ZResult,
 };
 
+/// The older transcripts wouldn't fit on the screen.
+const TRANSCRIPTS_SHOWN: usize = 10;
+
 #[derive(Copy, Clone, Debug)]
 enum Message {
     #[cfg_attr(target_arch = "wasm32", allow(unused))] // can't quit WASM so it's not used there
     Exit,
 
+    ContinueCampaign,
+
     StartCampaign,
+
+    LoadCampaign,
+
+    ReplayTranscript,
 }
 
 fn make_gui() -> ZResult<ui::Gui<Message>> {
@@ -29,7 +42,13 @@ fn make_gui() -> ZResult<ui::Gui<Message>> {
     let mut layout = Box::new(ui::VLayout::new().stretchable(true));
     // layout.add(button("demo battle", Message::StartInstant)?);
     layout.add(space());
-    layout.add(button("campaign", Message::StartCampaign)?);
+    layout.add(button("continue", Message::ContinueCampaign)?);
+    layout.add(space());
+    layout.add(button("new campaign", Message::StartCampaign)?);
+    layout.add(space());
+    layout.add(button("load campaign", Message::LoadCampaign)?);
+    layout.add(space());
+    layout.add(button("replay", Message::ReplayTranscript)?);
     #[cfg(not(target_arch = "wasm32"))] // can't quit WASM
     {
         layout.add(space());
@@ -45,18 +64,123 @@ fn make_gui() -> ZResult<ui::Gui<Message>> {
 #[derive(Debug)]
 pub struct MainMenu {
     gui: ui::Gui<Message>,
+    receiver_new_campaign_slot: Option<Receiver<&'static str>>,
+    receiver_overwrite_confirmation: Option<Receiver<screen::confirm::Message>>,
+
+    /// The slot waiting for the overwrite confirmation.
+    overwritten_slot: Option<&'static str>,
+
+    receiver_load_slot: Option<Receiver<&'static str>>,
+    receiver_transcript: Option<Receiver<usize>>,
+    transcripts: Vec<PathBuf>,
 }
 
 // TODO: add the game's version to one of the corners
 impl MainMenu {
     pub fn new() -> ZResult<Self> {
         let gui = make_gui()?;
-        Ok(Self { gui })
+        Ok(Self {
+            gui,
+            receiver_new_campaign_slot: None,
+            receiver_overwrite_confirmation: None,
+            overwritten_slot: None,
+            receiver_load_slot: None,
+            receiver_transcript: None,
+            transcripts: Vec::new(),
+        })
+    }
+
+    fn pick_slot(&mut self, purpose: SlotPurpose) -> ZResult<StackCommand> {
+        let (sender, receiver) = channel();
+        match purpose {
+            SlotPurpose::NewCampaign => self.receiver_new_campaign_slot = Some(receiver),
+            SlotPurpose::Load => self.receiver_load_slot = Some(receiver),
+        }
+        let popup = screen::Slots::new(purpose, sender)?;
+        Ok(StackCommand::PushPopup(Box::new(popup)))
+    }
+
+    fn start_campaign(&self, slot: &'static str) -> ZResult<StackCommand> {
+        let screen = screen::Campaign::new(slot)?;
+        Ok(StackCommand::PushScreen(Box::new(screen)))
+    }
+
+    /// A broken save is reported to the player instead of stopping the game.
+    fn load_campaign(&self, slot: &'static str) -> ZResult<StackCommand> {
+        match screen::Campaign::load(slot) {
+            Ok(screen) => Ok(StackCommand::PushScreen(Box::new(screen))),
+            Err(err) => {
+                let lines = [err.to_string()];
+                let popup = screen::GeneralInfo::new("can't load the campaign", &lines)?;
+                Ok(StackCommand::PushPopup(Box::new(popup)))
+            }
+        }
+    }
+
+    fn continue_campaign(&self) -> ZResult<StackCommand> {
+        match save::latest() {
+            Some(slot) => self.load_campaign(slot),
+            None => {
+                let lines = ["No campaigns in progress found.".to_owned()];
+                let popup = screen::GeneralInfo::new("continue", &lines)?;
+                Ok(StackCommand::PushPopup(Box::new(popup)))
+            }
+        }
+    }
+
+    fn pick_transcript(&mut self) -> ZResult<StackCommand> {
+        self.transcripts = transcript::recent(TRANSCRIPTS_SHOWN);
+        if self.transcripts.is_empty() {
+            let lines = ["No saved transcripts found.".to_owned()];
+            let popup = screen::GeneralInfo::new("replay", &lines)?;
+            return Ok(StackCommand::PushPopup(Box::new(popup)));
+        }
+        let items: Vec<_> = self
+            .transcripts
+            .iter()
+            .map(|path| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                (name.into_owned(), true)
+            })
+            .collect();
+        let (sender, receiver) = channel();
+        self.receiver_transcript = Some(receiver);
+        let popup = screen::Picker::new("Pick a transcript to replay:", &items, sender)?;
+        Ok(StackCommand::PushPopup(Box::new(popup)))
+    }
+
+    fn replay_transcript(&self, path: &Path) -> ZResult<StackCommand> {
+        let input = transcript::load(path)?;
+        let screen = screen::Replay::new(input)?;
+        Ok(StackCommand::PushScreen(Box::new(screen)))
     }
 }
 
 impl Screen for MainMenu {
     fn update(&mut self, _: Duration) -> ZResult<StackCommand> {
+        if let Some(slot) = utils::try_receive(&self.receiver_new_campaign_slot) {
+            if !save::exists(slot) {
+                return self.start_campaign(slot);
+            }
+            let (sender, receiver) = channel();
+            self.receiver_overwrite_confirmation = Some(receiver);
+            self.overwritten_slot = Some(slot);
+            let line = format!("The {} slot is taken. Overwrite it?", slot);
+            let popup = screen::Confirm::from_line(&line, sender)?;
+            return Ok(StackCommand::PushPopup(Box::new(popup)));
+        }
+        if screen::confirm::try_receive_yes(&self.receiver_overwrite_confirmation) {
+            if let Some(slot) = self.overwritten_slot.take() {
+                return self.start_campaign(slot);
+            }
+        }
+        if let Some(slot) = utils::try_receive(&self.receiver_load_slot) {
+            return self.load_campaign(slot);
+        }
+        if let Some(i) = utils::try_receive(&self.receiver_transcript) {
+            let path = self.transcripts[i].clone();
+            return self.replay_transcript(&path);
+        }
         Ok(StackCommand::None)
     }
 
@@ -83,10 +207,10 @@ impl Screen for MainMenu {
             //     )?;
             //     Ok(StackCommand::PushScreen(Box::new(screen)))
             // }
-            Some(Message::StartCampaign) => {
-                let screen = screen::Campaign::new()?;
-                Ok(StackCommand::PushScreen(Box::new(screen)))
-            }
+            Some(Message::ContinueCampaign) => self.continue_campaign(),
+            Some(Message::StartCampaign) => self.pick_slot(SlotPurpose::NewCampaign),
+            Some(Message::LoadCampaign) => self.pick_slot(SlotPurpose::Load),
+            Some(Message::ReplayTranscript) => self.pick_transcript(),
             Some(Message::Exit) => Ok(StackCommand::Pop),
             None => Ok(StackCommand::None),
         }
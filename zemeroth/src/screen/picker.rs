@@ -0,0 +1,91 @@
+use std::{sync::mpsc::Sender, time::Duration};
+
+use mq::math::Vec2;
+use ui::{self, Gui, Widget};
+
+use crate::{
+    assets,
+    screen::{Screen, StackCommand},
+    utils, ZResult,
+};
+
+#[derive(Clone, Copy, Debug)]
+enum Message {
+    Pick(usize),
+    Back,
+}
+
+/// A list of buttons that reports back the index of the picked one.
+#[derive(Debug)]
+pub struct Picker {
+    gui: Gui<Message>,
+    sender: Sender<usize>,
+}
+
+impl Picker {
+    /// Items are `(text, is_active)` pairs.
+    pub fn new(title: &str, items: &[(String, bool)], sender: Sender<usize>) -> ZResult<Self> {
+        let font = &assets::get().font;
+        let mut gui = ui::Gui::new();
+        let h = utils::line_heights().normal;
+        let mut layout = Box::new(ui::VLayout::new().stretchable(true));
+        let text = |s: &str| ui::Drawable::text(s, font.clone());
+        let spacer = || Box::new(ui::Spacer::new_vertical(h * 0.5));
+        layout.add(Box::new(ui::Label::new(text(title), h)?));
+        layout.add(spacer());
+        for (i, (line, is_active)) in items.iter().enumerate() {
+            let message = Message::Pick(i);
+            let mut button =
+                ui::Button::new(text(line), h, gui.sender(), message)?.stretchable(true);
+            button.set_active(*is_active);
+            layout.add(Box::new(button));
+            layout.add(Box::new(ui::Spacer::new_vertical(h / 8.0)));
+        }
+        layout.add(spacer());
+        {
+            let mut button =
+                ui::Button::new(text("back"), h, gui.sender(), Message::Back)?.stretchable(true);
+            button.stretch(layout.rect().w / 3.0);
+            button.set_stretchable(false);
+            layout.add(Box::new(button));
+        }
+        layout.stretch_to_self();
+        let layout = utils::add_offsets_and_bg_big(layout)?;
+        let anchor = ui::Anchor(ui::HAnchor::Middle, ui::VAnchor::Middle);
+        gui.add(&ui::pack(layout), anchor);
+        Ok(Self { gui, sender })
+    }
+}
+
+impl Screen for Picker {
+    fn update(&mut self, _dtime: Duration) -> ZResult<StackCommand> {
+        Ok(StackCommand::None)
+    }
+
+    fn draw(&self) -> ZResult {
+        self.gui.draw();
+        Ok(())
+    }
+
+    fn click(&mut self, pos: Vec2) -> ZResult<StackCommand> {
+        match self.gui.click(pos) {
+            Some(Message::Pick(i)) => {
+                self.sender
+                    .send(i)
+                    .expect("Can't report back the picked item");
+                Ok(StackCommand::Pop)
+            }
+            Some(Message::Back) => Ok(StackCommand::Pop),
+            None => Ok(StackCommand::None),
+        }
+    }
+
+    fn resize(&mut self, aspect_ratio: f32) {
+        self.gui.resize_if_needed(aspect_ratio);
+    }
+
+    fn move_mouse(&mut self, pos: Vec2) -> ZResult {
+        self.gui.move_mouse(pos);
+        Ok(())
+    }
+}
@@ -0,0 +1,355 @@
+use std::time::Duration;
+
+use log::warn;
+use mq::math::Vec2;
+use ui::{self, Gui, Widget};
+use zscene::{action, Action, Boxed};
+
+use battle::{
+    battle::{
+        ai::Ais,
+        check::check_strict,
+        command::{self, Command},
+        execute, PlayerId, State,
+    },
+    game::Input,
+    utils::SimpleRng,
+};
+
+use crate::{
+    assets, geom,
+    screen::{
+        battle::{
+            view::{make_action_create_map, BattleView},
+            visualize::{fork, visualize},
+        },
+        Screen, StackCommand,
+    },
+    utils::{self, line_heights},
+    ZResult,
+};
+
+/// A tick long enough to finish any single action.
+const SKIP_TICK: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Debug)]
+enum Message {
+    Exit,
+    Play,
+    Pause,
+    Step,
+    JumpToRound(usize),
+}
+
+/// What the next step of the replay is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Phase {
+    /// The index of the next recorded command of the current round.
+    /// The recorded commands are followed by the player's end of the turn.
+    Player(usize),
+
+    /// The AIs are replying to the current round one by one.
+    Ai,
+
+    /// The transcript is over or the battle has ended.
+    Done,
+}
+
+fn make_gui() -> ZResult<Gui<Message>> {
+    let mut gui = ui::Gui::new();
+    let h = line_heights().large;
+    let icon = assets::get().textures.icons.main_menu.clone();
+    let button = ui::Button::new(ui::Drawable::Texture(icon), h, gui.sender(), Message::Exit)?;
+    let layout = ui::VLayout::from_widget(Box::new(button));
+    let anchor = ui::Anchor(ui::HAnchor::Left, ui::VAnchor::Top);
+    gui.add(&ui::pack(layout), anchor);
+    Ok(gui)
+}
+
+fn build_panel_controls(
+    gui: &mut Gui<Message>,
+    round: usize,
+    rounds_count: usize,
+    phase: Phase,
+    is_playing: bool,
+) -> ZResult<ui::RcWidget> {
+    let font = assets::get().font.clone();
+    let h = line_heights().big;
+    let text = |s: &str| ui::Drawable::text(s, font.clone());
+    let button = |label: &str, message| -> ZResult<Box<dyn Widget>> {
+        let button = ui::Button::new(text(label), h, gui.sender(), message)?;
+        Ok(Box::new(button))
+    };
+    let space = || Box::new(ui::Spacer::new_horizontal(h / 4.0));
+    let mut layout = ui::HLayout::new();
+    let round_label = if phase == Phase::Done {
+        "the end".to_owned()
+    } else {
+        format!("round {}/{}", round + 1, rounds_count)
+    };
+    layout.add(Box::new(ui::Label::new(text(&round_label), h)?));
+    layout.add(space());
+    layout.add(button("<", Message::JumpToRound(round.saturating_sub(1)))?);
+    layout.add(space());
+    layout.add(button(">", Message::JumpToRound(round + 1))?);
+    layout.add(space());
+    if is_playing {
+        layout.add(button("pause", Message::Pause)?);
+    } else {
+        layout.add(button("play", Message::Play)?);
+        layout.add(space());
+        layout.add(button("step", Message::Step)?);
+    }
+    let layout = utils::add_offsets_and_bg(Box::new(layout), utils::OFFSET_SMALL)?;
+    let anchor = ui::Anchor(ui::HAnchor::Middle, ui::VAnchor::Bottom);
+    let packed_layout = ui::pack(layout);
+    gui.add(&packed_layout, anchor);
+    Ok(packed_layout)
+}
+
+/// Replays a recorded battle command by command, including the AI's replies.
+///
+/// The commands are validated the same way the guest does it,
+/// so the replay stops at the first command the guest would reject.
+#[derive(Debug)]
+pub struct Replay {
+    gui: Gui<Message>,
+    input: Input,
+    state: State,
+    view: BattleView,
+    rng: SimpleRng,
+    ais: Ais,
+    round: usize,
+    phase: Phase,
+    is_playing: bool,
+    panel_controls: Option<ui::RcWidget>,
+}
+
+impl Replay {
+    pub fn new(input: Input) -> ZResult<Self> {
+        let (state, view, rng) = start(&input)?;
+        let mut replay = Self {
+            gui: make_gui()?,
+            ais: Ais::new(input.scenario.players_count),
+            input,
+            state,
+            view,
+            rng,
+            round: 0,
+            phase: Phase::Player(0),
+            is_playing: false,
+            panel_controls: None,
+        };
+        replay.update_phase();
+        replay.rebuild_panel_controls()?;
+        Ok(replay)
+    }
+
+    fn restart(&mut self) -> ZResult {
+        let (state, view, rng) = start(&self.input)?;
+        self.state = state;
+        self.view = view;
+        self.rng = rng;
+        self.ais = Ais::new(self.input.scenario.players_count);
+        self.round = 0;
+        self.phase = Phase::Player(0);
+        self.update_phase();
+        Ok(())
+    }
+
+    fn rebuild_panel_controls(&mut self) -> ZResult {
+        utils::remove_widget(&mut self.gui, &mut self.panel_controls)?;
+        let panel = build_panel_controls(
+            &mut self.gui,
+            self.round,
+            self.input.commands.len(),
+            self.phase,
+            self.is_playing,
+        )?;
+        self.panel_controls = Some(panel);
+        Ok(())
+    }
+
+    fn update_phase(&mut self) {
+        let is_transcript_over =
+            matches!(self.phase, Phase::Player(_)) && self.round >= self.input.commands.len();
+        if self.state.battle_result().is_some() || is_transcript_over {
+            self.phase = Phase::Done;
+        }
+    }
+
+    fn end_round(&mut self) {
+        self.round += 1;
+        self.phase = Phase::Player(0);
+        self.update_phase();
+    }
+
+    /// Passes the turn to the next AI or starts the next round.
+    fn pass_turn(&mut self) {
+        let player_id = self.state.player_id();
+        if player_id == PlayerId(0) {
+            self.end_round();
+        } else {
+            self.ais.get_mut(player_id).update_obj_ids(&self.state);
+            self.phase = Phase::Ai;
+        }
+    }
+
+    /// Executes the next command: a recorded one, the player's end of the turn
+    /// or the AI's reply. Returns `false` if there's nothing left to execute.
+    fn step(&mut self) -> bool {
+        let command = match self.phase {
+            Phase::Done => return false,
+            Phase::Player(i) => match self.input.commands[self.round].get(i) {
+                Some(command) => command.clone(),
+                None => command::EndTurn.into(),
+            },
+            Phase::Ai => match self
+                .ais
+                .get_mut(self.state.player_id())
+                .command(&self.state)
+            {
+                Some(command) => command,
+                None => {
+                    self.end_round();
+                    return true;
+                }
+            },
+        };
+        if let Err(err) = check_strict(&self.state, &command) {
+            warn!("Bad command in the transcript: {:?}: {:?}", command, err);
+            self.phase = Phase::Done;
+            return false;
+        }
+        let action = self.do_command(&command);
+        self.view.add_action(action);
+        let is_end_turn = matches!(command, Command::EndTurn(_));
+        match self.phase {
+            Phase::Player(i) if i < self.input.commands[self.round].len() => {
+                self.phase = Phase::Player(i + 1);
+            }
+            Phase::Player(_) | Phase::Ai if is_end_turn => self.pass_turn(),
+            Phase::Player(_) | Phase::Ai | Phase::Done => {}
+        }
+        self.update_phase();
+        true
+    }
+
+    fn do_command(&mut self, command: &Command) -> Box<dyn Action> {
+        self.view.messages_map_mut().clear();
+        let mut actions = Vec::new();
+        let view = &mut self.view;
+        execute(
+            &mut self.state,
+            command,
+            &mut self.rng,
+            &mut |state, event, phase| {
+                let action =
+                    visualize(state, view, event, phase).expect("Can't visualize the event");
+                view.messages_map_mut().update(action.duration());
+                actions.push(action);
+                let sleep_duration = view.messages_map().total_duration().mul_f32(0.3);
+                actions.push(action::Sleep::new(sleep_duration).boxed());
+                view.messages_map_mut().update(sleep_duration);
+            },
+        )
+        .expect("Can't execute command");
+        action::Sequence::new(actions).boxed()
+    }
+
+    fn skip_animations(&mut self) {
+        while self.view.any_unfinished_actions() {
+            self.view.tick(SKIP_TICK);
+        }
+    }
+
+    /// Fast-forwards to the beginning of the round, restarting if it's in the past.
+    fn jump_to_round(&mut self, round: usize) -> ZResult {
+        if round < self.round || (round == self.round && self.phase != Phase::Player(0)) {
+            self.restart()?;
+        }
+        self.skip_animations();
+        while self.round < round && self.step() {
+            // Each command's animation must be over before the next one starts.
+            self.skip_animations();
+        }
+        Ok(())
+    }
+}
+
+fn start(input: &Input) -> ZResult<(State, BattleView, SimpleRng)> {
+    input.scenario.validate_layout()?;
+    let mut rng = SimpleRng::from_seed(input.seed);
+    let mut view = BattleView::new(input.scenario.map_radius)?;
+    let mut actions = Vec::new();
+    let mut state = State::new(
+        input.scenario.clone(),
+        input.prototypes.clone(),
+        input.ability_definitions.clone(),
+        input.tiles.clone(),
+        &mut rng,
+        &mut |state, event, phase| {
+            let action =
+                visualize(state, &mut view, event, phase).expect("Can't visualize the event");
+            actions.push(fork(action));
+        },
+    );
+    state.create_heroes(&input.heroes, &mut rng, &mut |state, event, phase| {
+        let action = visualize(state, &mut view, event, phase).expect("Can't visualize the event");
+        actions.push(fork(action));
+    });
+    actions.push(make_action_create_map(&state, &view)?);
+    view.add_action(action::Sequence::new(actions).boxed());
+    Ok((state, view, rng))
+}
+
+impl Screen for Replay {
+    fn update(&mut self, dtime: Duration) -> ZResult<StackCommand> {
+        self.view.tick(dtime);
+        if self.is_playing && !self.view.any_unfinished_actions() {
+            if !self.step() {
+                self.is_playing = false;
+            }
+            self.rebuild_panel_controls()?;
+        }
+        Ok(StackCommand::None)
+    }
+
+    fn draw(&self) -> ZResult {
+        self.view.draw()?;
+        self.gui.draw();
+        Ok(())
+    }
+
+    fn click(&mut self, pos: Vec2) -> ZResult<StackCommand> {
+        match self.gui.click(pos) {
+            Some(Message::Exit) => return Ok(StackCommand::Pop),
+            Some(Message::Play) => self.is_playing = true,
+            Some(Message::Pause) => self.is_playing = false,
+            Some(Message::Step) => {
+                if !self.view.any_unfinished_actions() {
+                    self.step();
+                }
+            }
+            Some(Message::JumpToRound(round)) => self.jump_to_round(round)?,
+            None => return Ok(StackCommand::None),
+        }
+        self.rebuild_panel_controls()?;
+        Ok(StackCommand::None)
+    }
+
+    fn resize(&mut self, aspect_ratio: f32) {
+        self.gui.resize_if_needed(aspect_ratio);
+    }
+
+    fn move_mouse(&mut self, point: Vec2) -> ZResult {
+        let pos = geom::point_to_hex(self.view.tile_size(), point);
+        if self.state.map().is_inboard(pos) {
+            self.view.show_current_tile_marker(pos);
+        } else {
+            self.view.hide_current_tile_marker();
+        }
+        self.gui.move_mouse(point);
+        Ok(())
+    }
+}
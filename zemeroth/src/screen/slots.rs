@@ -0,0 +1,127 @@
+use std::{sync::mpsc::Sender, time::Duration};
+
+use mq::math::Vec2;
+use ui::{self, Gui, Widget};
+
+use battle::campaign::Mode;
+
+use crate::{
+    assets, save,
+    screen::{Screen, StackCommand},
+    utils, ZResult,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SlotPurpose {
+    /// Any slot can be picked, overwriting a taken one is confirmed first.
+    NewCampaign,
+
+    Load,
+}
+
+#[derive(Clone, Debug)]
+enum Message {
+    Slot(&'static str),
+    Back,
+}
+
+fn slot_text(slot: &str, save: Option<ZResult<save::Summary>>) -> String {
+    let summary = match save {
+        None => return format!("{}: empty", slot),
+        Some(Err(_)) => return format!("{}: can't be loaded", slot),
+        Some(Ok(summary)) => summary,
+    };
+    let status = match summary.mode {
+        Mode::Won => "won",
+        Mode::Failed => "failed",
+        Mode::PreparingForBattle | Mode::ChoosingNextNode => "in progress",
+    };
+    format!(
+        "{}: {}, battles won: {}, renown: {}r",
+        slot, status, summary.battles_won, summary.renown.0
+    )
+}
+
+/// Picks a campaign save slot and reports it back.
+#[derive(Debug)]
+pub struct Slots {
+    gui: Gui<Message>,
+    sender: Sender<&'static str>,
+}
+
+impl Slots {
+    pub fn new(purpose: SlotPurpose, sender: Sender<&'static str>) -> ZResult<Self> {
+        let font = &assets::get().font;
+        let mut gui = ui::Gui::new();
+        let h = utils::line_heights().normal;
+        let mut layout = Box::new(ui::VLayout::new().stretchable(true));
+        let text = |s: &str| ui::Drawable::text(s, font.clone());
+        let spacer = || Box::new(ui::Spacer::new_vertical(h * 0.5));
+        let title = match purpose {
+            SlotPurpose::NewCampaign => "Pick a slot for the new campaign:",
+            SlotPurpose::Load => "Pick a campaign to load:",
+        };
+        layout.add(Box::new(ui::Label::new(text(title), h)?));
+        layout.add(spacer());
+        for slot in save::SLOTS {
+            let save = if save::exists(slot) {
+                Some(save::summary(slot))
+            } else {
+                None
+            };
+            let is_loadable = matches!(save, Some(Ok(_)));
+            let message = Message::Slot(slot);
+            let line = text(&slot_text(slot, save));
+            let mut button = ui::Button::new(line, h, gui.sender(), message)?.stretchable(true);
+            if purpose == SlotPurpose::Load && !is_loadable {
+                button.set_active(false);
+            }
+            layout.add(Box::new(button));
+            layout.add(Box::new(ui::Spacer::new_vertical(h / 8.0)));
+        }
+        layout.add(spacer());
+        {
+            let mut button =
+                ui::Button::new(text("back"), h, gui.sender(), Message::Back)?.stretchable(true);
+            button.stretch(layout.rect().w / 3.0);
+            button.set_stretchable(false);
+            layout.add(Box::new(button));
+        }
+        layout.stretch_to_self();
+        let layout = utils::add_offsets_and_bg_big(layout)?;
+        let anchor = ui::Anchor(ui::HAnchor::Middle, ui::VAnchor::Middle);
+        gui.add(&ui::pack(layout), anchor);
+        Ok(Self { gui, sender })
+    }
+}
+
+impl Screen for Slots {
+    fn update(&mut self, _dtime: Duration) -> ZResult<StackCommand> {
+        Ok(StackCommand::None)
+    }
+
+    fn draw(&self) -> ZResult {
+        self.gui.draw();
+        Ok(())
+    }
+
+    fn click(&mut self, pos: Vec2) -> ZResult<StackCommand> {
+        match self.gui.click(pos) {
+            Some(Message::Slot(slot)) => {
+                self.sender.send(slot).expect("Can't report back the slot");
+                Ok(StackCommand::Pop)
+            }
+            Some(Message::Back) => Ok(StackCommand::Pop),
+            None => Ok(StackCommand::None),
+        }
+    }
+
+    fn resize(&mut self, aspect_ratio: f32) {
+        self.gui.resize_if_needed(aspect_ratio);
+    }
+
+    fn move_mouse(&mut self, pos: Vec2) -> ZResult {
+        self.gui.move_mouse(pos);
+        Ok(())
+    }
+}
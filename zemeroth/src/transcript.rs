@@ -0,0 +1,86 @@
+//! Battle transcripts (`battle::game::Input`) saved to and loaded from disk.
+//!
+//! The format is picked by the file extension: `.ron` for RON, anything else is JSON.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use battle::game::Input;
+
+use crate::{error::ZError, utils, ZResult};
+
+const DIR: &str = "transcripts";
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some(extension)
+}
+
+fn is_ron(path: &Path) -> bool {
+    has_extension(path, "ron")
+}
+
+pub fn save(input: &Input, extension: &str) -> ZResult<PathBuf> {
+    let path = utils::timestamped_path(DIR, "battle", extension);
+    write(input, &path)?;
+    Ok(path)
+}
+
+pub fn write(input: &Input, path: &Path) -> ZResult {
+    let s = if is_ron(path) {
+        ron::ser::to_string_pretty(input, ron::ser::PrettyConfig::default()).map_err(|error| {
+            ZError::RonSerialize {
+                error,
+                path: path.into(),
+            }
+        })?
+    } else {
+        serde_json::to_string_pretty(input).map_err(|error| ZError::Json {
+            error,
+            path: path.into(),
+        })?
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, s)?;
+    Ok(())
+}
+
+pub fn load(path: &Path) -> ZResult<Input> {
+    let s = fs::read_to_string(path)?;
+    if is_ron(path) {
+        ron::de::from_str(&s).map_err(|e| ZError::from_ron_de_error(e, path.into()))
+    } else {
+        serde_json::from_str(&s).map_err(|error| ZError::Json {
+            error,
+            path: path.into(),
+        })
+    }
+}
+
+/// Up to `count` of the most recently modified transcripts, the newest first.
+pub fn recent(count: usize) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(DIR) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut transcripts: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let path = entry.path();
+            is_ron(&path) || has_extension(&path, "json")
+        })
+        .map(|entry| {
+            let modified = entry.metadata().and_then(|m| m.modified()).ok();
+            (modified, entry.path())
+        })
+        .collect();
+    transcripts.sort_by(|a, b| b.cmp(a));
+    transcripts
+        .into_iter()
+        .take(count)
+        .map(|(_, path)| path)
+        .collect()
+}
@@ -1,4 +1,8 @@
-use std::{sync::mpsc::Receiver, time::Duration};
+use std::{
+    path::PathBuf,
+    sync::mpsc::Receiver,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use mq::{
     camera::{set_camera, Camera2D},
@@ -95,3 +99,21 @@ pub fn get_world_mouse_pos(camera: &Camera2D) -> Vec2 {
 pub fn try_receive<Message>(opt_rx: &Option<Receiver<Message>>) -> Option<Message> {
     opt_rx.as_ref().and_then(|rx| rx.try_recv().ok())
 }
+
+/// A new file name for every call, so the older files are kept.
+///
+/// Files from the same millisecond get a numeric suffix.
+pub fn timestamped_path(dir: &str, prefix: &str, extension: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    let name = format!("{}_{}", prefix, millis);
+    let mut path = PathBuf::from(dir).join(format!("{}.{}", name, extension));
+    let mut i = 2;
+    while path.exists() {
+        path = PathBuf::from(dir).join(format!("{}_{}.{}", name, i, extension));
+        i += 1;
+    }
+    path
+}